@@ -0,0 +1,239 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use serde::Deserialize;
+
+use super::{Image, Package};
+use crate::component::Kind;
+use crate::purl::Purl;
+use crate::Error;
+
+// https://github.com/rust-secure-code/cargo-auditable/blob/master/PARSING.md
+const DEPENDENCIES_SECTIONS: &[&str] = &[".dep-v0", "__dep_v0"];
+// the same limit of rust-audit-info, protects against compression bombs
+const MAX_SIZE: u64 = 8 * 1024 * 1024;
+const CRATES_IO: &str = "crates.io";
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("cargo-auditable data", message)
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    packages: Vec<Dependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    name: String,
+    version: String,
+    source: String,
+    // runtime unless it's only used by build scripts
+    #[serde(default)]
+    kind: Option<String>,
+    // indexes in the list of packages
+    #[serde(default)]
+    dependencies: Vec<usize>,
+    #[serde(default)]
+    root: bool,
+}
+
+impl Dependency {
+    fn purl(&self) -> Purl {
+        Purl::new("cargo", &self.name).with_version(&self.version)
+    }
+}
+
+fn read_version_info(data: &[u8]) -> Result<VersionInfo, Error> {
+    let mut json = vec![];
+    ZlibDecoder::new(data)
+        .take(MAX_SIZE + 1)
+        .read_to_end(&mut json)
+        .map_err(malformed)?;
+    if json.len() as u64 > MAX_SIZE {
+        return Err(malformed(format!("more than {} bytes", MAX_SIZE)));
+    }
+
+    let info: VersionInfo = serde_json::from_slice(&json).map_err(malformed)?;
+    for dep in &info.packages {
+        if let Some(idx) = dep
+            .dependencies
+            .iter()
+            .find(|idx| **idx >= info.packages.len())
+        {
+            return Err(malformed(format!(
+                "{} depends on the missing package {}",
+                dep.name, idx
+            )));
+        }
+    }
+
+    Ok(info)
+}
+
+/// Reads the dependency tree embedded by cargo auditable: the root package is set on the
+/// executable and the crates it was built from are returned.
+pub(crate) fn analyze(
+    image: &Image,
+    executable: &mut Package,
+) -> Result<Option<Vec<Package>>, Error> {
+    let data = match image.section(DEPENDENCIES_SECTIONS)? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let info = read_version_info(data)?;
+    let purls: Vec<Purl> = info.packages.iter().map(|dep| dep.purl()).collect();
+    let dependencies = |dep: &Dependency| -> Vec<Purl> {
+        dep.dependencies
+            .iter()
+            .map(|idx| purls[*idx].clone())
+            .collect()
+    };
+
+    let mut packages = vec![];
+    for dep in &info.packages {
+        if dep.root {
+            executable.version = dep.version.clone();
+            executable.purl = Some(dep.purl());
+            executable.dependencies = dependencies(dep);
+            executable
+                .properties
+                .insert("package".to_owned(), dep.name.clone());
+            continue;
+        }
+
+        let mut package = Package {
+            kind: Kind::Dependency,
+            name: dep.name.clone(),
+            id: dep.name.clone(),
+            version: dep.version.clone(),
+            path: executable.path.clone(),
+            modified: executable.modified,
+            purl: Some(dep.purl()),
+            dependencies: dependencies(dep),
+            ..Default::default()
+        };
+        if dep.source != CRATES_IO {
+            package
+                .properties
+                .insert("source".to_owned(), dep.source.clone());
+        }
+        if let Some(kind) = &dep.kind {
+            package.properties.insert("kind".to_owned(), kind.clone());
+        }
+        packages.push(package);
+    }
+
+    Ok(Some(packages))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::binary::Format;
+
+    const VERSION_INFO: &str = r#"{"packages": [
+        {"name": "tool", "version": "0.3.0", "source": "local", "dependencies": [1, 2], "root": true},
+        {"name": "serde", "version": "1.0.193", "source": "crates.io", "dependencies": [3]},
+        {"name": "cc", "version": "1.0.83", "source": "crates.io", "kind": "build"},
+        {"name": "serde_derive", "version": "1.0.193", "source": "git"}
+    ]}"#;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn analyze_section(name: &str, data: &[u8]) -> Result<Option<(Package, Vec<Package>)>, Error> {
+        let image = Image::from_sections(Format::Elf, &[(name, 0, data)]);
+        let mut executable = Package {
+            path: "/usr/local/bin/tool".to_owned(),
+            ..Default::default()
+        };
+        let packages = analyze(&image, &mut executable)?;
+        Ok(packages.map(|packages| (executable, packages)))
+    }
+
+    #[test]
+    fn reads_dependency_tree() {
+        let data = compress(VERSION_INFO.as_bytes());
+        let (executable, packages) = analyze_section(".dep-v0", &data).unwrap().unwrap();
+
+        // the root package is the executable itself
+        assert_eq!(executable.version, "0.3.0");
+        assert_eq!(
+            executable.purl.as_ref().unwrap().to_string(),
+            "pkg:cargo/tool@0.3.0"
+        );
+        assert_eq!(executable.properties.get("package").unwrap(), "tool");
+        let dependencies: Vec<String> = executable
+            .dependencies
+            .iter()
+            .map(|purl| purl.to_string())
+            .collect();
+        assert_eq!(
+            dependencies,
+            vec!["pkg:cargo/serde@1.0.193", "pkg:cargo/cc@1.0.83"]
+        );
+
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["serde", "cc", "serde_derive"]);
+        assert!(packages
+            .iter()
+            .all(|p| p.kind == Kind::Dependency && p.path == "/usr/local/bin/tool"));
+        assert_eq!(
+            packages[0].dependencies,
+            vec![Purl::new("cargo", "serde_derive").with_version("1.0.193")]
+        );
+        assert!(packages[0].properties.is_empty());
+        assert_eq!(packages[1].properties.get("kind").unwrap(), "build");
+        assert_eq!(packages[2].properties.get("source").unwrap(), "git");
+
+        // mach-o section names
+        assert!(analyze_section("__dep_v0", &data).unwrap().is_some());
+        assert!(analyze_section(".data", &data).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_missing_dependencies() {
+        let info = VERSION_INFO.replace("[1, 2]", "[1, 4]");
+        let error = analyze_section(".dep-v0", &compress(info.as_bytes())).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("tool depends on the missing package 4"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        assert!(analyze_section(".dep-v0", VERSION_INFO.as_bytes()).is_err());
+        let data = compress(VERSION_INFO.as_bytes());
+        assert!(analyze_section(".dep-v0", &data[..data.len() / 2]).is_err());
+        assert!(analyze_section(".dep-v0", &compress(b"{\"packages\": 1}")).is_err());
+    }
+
+    #[test]
+    fn limits_decompressed_size() {
+        // valid json once decompressed, a few kilobytes before
+        let mut info = VERSION_INFO.to_owned();
+        info.push_str(&" ".repeat(MAX_SIZE as usize));
+        let data = compress(info.as_bytes());
+        assert!(data.len() < 64 * 1024);
+        let error = analyze_section(".dep-v0", &data).unwrap_err();
+        assert!(error.to_string().contains("more than"), "{}", error);
+
+        // exactly at the limit
+        let mut info = VERSION_INFO.to_owned();
+        info.push_str(&" ".repeat(MAX_SIZE as usize - VERSION_INFO.len()));
+        assert!(analyze_section(".dep-v0", &compress(info.as_bytes())).is_ok());
+    }
+}
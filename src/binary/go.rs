@@ -0,0 +1,493 @@
+use super::{Image, Package};
+use crate::component::Kind;
+use crate::purl::Purl;
+use crate::Error;
+
+// https://go.dev/src/debug/buildinfo/buildinfo.go
+const MAGIC: &[u8] = b"\xff Go buildinf:";
+const ALIGN: usize = 16;
+const HEADER_SIZE: usize = 32;
+const FLAG_BIG_ENDIAN: u8 = 0x1;
+// since go 1.18 the strings follow the header instead of being referenced by pointers
+const FLAG_INLINE: u8 = 0x2;
+const BUILDINFO_SECTIONS: &[&str] = &[".go.buildinfo", "__go_buildinfo"];
+// older toolchains and PE images don't have a dedicated section
+const DATA_SECTIONS: &[&str] = &[".data", "__data"];
+// the module information is wrapped between two 16 bytes sentinels
+const SENTINEL_SIZE: usize = 16;
+const DEVEL_VERSION: &str = "(devel)";
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("go build info", message)
+}
+
+fn find_header(data: &[u8]) -> Option<&[u8]> {
+    (0..data.len())
+        .step_by(ALIGN)
+        .map(|offset| &data[offset..])
+        .find(|candidate| candidate.len() >= HEADER_SIZE && candidate.starts_with(MAGIC))
+}
+
+// a string prefixed by its length as an unsigned LEB128 varint, returns the rest of the data too
+fn read_inline(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut len = 0usize;
+    for (idx, byte) in data.iter().enumerate().take(10) {
+        len |= ((byte & 0x7f) as usize).checked_shl(7 * idx as u32)?;
+        if byte & 0x80 == 0 {
+            let rest = &data[idx + 1..];
+            return (rest.len() >= len).then(|| rest.split_at(len));
+        }
+    }
+    None
+}
+
+fn read_pointer(data: &[u8], size: usize, big_endian: bool) -> Option<u64> {
+    let bytes = data.get(..size)?;
+    let mut value = 0u64;
+    for idx in 0..size {
+        let byte = if big_endian {
+            bytes[idx]
+        } else {
+            bytes[size - 1 - idx]
+        };
+        value = (value << 8) | byte as u64;
+    }
+    Some(value)
+}
+
+// a go string header, the address and the length of its data
+fn read_string(image: &Image, address: u64, ptr_size: usize, big_endian: bool) -> Option<&[u8]> {
+    let header = image.read(address, 2 * ptr_size as u64)?;
+    let data = read_pointer(header, ptr_size, big_endian)?;
+    let len = read_pointer(&header[ptr_size..], ptr_size, big_endian)?;
+    image.read(data, len)
+}
+
+// returns the toolchain version and the module information
+fn read_build_info(image: &Image) -> Result<Option<(String, String)>, Error> {
+    let sections: Vec<&[u8]> = match image.section(BUILDINFO_SECTIONS)? {
+        Some(section) => vec![section],
+        None => DATA_SECTIONS
+            .iter()
+            .filter_map(|name| image.section(&[name]).transpose())
+            .collect::<Result<_, _>>()?,
+    };
+    let header = match sections.into_iter().find_map(find_header) {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    let ptr_size = header[14] as usize;
+    let flags = header[15];
+    let (version, modinfo) = if flags & FLAG_INLINE != 0 {
+        let (version, rest) =
+            read_inline(&header[HEADER_SIZE..]).ok_or_else(|| malformed("truncated version"))?;
+        let (modinfo, _) = read_inline(rest).ok_or_else(|| malformed("truncated module info"))?;
+        (version, modinfo)
+    } else {
+        if ptr_size != 4 && ptr_size != 8 {
+            return Err(malformed(format!("invalid pointer size {}", ptr_size)));
+        }
+        let big_endian = flags & FLAG_BIG_ENDIAN != 0;
+        let string_at = |offset: usize| {
+            read_pointer(&header[offset..], ptr_size, big_endian)
+                .and_then(|address| read_string(image, address, ptr_size, big_endian))
+        };
+        (
+            string_at(16).ok_or_else(|| malformed("version out of bounds"))?,
+            string_at(16 + ptr_size).ok_or_else(|| malformed("module info out of bounds"))?,
+        )
+    };
+
+    let len = modinfo.len();
+    let modinfo = if len > 2 * SENTINEL_SIZE && modinfo[len - SENTINEL_SIZE - 1] == b'\n' {
+        &modinfo[SENTINEL_SIZE..len - SENTINEL_SIZE]
+    } else {
+        // built without module support
+        &[]
+    };
+
+    Ok(Some((
+        String::from_utf8_lossy(version).into_owned(),
+        String::from_utf8_lossy(modinfo).into_owned(),
+    )))
+}
+
+// github.com/spf13/cobra is pkg:golang/github.com/spf13/cobra
+fn purl(path: &str, version: &str) -> Purl {
+    match path.rsplit_once('/') {
+        Some((namespace, name)) => Purl::new("golang", name).with_namespace(namespace),
+        None => Purl::new("golang", path),
+    }
+    .with_version(version)
+}
+
+// build settings with special characters are quoted
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_owned(),
+    }
+}
+
+struct Module {
+    path: String,
+    version: String,
+    sum: String,
+    replaces: Option<String>,
+    directory: Option<String>,
+}
+
+impl Module {
+    fn parse(fields: &[&str]) -> Self {
+        let field = |idx: usize| fields.get(idx).copied().unwrap_or_default().to_owned();
+        Self {
+            path: field(1),
+            version: field(2),
+            sum: field(3),
+            replaces: None,
+            directory: None,
+        }
+    }
+
+    fn version(&self) -> &str {
+        if self.version == DEVEL_VERSION {
+            ""
+        } else {
+            &self.version
+        }
+    }
+}
+
+/// Reads the build information embedded by the go toolchain: the main module is set on the
+/// executable and the modules it depends on are returned.
+pub(crate) fn analyze(
+    image: &Image,
+    executable: &mut Package,
+) -> Result<Option<Vec<Package>>, Error> {
+    let (toolchain, modinfo) = match read_build_info(image)? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+
+    let mut main = None;
+    let mut deps: Vec<Module> = vec![];
+    for line in modinfo.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields[0] {
+            "path" if fields.len() > 1 => {
+                executable
+                    .properties
+                    .insert("package".to_owned(), fields[1].to_owned());
+            }
+            "mod" => main = Some(Module::parse(&fields)),
+            "dep" => deps.push(Module::parse(&fields)),
+            // the previous module is replaced by this one
+            "=>" => {
+                let replaced = match deps.last_mut() {
+                    Some(dep) => dep,
+                    None => match main.as_mut() {
+                        Some(main) => main,
+                        None => continue,
+                    },
+                };
+                let replacement = Module::parse(&fields);
+                // local folders have no version, the original module is kept
+                if replacement.version.is_empty() {
+                    replaced.directory = Some(replacement.path);
+                    continue;
+                }
+                let original = match replaced.version.as_str() {
+                    "" => replaced.path.clone(),
+                    version => format!("{}@{}", replaced.path, version),
+                };
+                *replaced = Module {
+                    replaces: Some(original),
+                    ..replacement
+                };
+            }
+            "build" if fields.len() > 1 => {
+                if let Some((key, value)) = fields[1].split_once('=') {
+                    executable.properties.insert(
+                        format!("build.{}", key.trim_start_matches('-')),
+                        unquote(value),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    executable.properties.insert("go".to_owned(), toolchain);
+    if let Some(main) = &main {
+        if !main.version().is_empty() {
+            executable.version = main.version().to_owned();
+        }
+        executable.purl = Some(purl(&main.path, main.version()));
+        executable
+            .properties
+            .insert("module".to_owned(), main.path.clone());
+        if !main.sum.is_empty() {
+            executable
+                .properties
+                .insert("sum".to_owned(), main.sum.clone());
+        }
+    }
+
+    let packages: Vec<Package> = deps
+        .into_iter()
+        .map(|dep| {
+            let mut package = Package {
+                kind: Kind::Dependency,
+                name: dep.path.clone(),
+                id: dep.path.clone(),
+                version: dep.version().to_owned(),
+                path: executable.path.clone(),
+                modified: executable.modified,
+                purl: Some(purl(&dep.path, dep.version())),
+                ..Default::default()
+            };
+            if !dep.sum.is_empty() {
+                package.properties.insert("sum".to_owned(), dep.sum);
+            }
+            if let Some(replaces) = dep.replaces {
+                package.properties.insert("replaces".to_owned(), replaces);
+            }
+            if let Some(directory) = dep.directory {
+                package.properties.insert("directory".to_owned(), directory);
+            }
+            package
+        })
+        .collect();
+    // the build info lists every module without the dependency graph
+    executable.dependencies = packages.iter().filter_map(|pkg| pkg.purl.clone()).collect();
+
+    Ok(Some(packages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::Format;
+
+    const SENTINEL: &[u8] = b"0w\xaf\x0c\x92t\x08\x02A\xe1\xc1\x07\xe6\xd6\x18\xe6";
+    const MODINFO: &str = "path\texample.com/tool/cmd/tool
+mod\texample.com/tool\tv1.2.3\th1:main=
+dep\tgithub.com/spf13/cobra\tv1.7.0\th1:cobra=
+dep\tgolang.org/x/sys\tv0.1.0\th1:sys=
+=>\tgolang.org/x/sys\tv0.2.0\th1:sys2=
+dep\texample.com/local\tv0.0.0
+=>\t../local
+build\t-compiler=gc
+build\tCGO_ENABLED=1
+build\t-ldflags=\"-X main.version=\\\"1.2.3\\\"\"
+";
+
+    fn header(ptr_size: u8, flags: u8) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&[ptr_size, flags]);
+        header.resize(HEADER_SIZE, 0);
+        header
+    }
+
+    fn wrapped(modinfo: &str) -> Vec<u8> {
+        [SENTINEL, modinfo.as_bytes(), SENTINEL].concat()
+    }
+
+    fn put_inline(data: &mut Vec<u8>, s: &[u8]) {
+        let mut len = s.len();
+        while len >= 0x80 {
+            data.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        data.push(len as u8);
+        data.extend_from_slice(s);
+    }
+
+    fn inline(version: &str, modinfo: &[u8]) -> Vec<u8> {
+        let mut data = header(8, FLAG_INLINE);
+        put_inline(&mut data, version.as_bytes());
+        put_inline(&mut data, modinfo);
+        data
+    }
+
+    // the header references go strings of a .rodata section at 0x1000
+    fn pointers(modinfo: &[u8], ptr_size: usize, big_endian: bool) -> (Vec<u8>, Vec<u8>) {
+        let put = |data: &mut Vec<u8>, value: usize| {
+            let bytes = (value as u64).to_be_bytes();
+            let mut bytes = bytes[8 - ptr_size..].to_vec();
+            if !big_endian {
+                bytes.reverse();
+            }
+            data.extend_from_slice(&bytes);
+        };
+
+        let version = b"go1.17.13";
+        let strings = 0x1000 + 4 * ptr_size;
+        let mut rodata = vec![];
+        put(&mut rodata, strings);
+        put(&mut rodata, version.len());
+        put(&mut rodata, strings + version.len());
+        put(&mut rodata, modinfo.len());
+        rodata.extend_from_slice(version);
+        rodata.extend_from_slice(modinfo);
+
+        let flags = if big_endian { FLAG_BIG_ENDIAN } else { 0 };
+        let mut header = header(ptr_size as u8, flags);
+        header.truncate(16);
+        put(&mut header, 0x1000);
+        put(&mut header, 0x1000 + 2 * ptr_size);
+        header.resize(HEADER_SIZE, 0);
+        (header, rodata)
+    }
+
+    fn image(sections: &[(&str, u64, &[u8])]) -> Image {
+        Image::from_sections(Format::Elf, sections)
+    }
+
+    fn analyze_image(image: &Image) -> Result<Option<(Package, Vec<Package>)>, Error> {
+        let mut executable = Package {
+            path: "/usr/local/bin/tool".to_owned(),
+            ..Default::default()
+        };
+        let packages = analyze(image, &mut executable)?;
+        Ok(packages.map(|packages| (executable, packages)))
+    }
+
+    #[test]
+    fn reads_inline_build_info() {
+        let data = inline("go1.21.5", &wrapped(MODINFO));
+        let (executable, packages) = analyze_image(&image(&[(".go.buildinfo", 0, &data)]))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(executable.version, "v1.2.3");
+        assert_eq!(
+            executable.purl.as_ref().unwrap().to_string(),
+            "pkg:golang/example.com/tool@v1.2.3"
+        );
+        let properties: Vec<(&str, &str)> = executable
+            .properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            properties,
+            vec![
+                ("build.CGO_ENABLED", "1"),
+                ("build.compiler", "gc"),
+                ("build.ldflags", "-X main.version=\"1.2.3\""),
+                ("go", "go1.21.5"),
+                ("module", "example.com/tool"),
+                ("package", "example.com/tool/cmd/tool"),
+                ("sum", "h1:main="),
+            ]
+        );
+
+        let purls: Vec<String> = packages
+            .iter()
+            .map(|package| package.purl.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            purls,
+            vec![
+                "pkg:golang/github.com/spf13/cobra@v1.7.0",
+                "pkg:golang/golang.org/x/sys@v0.2.0",
+                "pkg:golang/example.com/local@v0.0.0",
+            ]
+        );
+        assert_eq!(
+            executable.dependencies,
+            packages
+                .iter()
+                .filter_map(|package| package.purl.clone())
+                .collect::<Vec<Purl>>()
+        );
+        assert!(packages.iter().all(
+            |package| package.kind == Kind::Dependency && package.path == "/usr/local/bin/tool"
+        ));
+
+        let sys = &packages[1];
+        assert_eq!(sys.properties.get("sum").unwrap(), "h1:sys2=");
+        assert_eq!(
+            sys.properties.get("replaces").unwrap(),
+            "golang.org/x/sys@v0.1.0"
+        );
+        let local = &packages[2];
+        assert_eq!(local.version, "v0.0.0");
+        assert_eq!(local.properties.get("directory").unwrap(), "../local");
+        assert!(!local.properties.contains_key("replaces"));
+    }
+
+    #[test]
+    fn reads_pointer_build_info() {
+        let modinfo = wrapped("path\texample.com/tool\nmod\texample.com/tool\t(devel)\t\n");
+        for (ptr_size, big_endian) in [(8, false), (4, true)] {
+            let (header, rodata) = pointers(&modinfo, ptr_size, big_endian);
+            // without a dedicated section the header is aligned in the data section
+            let data = [vec![0; ALIGN], header].concat();
+            let image = image(&[(".rodata", 0x1000, &rodata), (".data", 0x2000, &data)]);
+            let (executable, packages) = analyze_image(&image).unwrap().unwrap();
+
+            assert!(packages.is_empty());
+            assert_eq!(executable.version, "");
+            assert_eq!(
+                executable.purl.as_ref().unwrap().to_string(),
+                "pkg:golang/example.com/tool"
+            );
+            assert_eq!(executable.properties.get("go").unwrap(), "go1.17.13");
+            assert!(!executable.properties.contains_key("sum"));
+        }
+    }
+
+    #[test]
+    fn strips_sentinels() {
+        // built without module support, the module info is empty
+        let data = inline("go1.21.5", MODINFO.as_bytes());
+        let (executable, packages) = analyze_image(&image(&[(".go.buildinfo", 0, &data)]))
+            .unwrap()
+            .unwrap();
+        assert!(packages.is_empty());
+        assert!(executable.purl.is_none());
+        assert_eq!(executable.properties.get("go").unwrap(), "go1.21.5");
+
+        let info = read_build_info(&image(&[(
+            ".go.buildinfo",
+            0,
+            &inline("go1.21.5", &wrapped(MODINFO)),
+        )]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(info.1, MODINFO);
+    }
+
+    #[test]
+    fn rejects_truncated_build_info() {
+        let data = inline("go1.21.5", &wrapped(MODINFO));
+        let truncated = &data[..data.len() - 1];
+        assert!(analyze_image(&image(&[(".go.buildinfo", 0, truncated)])).is_err());
+        let truncated = &data[..HEADER_SIZE + 4];
+        assert!(analyze_image(&image(&[(".go.buildinfo", 0, truncated)])).is_err());
+        // too short for a header
+        let truncated = &data[..HEADER_SIZE - 1];
+        assert!(analyze_image(&image(&[(".go.buildinfo", 0, truncated)]))
+            .unwrap()
+            .is_none());
+
+        // the strings are out of the image
+        let (mut header, rodata) = pointers(&wrapped(MODINFO), 8, false);
+        let truncated = &rodata[..rodata.len() - 1];
+        let sections = [
+            (".rodata", 0x1000, truncated),
+            (".go.buildinfo", 0x2000, &header[..]),
+        ];
+        assert!(analyze_image(&image(&sections)).is_err());
+
+        header[14] = 3;
+        let sections = [
+            (".rodata", 0x1000, &rodata[..]),
+            (".go.buildinfo", 0x2000, &header[..]),
+        ];
+        assert!(analyze_image(&image(&sections)).is_err());
+    }
+}
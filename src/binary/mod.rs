@@ -0,0 +1,734 @@
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::collector::{self, Collected, Owners};
+use crate::component::{
+    ComponentTrait, Kind, OwnedFile, Relationship, RelationshipKind, EMBEDDED_IN_PROPERTY,
+};
+use crate::lang::SearchPath;
+use crate::purl::Purl;
+use crate::{elf, macho, pe, Error};
+
+mod cargo;
+mod go;
+
+// where executables that are not installed by a package manager usually end up, C:\Tools included
+const DEFAULT_PATHS: &[&str] = &[
+    "/usr/local/bin",
+    "/usr/local/sbin",
+    "/usr/bin",
+    "/usr/sbin",
+    "/bin",
+    "/sbin",
+    "/opt",
+    "/root/bin",
+    "/root/.local/bin",
+    "/Program Files",
+    "/Program Files (x86)",
+    "/Tools",
+];
+// the bin and .local/bin folders of each user are searched too
+const HOME_PATHS: &[&str] = &["/home", "/Users"];
+const HOME_BIN_DIRS: &[&str] = &["bin", ".local/bin"];
+// each one reads the packages embedded by a toolchain
+type Analyzer = fn(&Image, &mut Package) -> Result<Option<Vec<Package>>, Error>;
+const ANALYZERS: &[Analyzer] = &[go::analyze, cargo::analyze];
+// enough for the signature of every format and the PE header offset
+const HEADER_SIZE: u64 = 4096;
+const INFO_PLIST_SECTION: &str = "__info_plist";
+
+/// A section of an executable image, its address once loaded and where its data is in the file.
+/// Sections without data in the file are empty.
+pub(crate) struct SectionHeader {
+    pub name: String,
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Reads up to size bytes at an offset of a file, less if the file ends before.
+pub(crate) fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    size: u64,
+) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![];
+    reader.take(size).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Anything an image can be read from, a file or its content.
+pub(crate) trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Elf,
+    Pe,
+    MachO,
+}
+
+impl Format {
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if elf::is_elf(header) {
+            Some(Self::Elf)
+        } else if pe::is_pe(header) {
+            Some(Self::Pe)
+        } else if macho::is_macho(header) {
+            Some(Self::MachO)
+        } else {
+            None
+        }
+    }
+
+    // libraries, object files and core dumps share the same formats, position independent
+    // executables and shared libraries can only be told apart by their name
+    fn is_executable(&self, header: &[u8], name: &str) -> bool {
+        match self {
+            Self::Elf => {
+                elf::is_executable(header) && !(name.ends_with(".so") || name.contains(".so."))
+            }
+            Self::Pe => pe::is_executable(header),
+            Self::MachO => macho::is_executable(header),
+        }
+    }
+}
+
+/// The sections of an executable, regardless of its format. Only the headers are read when it's
+/// opened, the data of a section is read the first time it's needed.
+pub(crate) struct Image {
+    pub format: Format,
+    headers: Vec<SectionHeader>,
+    data: Vec<OnceCell<Vec<u8>>>,
+    source: RefCell<Box<dyn Source>>,
+}
+
+impl Image {
+    pub fn open(format: Format, mut source: Box<dyn Source>) -> Result<Self, Error> {
+        let headers = match format {
+            Format::Elf => elf::section_headers(&mut source)?,
+            Format::Pe => pe::section_headers(&mut source)?,
+            Format::MachO => macho::section_headers(&mut source)?,
+        };
+
+        Ok(Self {
+            format,
+            data: headers.iter().map(|_| OnceCell::new()).collect(),
+            headers,
+            source: RefCell::new(source),
+        })
+    }
+
+    fn load(&self, idx: usize) -> Result<&[u8], Error> {
+        if let Some(data) = self.data[idx].get() {
+            return Ok(data);
+        }
+
+        let header = &self.headers[idx];
+        let data = read_at(&mut *self.source.borrow_mut(), header.offset, header.size)
+            .map_err(|e| Error::parse("executable", e))?;
+        if data.len() as u64 != header.size {
+            return Err(Error::parse(
+                "executable",
+                format!("section {} is out of bounds", header.name),
+            ));
+        }
+        Ok(self.data[idx].get_or_init(|| data))
+    }
+
+    /// Returns the data of the first of these sections found in the image.
+    pub fn section(&self, names: &[&str]) -> Result<Option<&[u8]>, Error> {
+        for name in names {
+            if let Some(idx) = self
+                .headers
+                .iter()
+                .position(|header| header.name == *name && header.size > 0)
+            {
+                return self.load(idx).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads data at an address of the loaded image, only the section holding it is read.
+    pub fn read(&self, address: u64, size: u64) -> Option<&[u8]> {
+        self.headers.iter().enumerate().find_map(|(idx, header)| {
+            let start = address.checked_sub(header.address)?;
+            let end = start.checked_add(size)?;
+            if end > header.size {
+                return None;
+            }
+            match self.load(idx) {
+                Ok(data) => data.get(start as usize..end as usize),
+                Err(e) => {
+                    log::debug!("{}", e);
+                    None
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+impl Image {
+    /// An image made of these sections (name, address and data), one after the other in memory.
+    pub fn from_sections(format: Format, sections: &[(&str, u64, &[u8])]) -> Self {
+        let mut data = vec![];
+        let mut headers = vec![];
+        for (name, address, section) in sections {
+            headers.push(SectionHeader {
+                name: name.to_string(),
+                address: *address,
+                offset: data.len() as u64,
+                size: section.len() as u64,
+            });
+            data.extend_from_slice(section);
+        }
+
+        Self {
+            format,
+            data: headers.iter().map(|_| OnceCell::new()).collect(),
+            headers,
+            source: RefCell::new(Box::new(io::Cursor::new(data))),
+        }
+    }
+}
+
+/// A component found in an executable, either the executable itself or a package linked into it.
+#[derive(Debug, Default)]
+pub(crate) struct Package {
+    kind: Kind,
+    name: String,
+    id: String,
+    version: String,
+    path: String,
+    modified: DateTime<Utc>,
+    publishers: Vec<String>,
+    purl: Option<Purl>,
+    license: Option<String>,
+    properties: BTreeMap<String, String>,
+    dependencies: Vec<Purl>,
+    // the package that installed the executable
+    provider: Option<Purl>,
+    files: Vec<OwnedFile>,
+}
+
+impl Package {
+    fn set_version_info(&mut self, info: &pe::VersionInfo) {
+        if let Some(version) = info.product_version() {
+            self.version = version;
+        }
+        if let Some(company) = info.company_name() {
+            self.publishers = vec![company.to_owned()];
+        }
+        if let Some(product) = info.product_name() {
+            self.properties
+                .insert("product".to_owned(), product.to_owned());
+        }
+        if let Some(description) = info.file_description() {
+            self.properties
+                .insert("description".to_owned(), description.to_owned());
+        }
+        if let Some(file_version) = info.file_version() {
+            self.properties
+                .insert("file_version".to_owned(), file_version);
+        }
+        if let Some(filename) = info.original_filename() {
+            self.properties
+                .insert("original_filename".to_owned(), filename.to_owned());
+        }
+    }
+
+    // command line tools can embed the Info.plist of an application bundle
+    fn set_info_plist(&mut self, plist: &str) {
+        let version = plist_string(plist, "CFBundleShortVersionString")
+            .or_else(|| plist_string(plist, "CFBundleVersion"));
+        if let Some(version) = version {
+            self.version = version;
+        }
+        if let Some(identifier) = plist_string(plist, "CFBundleIdentifier") {
+            self.properties.insert("identifier".to_owned(), identifier);
+        }
+    }
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        self.purl.clone()
+    }
+
+    fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        let mut relationships: Vec<Relationship> = self
+            .dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect();
+        if let Some(provider) = &self.provider {
+            relationships.push(Relationship::new(
+                RelationshipKind::ProvidedBy,
+                provider.clone(),
+            ));
+        }
+        relationships
+    }
+
+    fn files(&self) -> Vec<OwnedFile> {
+        self.files.clone()
+    }
+}
+
+// the <string> value that follows a <key> of an XML property list
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let (_, rest) = plist.split_once(&format!("<key>{}</key>", key))?;
+    let value = rest.trim_start().strip_prefix("<string>")?;
+    let (value, _) = value.split_once("</string>")?;
+    Some(value.trim().to_owned()).filter(|value| !value.is_empty())
+}
+
+fn read_header(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut header = vec![];
+    File::open(path)?
+        .take(HEADER_SIZE)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Reports the executables that are not installed by a package manager, and the ones that embed
+/// the list of packages they were built from along with these packages.
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    paths: Vec<String>,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            paths: vec![],
+        }
+    }
+
+    fn default_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = DEFAULT_PATHS.iter().map(|path| path.to_string()).collect();
+        for home in HOME_PATHS {
+            let users = match fs::read_dir(self.ctx.path(home)) {
+                Ok(users) => users,
+                Err(_) => continue,
+            };
+            for user in users.flatten() {
+                for dir in HOME_BIN_DIRS {
+                    paths.push(format!(
+                        "{}/{}/{}",
+                        home,
+                        user.file_name().to_string_lossy(),
+                        dir
+                    ));
+                }
+            }
+        }
+        paths
+    }
+
+    // the databases list the paths the packages were built with, such as /bin/ls for /usr/bin/ls
+    // on merged /usr systems, so their folders are resolved
+    fn owned_on_host(&self, owned: &Owners) -> HashMap<PathBuf, Option<Purl>> {
+        let mut dirs: HashMap<&Path, PathBuf> = HashMap::new();
+        owned
+            .iter()
+            .filter_map(|(file, owner)| {
+                let file = Path::new(file);
+                let dir = dirs.entry(file.parent()?).or_insert_with_key(|dir| {
+                    let on_host = self.ctx.path(dir);
+                    fs::canonicalize(&on_host).unwrap_or(on_host)
+                });
+                Some((dir.join(file.file_name()?), owner.clone()))
+            })
+            .collect()
+    }
+
+    fn collect_executable(
+        &self,
+        path: &str,
+        on_host: &Path,
+        format: Format,
+        owner: Option<&Option<Purl>>,
+        collected: &mut Collected,
+    ) -> Result<(), Error> {
+        let file = File::open(on_host).map_err(|e| Error::io(on_host, e))?;
+        let meta = file.metadata().map_err(|e| Error::io(on_host, e))?;
+        let image =
+            Image::open(format, Box::new(file)).map_err(|e| Error::parse(on_host.display(), e))?;
+
+        let mut executable = Package {
+            name: Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            id: path.to_owned(),
+            path: path.to_owned(),
+            modified: meta.modified().map(DateTime::from).unwrap_or_default(),
+            ..Default::default()
+        };
+        match image.format {
+            Format::Pe => match pe::read_version_info(on_host) {
+                Ok(info) => executable.set_version_info(&info),
+                Err(e) => log::debug!("{}", e),
+            },
+            Format::MachO => {
+                let plist = image
+                    .section(&[INFO_PLIST_SECTION])
+                    .map_err(|e| Error::parse(on_host.display(), e))?;
+                if let Some(plist) = plist {
+                    executable.set_info_plist(&String::from_utf8_lossy(plist));
+                }
+            }
+            Format::Elf => {}
+        }
+
+        let mut packages = None;
+        for analyze in ANALYZERS {
+            packages =
+                analyze(&image, &mut executable).map_err(|e| Error::parse(on_host.display(), e))?;
+            if packages.is_some() {
+                break;
+            }
+        }
+        // the packages linked into an executable are not in the database of its package
+        let packages = match packages {
+            Some(packages) => packages,
+            None if owner.is_some() => return Ok(()),
+            None => vec![],
+        };
+
+        let embedded_in = match &executable.purl {
+            Some(purl) => purl.to_string(),
+            None => path.to_owned(),
+        };
+        executable.provider = owner.cloned().flatten();
+        // the hashes are computed with the ones of the other components, if requested
+        executable.files = vec![OwnedFile {
+            path: path.to_owned(),
+            size: Some(meta.len()),
+            hashes: BTreeMap::new(),
+        }];
+        collected.push(executable);
+        for mut package in packages {
+            package
+                .properties
+                .insert(EMBEDDED_IN_PROPERTY.to_owned(), embedded_in.clone());
+            collected.push(package);
+        }
+
+        Ok(())
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        let defaults = self.default_paths();
+        let defaults: Vec<&str> = defaults.iter().map(|path| path.as_str()).collect();
+        self.paths = self.ctx.search_paths(&defaults);
+        if self.paths.is_empty() {
+            return Err(Error::unsupported("no executable search path found"));
+        }
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        self.collect_unowned(&Owners::new())
+    }
+
+    fn collect_unowned(&self, owned: &Owners) -> Result<Collected, Error> {
+        log::info!("searching executables in {} ...", self.paths.join(", "));
+
+        let mut collected = Collected::new("binaries");
+        let owned = self.owned_on_host(owned);
+        // /bin and /usr/bin are the same folder on merged /usr systems
+        let mut seen = HashSet::new();
+
+        for path in &self.paths {
+            let search = SearchPath::new(&self.ctx, path);
+            for entry in search.entries() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        continue;
+                    }
+                };
+                let executable = search.inspected(entry.path());
+                if entry.file_type().is_dir() || !self.ctx.is_included(&executable) {
+                    continue;
+                }
+                // bin folders often link to executables installed somewhere else, such as /opt
+                let on_host = if entry.path_is_symlink() {
+                    self.ctx.path(&executable)
+                } else {
+                    entry.path().to_path_buf()
+                };
+                if !on_host.is_file() {
+                    continue;
+                }
+                let canonical = fs::canonicalize(&on_host).unwrap_or(on_host.clone());
+                if !seen.insert(canonical.clone()) {
+                    continue;
+                }
+
+                let header = match read_header(&on_host) {
+                    Ok(header) => header,
+                    Err(e) => {
+                        log::debug!("{}: {}", on_host.display(), e);
+                        continue;
+                    }
+                };
+                let format = match Format::detect(&header) {
+                    Some(format)
+                        if format.is_executable(&header, &entry.file_name().to_string_lossy()) =>
+                    {
+                        format
+                    }
+                    _ => continue,
+                };
+
+                // either the link or its target can be owned
+                let link = entry
+                    .path()
+                    .parent()
+                    .and_then(|dir| fs::canonicalize(dir).ok())
+                    .map(|dir| dir.join(entry.file_name()));
+                let owner = owned
+                    .get(&canonical)
+                    .or_else(|| link.and_then(|link| owned.get(&link)));
+
+                if let Err(e) =
+                    self.collect_executable(&executable, &on_host, format, owner, &mut collected)
+                {
+                    collected.warn(e);
+                }
+            }
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::Collector as _;
+
+    const ET_EXEC: u16 = 2;
+    const ET_DYN: u16 = 3;
+
+    // a 64 bits little endian ELF header without sections
+    fn elf(e_type: u16) -> Vec<u8> {
+        let mut data = vec![0; 64];
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = 2;
+        data[5] = 1;
+        data[16..18].copy_from_slice(&e_type.to_le_bytes());
+        data
+    }
+
+    fn write(root: &Path, path: &str, data: &[u8]) {
+        let path = root.join(path.trim_start_matches('/'));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn root() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        write(root.path(), "/usr/local/bin/tool", &elf(ET_EXEC));
+        write(root.path(), "/usr/local/bin/pie", &elf(ET_DYN));
+        write(root.path(), "/usr/local/bin/owned", &elf(ET_EXEC));
+        write(
+            root.path(),
+            "/usr/local/bin/script",
+            b"#!/bin/sh\necho hello\n",
+        );
+        write(root.path(), "/usr/local/bin/libtool.so", &elf(ET_DYN));
+        write(root.path(), "/usr/local/bin/libtool.so.1", &elf(ET_DYN));
+        write(root.path(), "/usr/local/bin/sub/deep/nested", &elf(ET_EXEC));
+        root
+    }
+
+    fn context(root: &tempfile::TempDir) -> collector::Context {
+        collector::Context::new(Some(root.path().to_path_buf()))
+            .unwrap()
+            .with_paths(&["/usr/local/bin".to_owned()])
+    }
+
+    fn collect(ctx: &collector::Context, owned: &Owners) -> Vec<String> {
+        let mut collector = Collector::new(ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect_unowned(owned).unwrap();
+        assert!(collected.diagnostics.is_empty());
+
+        let mut paths: Vec<String> = collected
+            .components
+            .iter()
+            .map(|comp| comp.path().to_owned())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn skips_owned_files_and_libraries() {
+        let root = root();
+        let ctx = context(&root);
+        let owned = Owners::from([(
+            "/usr/local/bin/owned".to_owned(),
+            Some(Purl::new("deb", "owned").with_version("1.0")),
+        )]);
+
+        assert_eq!(
+            collect(&ctx, &owned),
+            vec![
+                "/usr/local/bin/pie",
+                "/usr/local/bin/sub/deep/nested",
+                "/usr/local/bin/tool",
+            ]
+        );
+
+        // the hashes are left to the hashes module
+        let mut collector = Collector::new(&ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect_unowned(&owned).unwrap();
+        let files = collected.components[0].files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].size, Some(64));
+        assert!(files[0].hashes.is_empty());
+    }
+
+    #[test]
+    fn applies_filters_and_max_depth() {
+        let root = root();
+        let owned = Owners::new();
+
+        let ctx = context(&root)
+            .with_filters(&["/usr/local/bin/sub/**".to_owned()], &[])
+            .unwrap();
+        assert_eq!(
+            collect(&ctx, &owned),
+            vec!["/usr/local/bin/sub/deep/nested"]
+        );
+
+        let ctx = context(&root)
+            .with_filters(
+                &["/usr/local/bin/*".to_owned()],
+                &["**/pie".to_owned(), "**/owned".to_owned()],
+            )
+            .unwrap();
+        assert_eq!(collect(&ctx, &owned), vec!["/usr/local/bin/tool"]);
+
+        // an excluded folder isn't searched
+        let ctx = context(&root)
+            .with_filters(&[], &["/usr/local/bin/sub".to_owned()])
+            .unwrap();
+        assert!(!collect(&ctx, &owned).contains(&"/usr/local/bin/sub/deep/nested".to_owned()));
+
+        let ctx = context(&root).with_max_depth(2);
+        assert_eq!(
+            collect(&ctx, &owned),
+            vec![
+                "/usr/local/bin/owned",
+                "/usr/local/bin/pie",
+                "/usr/local/bin/tool",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_symlinks_inside_root() {
+        use std::os::unix::fs::symlink;
+
+        let root = root();
+        let outside = tempfile::tempdir().unwrap();
+        write(outside.path(), "/host-tool", &elf(ET_EXEC));
+        write(root.path(), "/opt/app/bin/app", &elf(ET_EXEC));
+        write(root.path(), "/opt/other/bin/other", &elf(ET_EXEC));
+        symlink("/opt/app/bin/app", root.path().join("usr/local/bin/app")).unwrap();
+        symlink(
+            "../../../opt/other/bin/other",
+            root.path().join("usr/local/bin/other"),
+        )
+        .unwrap();
+        // absolute links are resolved in the root and not on the host
+        symlink(
+            outside.path().join("host-tool"),
+            root.path().join("usr/local/bin/host-tool"),
+        )
+        .unwrap();
+
+        let ctx = context(&root).with_max_depth(1);
+        assert_eq!(
+            collect(&ctx, &Owners::new()),
+            vec![
+                "/usr/local/bin/app",
+                "/usr/local/bin/other",
+                "/usr/local/bin/owned",
+                "/usr/local/bin/pie",
+                "/usr/local/bin/tool",
+            ]
+        );
+
+        // the target of a link is owned
+        let owned = Owners::from([("/opt/app/bin/app".to_owned(), None)]);
+        assert!(!collect(&ctx, &owned).contains(&"/usr/local/bin/app".to_owned()));
+
+        // a target found in several search paths is only reported once
+        let ctx = ctx.with_paths(&["/usr/local/bin".to_owned(), "/opt/app/bin".to_owned()]);
+        let paths = collect(&ctx, &Owners::new());
+        assert!(paths.contains(&"/usr/local/bin/app".to_owned()));
+        assert!(!paths.contains(&"/opt/app/bin/app".to_owned()));
+    }
+}
@@ -1,35 +1,533 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
 use crate::component::ComponentTrait;
+use crate::purl::Purl;
+use crate::sbom::Diagnostic;
 use crate::Error;
 
+// same limit used by the linux kernel
+const MAX_SYMLINK_HOPS: usize = 40;
+// returned like ELOOP for the links that can't be resolved, a path with a NUL byte is rejected
+// before reaching the filesystem so it can't be opened
+const UNRESOLVED_LINK: &str = "\0";
+
+/// Files installed by the package managers and the package that owns each of them, unknown for
+/// the directories shared by several packages.
+pub(crate) type Owners = HashMap<String, Option<Purl>>;
+
 pub(crate) trait Collector {
     fn setup(&mut self) -> Result<(), Error>;
-    fn collect(&self) -> Result<Vec<Box<dyn ComponentTrait>>, Error>;
-    fn collect_from_json(&self, json: &str) -> Result<Vec<Box<dyn ComponentTrait>>, Error>;
+    fn collect(&self) -> Result<Collected, Error>;
+    fn collect_from_json(&self, json: &str) -> Result<Collected, Error>;
+
+    /// Collects the components, the files installed by the packages that the previous collectors
+    /// found are passed to the ones that search the filesystem.
+    fn collect_unowned(&self, _owned: &Owners) -> Result<Collected, Error> {
+        self.collect()
+    }
 }
 
-#[cfg(target_os = "macos")]
-pub(crate) fn get() -> Result<Box<dyn Collector>, Error> {
-    use crate::macos;
+/// Components found by a collector along with the errors that prevented finding the others.
+pub(crate) struct Collected {
+    collector: &'static str,
+    pub components: Vec<Box<dyn ComponentTrait>>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub owned: Owners,
+}
+
+impl Collected {
+    pub fn new(collector: &'static str) -> Self {
+        Self {
+            collector,
+            components: vec![],
+            diagnostics: vec![],
+            owned: Owners::new(),
+        }
+    }
+
+    pub fn push<C: ComponentTrait + 'static>(&mut self, comp: C) {
+        self.components.push(Box::new(comp));
+    }
+
+    /// Records a non-fatal error.
+    pub fn warn(&mut self, error: Error) {
+        log::warn!("{}: {}", self.collector, &error);
+        self.diagnostics.push(Diagnostic {
+            collector: self.collector.to_owned(),
+            message: error.to_string(),
+        });
+    }
+
+    /// Records the files installed by a package.
+    pub fn own(&mut self, files: &[String], package: Option<Purl>) {
+        for file in files {
+            self.owned
+                .entry(file.clone())
+                .and_modify(|owner| {
+                    if *owner != package {
+                        *owner = None;
+                    }
+                })
+                .or_insert_with(|| package.clone());
+        }
+    }
+
+    pub fn append(&mut self, mut other: Collected) {
+        self.components.append(&mut other.components);
+        self.diagnostics.append(&mut other.diagnostics);
+        self.owned.extend(other.owned);
+    }
+}
+
+/// Format of data captured on another system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Input {
+    /// Output of `system_profiler -json SPSoftwareDataType SPExtensionsDataType SPApplicationsDataType`.
+    SystemProfiler,
+    /// Output of `driverquery /v /fo csv`.
+    DriverqueryCsv,
+    /// A .reg export of the Uninstall registry keys.
+    UninstallRegExport,
+}
+
+/// The collectors that can be selected, by default all the ones supported by the system are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CollectorKind {
+    Dpkg,
+    Apk,
+    Pacman,
+    Rpm,
+    Kernel,
+    Python,
+    Npm,
+    Binaries,
+    MacOS,
+    Windows,
+}
+
+/// Describes the system being inspected by the collectors.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Context {
+    root: Option<PathBuf>,
+    collectors: Option<Vec<CollectorKind>>,
+    paths: Option<Vec<String>>,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    max_depth: Option<usize>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>, Error> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // like shells, * doesn't match the separator while ** does
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| Error::parse("glob pattern", e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| Error::parse("glob pattern", e))
+}
+
+impl Context {
+    pub fn new(root: Option<PathBuf>) -> Result<Self, Error> {
+        if let Some(root) = &root {
+            let meta = fs::metadata(root).map_err(|e| Error::io(root, e))?;
+            if !meta.is_dir() {
+                return Err(Error::io(
+                    root,
+                    std::io::Error::from(std::io::ErrorKind::NotADirectory),
+                ));
+            }
+        }
+
+        Ok(Self {
+            root,
+            collectors: None,
+            paths: None,
+            include: None,
+            exclude: None,
+            max_depth: None,
+        })
+    }
+
+    /// Restricts the collectors to the given ones.
+    pub fn with_collectors(mut self, collectors: &[CollectorKind]) -> Self {
+        self.collectors = Some(collectors.to_vec());
+        self
+    }
+
+    /// Searches these directories for language packages and executables instead of the default ones.
+    pub fn with_paths(mut self, paths: &[String]) -> Self {
+        self.paths = Some(paths.to_vec());
+        self
+    }
+
+    /// Only reports the files found in the searched directories that match these patterns, and
+    /// skips the files and directories that match the exclude ones.
+    pub fn with_filters(mut self, include: &[String], exclude: &[String]) -> Result<Self, Error> {
+        self.include = glob_set(include)?;
+        self.exclude = glob_set(exclude)?;
+        Ok(self)
+    }
+
+    /// Limits how deep the directories are searched.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Returns true if a path of the inspected system matches the include patterns, if any.
+    pub fn is_included(&self, path: &str) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|globs| globs.is_match(path))
+    }
+
+    /// Returns true if a path of the inspected system matches the exclude patterns.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|globs| globs.is_match(path))
+    }
+
+    /// Returns the directories searched for packages that exist on the inspected system.
+    pub fn search_paths(&self, defaults: &[&str]) -> Vec<String> {
+        let paths = match &self.paths {
+            Some(paths) => paths.clone(),
+            None => defaults.iter().map(|path| path.to_string()).collect(),
+        };
+
+        paths
+            .into_iter()
+            .filter(|path| self.path(path).is_dir())
+            .collect()
+    }
+
+    /// Returns true if the collector has not been excluded.
+    pub fn is_enabled(&self, kind: CollectorKind) -> bool {
+        match &self.collectors {
+            None => true,
+            Some(collectors) => collectors.contains(&kind),
+        }
+    }
+
+    /// Returns true if the collector has been explicitly selected.
+    pub fn is_selected(&self, kind: CollectorKind) -> bool {
+        match &self.collectors {
+            None => false,
+            Some(collectors) => collectors.contains(&kind),
+        }
+    }
+
+    /// Returns true if the collectors are inspecting the running system.
+    pub fn is_live(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the name of the inspected system.
+    pub fn hostname(&self) -> String {
+        match &self.root {
+            None => gethostname::gethostname().to_string_lossy().into_owned(),
+            Some(root) => fs::read_to_string(self.path("/etc/hostname"))
+                .ok()
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| {
+                    root.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| root.display().to_string())
+                }),
+        }
+    }
+
+    /// Resolves an absolute path of the inspected system to a path on the host. When scanning an alternate
+    /// root, symlinks are resolved as if chrooted so that they can't escape to the host filesystem.
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let root = match &self.root {
+            None => return path.as_ref().to_path_buf(),
+            Some(root) => root,
+        };
+
+        let mut resolved = PathBuf::new();
+        let mut pending: Vec<OsString> = vec![];
+        let mut hops = 0;
+
+        // components are processed from the end of the vector
+        let push_components = |pending: &mut Vec<OsString>, path: &Path| {
+            for comp in path.components().rev() {
+                match comp {
+                    Component::Normal(name) => pending.push(name.to_owned()),
+                    Component::ParentDir => pending.push(OsString::from("..")),
+                    _ => {}
+                }
+            }
+        };
+
+        push_components(&mut pending, path.as_ref());
+
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                resolved.pop();
+                continue;
+            }
+
+            let candidate = resolved.join(&name);
+            let on_host = root.join(&candidate);
+            let is_symlink = fs::symlink_metadata(&on_host)
+                .map(|meta| meta.file_type().is_symlink())
+                .unwrap_or(false);
 
-    let mut coll = macos::Collector::default();
+            if is_symlink {
+                hops += 1;
+                // the host would follow the link left in the path, even outside of the root
+                let target = match fs::read_link(&on_host) {
+                    Ok(target) if hops <= MAX_SYMLINK_HOPS => target,
+                    _ => return root.join(UNRESOLVED_LINK),
+                };
+                if target.is_absolute() {
+                    resolved = PathBuf::new();
+                }
+                push_components(&mut pending, &target);
+                continue;
+            }
+
+            resolved = candidate;
+        }
+
+        root.join(resolved)
+    }
+}
+
+/// Adds the components of each collector, a failing collector is only fatal if all of them fail.
+pub(crate) fn collect_all(
+    collected: &mut Collected,
+    collectors: &[Box<dyn Collector>],
+) -> Result<(), Error> {
+    let mut errors = vec![];
+    for coll in collectors {
+        match coll.collect_unowned(&collected.owned) {
+            Ok(found) => collected.append(found),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() && errors.len() == collectors.len() {
+        return Err(errors.remove(0));
+    }
+    for e in errors {
+        collected.warn(e);
+    }
+
+    Ok(())
+}
+
+// the collector of the system followed by the language and executable ones
+struct Chain {
+    collectors: Vec<Box<dyn Collector>>,
+}
+
+impl Collector for Chain {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let mut collected = Collected::new("scanner");
+        collect_all(&mut collected, &self.collectors)?;
+        Ok(collected)
+    }
+}
+
+fn system(ctx: &Context) -> Result<Box<dyn Collector>, Error> {
+    let mut coll: Box<dyn Collector> = if ctx.is_live() {
+        native(ctx)?
+    } else if ctx.is_enabled(CollectorKind::Windows) && crate::windows::is_available(ctx) {
+        log::info!("found windows image");
+        Box::new(crate::windows::Collector::new(ctx))
+    } else {
+        // alternate roots can only be inspected by the file based collectors
+        Box::new(crate::linux::Collector::new(ctx))
+    };
 
     coll.setup()?;
 
-    Ok(Box::new(coll))
+    Ok(coll)
+}
+
+pub(crate) fn get(ctx: &Context) -> Result<Box<dyn Collector>, Error> {
+    let mut collectors = vec![];
+
+    // a system without packages can still have language packages, such as a python image
+    let unsupported = match system(ctx) {
+        Ok(coll) => {
+            collectors.push(coll);
+            None
+        }
+        Err(e @ Error::Unsupported(_)) => Some(e),
+        Err(e) => return Err(e),
+    };
+
+    let mut others = crate::lang::collectors(ctx);
+    if ctx.is_enabled(CollectorKind::Binaries) {
+        others.push(Box::new(crate::binary::Collector::new(ctx)));
+    }
+    for mut coll in others {
+        match coll.setup() {
+            Ok(()) => collectors.push(coll),
+            Err(e) => log::debug!("{}", e),
+        }
+    }
+
+    match unsupported {
+        Some(e) if collectors.is_empty() => return Err(e),
+        Some(e) => log::warn!("{}", e),
+        None => {}
+    }
+
+    match collectors.len() {
+        0 => Err(Error::unsupported("no collector available")),
+        1 => Ok(collectors.remove(0)),
+        _ => Ok(Box::new(Chain { collectors })),
+    }
+}
+
+/// Builds the components from data captured on another system.
+pub(crate) fn import(input: Input, data: &str) -> Result<Collected, Error> {
+    log::info!("importing components from {:?} data ...", input);
+
+    match input {
+        Input::SystemProfiler => crate::macos::Collector::default().collect_from_json(data),
+        Input::DriverqueryCsv => crate::windows::import_driverquery(data),
+        Input::UninstallRegExport => crate::windows::import_uninstall_export(data),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn native(ctx: &Context) -> Result<Box<dyn Collector>, Error> {
+    use crate::macos;
+
+    if !ctx.is_enabled(CollectorKind::MacOS) {
+        return Err(Error::unsupported("the macos collector is not selected"));
+    }
+
+    Ok(Box::new(macos::Collector::default()))
 }
 
 #[cfg(target_os = "windows")]
-pub(crate) fn get() -> Result<Box<dyn Collector>, Error> {
+fn native(ctx: &Context) -> Result<Box<dyn Collector>, Error> {
     use crate::windows;
 
-    let mut coll = windows::Collector::default();
-
-    coll.setup()?;
+    if !ctx.is_enabled(CollectorKind::Windows) {
+        return Err(Error::unsupported("the windows collector is not selected"));
+    }
 
-    Ok(Box::new(coll))
+    Ok(Box::new(windows::Collector::new(ctx)))
 }
 
 #[cfg(target_os = "linux")]
-pub(crate) fn get() -> Result<Box<dyn Collector>, Error> {
-    Err("unsupported operating system".to_string())
+fn native(ctx: &Context) -> Result<Box<dyn Collector>, Error> {
+    use crate::linux;
+
+    Ok(Box::new(linux::Collector::new(ctx)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn context(root: &Path) -> Context {
+        Context::new(Some(root.to_path_buf())).unwrap()
+    }
+
+    #[test]
+    fn path_resolves_links_inside_root() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("usr/lib")).unwrap();
+        fs::write(root.path().join("usr/lib/os-release"), "ID=test").unwrap();
+        symlink("../usr/lib/os-release", root.path().join("usr/os-release")).unwrap();
+        fs::create_dir(root.path().join("etc")).unwrap();
+        symlink("/usr/os-release", root.path().join("etc/os-release")).unwrap();
+
+        let ctx = context(root.path());
+        assert_eq!(
+            ctx.path("/etc/os-release"),
+            root.path().join("usr/lib/os-release")
+        );
+        assert_eq!(
+            ctx.path("/etc/../../../usr/lib"),
+            root.path().join("usr/lib")
+        );
+    }
+
+    #[test]
+    fn path_keeps_absolute_links_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("shadow"), "secret").unwrap();
+        symlink(outside.path().join("shadow"), root.path().join("shadow")).unwrap();
+        symlink("../../../../../..", root.path().join("up")).unwrap();
+
+        let ctx = context(root.path());
+        let path = ctx.path("/shadow");
+        assert!(path.starts_with(root.path()));
+        assert!(fs::read_to_string(&path).is_err());
+        assert_eq!(ctx.path("/up"), root.path().to_path_buf());
+    }
+
+    #[test]
+    fn path_stops_at_symlink_loops() {
+        let root = tempfile::tempdir().unwrap();
+        symlink("b", root.path().join("a")).unwrap();
+        symlink("a", root.path().join("b")).unwrap();
+
+        let ctx = context(root.path());
+        let path = ctx.path("/a/file");
+        assert!(path.starts_with(root.path()));
+        assert!(fs::metadata(&path).is_err());
+    }
+
+    #[test]
+    fn path_stops_at_long_chains_out_of_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("shadow"), "secret").unwrap();
+        // one link more than the limit, the last one pointing outside of the root
+        symlink(outside.path().join("shadow"), root.path().join("link0")).unwrap();
+        for idx in 1..=MAX_SYMLINK_HOPS {
+            symlink(
+                format!("link{}", idx - 1),
+                root.path().join(format!("link{}", idx)),
+            )
+            .unwrap();
+        }
+
+        let ctx = context(root.path());
+        let path = ctx.path(format!("/link{}", MAX_SYMLINK_HOPS));
+        assert!(path.starts_with(root.path()));
+        assert!(fs::read_to_string(&path).is_err());
+    }
 }
@@ -1,16 +1,76 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone)]
-pub(crate) enum Kind {
+use crate::cpe;
+use crate::purl::Purl;
+use crate::vulns::Finding;
+
+// application and kernel extension bundles are directories
+const BUNDLE_EXTENSIONS: &[&str] = &["app", "kext"];
+
+/// Returns true if the path is the one of an application or kernel extension bundle.
+pub(crate) fn is_bundle(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        BUNDLE_EXTENSIONS
+            .iter()
+            .any(|b| ext.eq_ignore_ascii_case(b))
+    })
+}
+
+/// Property of the packages linked into an executable, the package URL or the path of the executable.
+pub(crate) const EMBEDDED_IN_PROPERTY: &str = "embedded_in";
+
+/// Kind of software component.
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
     OS,
     #[default]
     Application,
     Driver,
+    // a package that was only installed as a dependency of another one
+    Dependency,
     Other,
 }
 
-pub(crate) trait ComponentTrait {
+/// Kind of relationship of a component with another one.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RelationshipKind {
+    DependsOn,
+    // the other component is inside its bundle, such as a kernel extension of an application
+    Contains,
+    // the other component is the package that installed it
+    ProvidedBy,
+}
+
+/// A relationship of a component with another one, identified by its package URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Relationship {
+    pub kind: RelationshipKind,
+    pub purl: Purl,
+}
+
+impl Relationship {
+    pub fn new(kind: RelationshipKind, purl: Purl) -> Self {
+        Self { kind, purl }
+    }
+}
+
+/// A file installed by a component, with its hashes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnedFile {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // algorithm and hex digest
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+}
+
+/// A component found by a collector.
+pub trait ComponentTrait {
     fn kind(&self) -> Kind;
     fn name(&self) -> &str;
     fn id(&self) -> &str;
@@ -18,10 +78,44 @@ pub(crate) trait ComponentTrait {
     fn path(&self) -> &str;
     fn modified(&self) -> DateTime<Utc>;
     fn publishers(&self) -> &Vec<String>;
+    fn purl(&self) -> Option<Purl>;
+
+    fn license(&self) -> Option<&str> {
+        None
+    }
+
+    fn origin(&self) -> Option<&str> {
+        None
+    }
+
+    /// CPE name declared by the component itself, when missing it's derived from the other fields.
+    fn cpe(&self) -> Option<String> {
+        None
+    }
+
+    /// Details that don't fit the other fields, such as the vermagic of a kernel module.
+    fn properties(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    /// Components it directly depends on, contains or was installed by.
+    fn relationships(&self) -> Vec<Relationship> {
+        vec![]
+    }
+
+    /// Files owned by the component, only reported when their hashes are known.
+    fn files(&self) -> Vec<OwnedFile> {
+        vec![]
+    }
+
+    fn vulnerabilities(&self) -> &[Finding] {
+        &[]
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub(crate) struct Component {
+/// A component with all of its details, as returned by the [`Scanner`](crate::Scanner).
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Component {
     pub kind: Kind,
     pub name: String,
     pub id: String,
@@ -29,6 +123,25 @@ pub(crate) struct Component {
     pub path: String,
     pub modified: DateTime<Utc>,
     pub publishers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purl: Option<Purl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relationships: Vec<Relationship>,
+    /// Algorithm and hex digest of the file of the component, or of the whole bundle directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<OwnedFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vulnerabilities: Vec<Finding>,
 }
 
 impl Component {
@@ -41,6 +154,77 @@ impl Component {
             path: comp.path().to_owned(),
             modified: comp.modified(),
             publishers: comp.publishers().to_owned(),
+            purl: comp.purl(),
+            cpes: cpe::derive(comp),
+            license: comp.license().map(|l| l.to_owned()),
+            origin: comp.origin().map(|o| o.to_owned()),
+            properties: comp.properties(),
+            relationships: comp.relationships(),
+            hashes: BTreeMap::new(),
+            files: comp.files(),
+            vulnerabilities: comp.vulnerabilities().to_vec(),
         }
     }
 }
+
+impl ComponentTrait for Component {
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        self.purl.clone()
+    }
+
+    fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    fn cpe(&self) -> Option<String> {
+        self.cpes.first().cloned()
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.relationships.clone()
+    }
+
+    fn files(&self) -> Vec<OwnedFile> {
+        self.files.clone()
+    }
+
+    fn vulnerabilities(&self) -> &[Finding] {
+        &self.vulnerabilities
+    }
+}
@@ -0,0 +1,382 @@
+use lazy_static::lazy_static;
+
+use crate::component::{ComponentTrait, Kind};
+
+// https://nvlpubs.nist.gov/nistpubs/Legacy/IR/nistir7695.pdf
+const CPE_PREFIX: &str = "cpe:2.3";
+const ANY: &str = "*";
+
+lazy_static! {
+    // corporate suffixes that are never part of NVD vendor names
+    static ref VENDOR_SUFFIXES: Vec<&'static str> = vec![
+        "inc", "inc.", "llc", "l.l.c.", "ltd", "ltd.", "limited", "corp", "corp.", "corporation",
+        "co", "co.", "gmbh", "ag", "s.a.", "s.r.l.", "b.v.", "oy", "ab", "pty", "plc",
+    ];
+    // signing certificates and package maintainers have a prefix before the actual vendor name
+    static ref VENDOR_PREFIXES: Vec<&'static str> = vec![
+        "Developer ID Application:",
+        "Apple Mac OS Application Signing:",
+        "Apple Development:",
+    ];
+}
+
+struct KnownOS {
+    name: &'static str,
+    // (vendor, product, minimum version) tuples, the last matching entry wins
+    products: &'static [(&'static str, &'static str, &'static str)],
+}
+
+// built-in table for operating systems, the NVD naming has very little to do with the marketing names
+const KNOWN_OS: &[KnownOS] = &[
+    KnownOS {
+        name: "macOS",
+        products: &[("apple", "mac_os_x", "0"), ("apple", "macos", "10.12")],
+    },
+    KnownOS {
+        name: "Microsoft Windows",
+        products: &[
+            ("microsoft", "windows_10", "10.0"),
+            // windows 11 kept the 10.0 version, only the build number tells them apart
+            ("microsoft", "windows_11", "10.0.22000"),
+        ],
+    },
+    KnownOS {
+        name: "Linux",
+        products: &[("linux", "linux_kernel", "0")],
+    },
+    KnownOS {
+        name: "Debian GNU/Linux",
+        products: &[("debian", "debian_linux", "0")],
+    },
+    KnownOS {
+        name: "Ubuntu",
+        products: &[("canonical", "ubuntu_linux", "0")],
+    },
+    KnownOS {
+        name: "Red Hat Enterprise Linux",
+        products: &[("redhat", "enterprise_linux", "0")],
+    },
+    KnownOS {
+        name: "Alpine Linux",
+        products: &[("alpinelinux", "alpine_linux", "0")],
+    },
+];
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .take_while(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Escapes a value according to the formatted string binding rules.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            escaped.push(c);
+        } else if c.is_whitespace() {
+            escaped.push('_');
+        } else if c.is_ascii() {
+            escaped.push('\\');
+            escaped.push(c);
+        }
+        // non ascii characters are not allowed at all
+    }
+    escaped
+}
+
+fn normalize(value: &str) -> String {
+    escape(value.trim().to_lowercase().as_str())
+}
+
+/// Turns a publisher (certificate subject, maintainer, registry entry, ...) into a vendor name.
+fn normalize_vendor(publisher: &str) -> String {
+    let mut vendor = publisher.trim();
+
+    for prefix in VENDOR_PREFIXES.iter() {
+        if let Some(stripped) = vendor.strip_prefix(prefix) {
+            vendor = stripped.trim();
+        }
+    }
+
+    // team identifiers, emails and other parenthesized details
+    if let Some(idx) = vendor.find(['(', '<']) {
+        vendor = vendor[..idx].trim();
+    }
+
+    let mut words: Vec<&str> = vendor
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect();
+    while words.len() > 1 {
+        let last = words[words.len() - 1].to_lowercase();
+        if VENDOR_SUFFIXES.contains(&last.as_str()) {
+            words.pop();
+        } else {
+            break;
+        }
+    }
+
+    normalize(&words.join(" "))
+}
+
+fn format(part: char, vendor: &str, product: &str, version: &str) -> String {
+    let version = if version.is_empty() {
+        ANY.to_owned()
+    } else {
+        normalize(version)
+    };
+
+    format!(
+        "{}:{}:{}:{}:{}:*:*:*:*:*:*:*",
+        CPE_PREFIX, part, vendor, product, version
+    )
+}
+
+fn derive_os(comp: &dyn ComponentTrait) -> Vec<String> {
+    // strip build numbers such as "12.5.1 (21G83)"
+    let version = comp.version().split_whitespace().next().unwrap_or_default();
+
+    if let Some(known) = KNOWN_OS.iter().find(|os| os.name == comp.name()) {
+        // and local versions such as "6.1.0-13-amd64"
+        let version = version.split('-').next().unwrap_or_default();
+        let parts = version_parts(version);
+        return known
+            .products
+            .iter()
+            .rev()
+            .find(|(_, _, min)| parts >= version_parts(min))
+            .map(|(vendor, product, _)| vec![format('o', vendor, product, version)])
+            .unwrap_or_default();
+    }
+
+    let product = normalize(comp.name());
+    let vendor = comp
+        .publishers()
+        .first()
+        .map(|p| normalize_vendor(p))
+        .unwrap_or_else(|| product.clone());
+
+    vec![format('o', &vendor, &product, version)]
+}
+
+/// Converts a CPE name in the URI binding ("cpe:/o:fedoraproject:fedora:39") to the formatted
+/// string binding, names that are already formatted strings are returned as they are.
+pub(crate) fn from_uri(name: &str) -> Option<String> {
+    if name.starts_with(CPE_PREFIX) {
+        return Some(name.to_owned());
+    }
+
+    let parts: Vec<&str> = name.strip_prefix("cpe:/")?.split(':').collect();
+    let part = parts.first()?.chars().next()?;
+    let field = |idx: usize| {
+        parts
+            .get(idx)
+            .filter(|value| !value.is_empty())
+            .map(|value| escape(&value.replace("%20", " ")))
+            .unwrap_or_else(|| ANY.to_owned())
+    };
+
+    Some(format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}:*:*:*:*",
+        CPE_PREFIX,
+        part,
+        field(1),
+        field(2),
+        field(3),
+        field(4),
+        field(5),
+        field(6)
+    ))
+}
+
+/// Derives the candidate CPE names of a component.
+pub(crate) fn derive(comp: &dyn ComponentTrait) -> Vec<String> {
+    if let Some(cpe) = comp.cpe() {
+        return vec![cpe];
+    }
+
+    if comp.name().is_empty() {
+        return vec![];
+    }
+
+    if let Kind::OS = comp.kind() {
+        return derive_os(comp);
+    }
+
+    // go modules and scoped npm packages are named by a path, NVD products are not
+    if comp.name().contains('/') {
+        return vec![];
+    }
+    let product = normalize(comp.name());
+    if product.is_empty() {
+        return vec![];
+    }
+
+    // when the publisher is unknown or is not the actual vendor, many NVD entries use the product
+    // name as the vendor name too. Publishers with an email address are package maintainers.
+    let mut vendors = vec![];
+    if let Some(publisher) = comp.publishers().first() {
+        let vendor = normalize_vendor(publisher);
+        if !vendor.is_empty() && !publisher.contains('@') {
+            vendors.push(vendor);
+        }
+    }
+    if !vendors.contains(&product) {
+        vendors.push(product.clone());
+    }
+
+    vendors
+        .iter()
+        .map(|vendor| format('a', vendor, &product, comp.version()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::component::Component;
+
+    fn component(kind: Kind, name: &str, version: &str, publisher: Option<&str>) -> Component {
+        Component {
+            kind,
+            name: name.to_owned(),
+            id: name.to_owned(),
+            version: version.to_owned(),
+            publishers: publisher.map(|p| p.to_owned()).into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalizes_vendors() {
+        for (publisher, vendor) in [
+            (
+                "Developer ID Application: Google LLC (EQHXZ8M8AV)",
+                "google",
+            ),
+            ("Apple Development: Jane Doe (ABCDE12345)", "jane_doe"),
+            ("Jane Doe <jane@example.com>", "jane_doe"),
+            ("Oracle America, Inc.", "oracle_america"),
+            ("Microsoft Corporation", "microsoft"),
+            ("Canonical Group Limited", "canonical_group"),
+            ("Example GmbH & Co. KG", "example_gmbh_\\&_co._kg"),
+            ("Mozilla Corp. LLC", "mozilla"),
+            // a suffix alone is kept
+            ("Inc.", "inc."),
+            ("  VideoLAN  ", "videolan"),
+        ] {
+            assert_eq!(normalize_vendor(publisher), vendor, "{}", publisher);
+        }
+    }
+
+    #[test]
+    fn derives_os_names() {
+        for (name, version, cpe) in [
+            (
+                "Microsoft Windows",
+                "10.0.19045.3570",
+                Some("cpe:2.3:o:microsoft:windows_10:10.0.19045.3570:*:*:*:*:*:*:*"),
+            ),
+            (
+                "Microsoft Windows",
+                "10.0.22000.2538",
+                Some("cpe:2.3:o:microsoft:windows_11:10.0.22000.2538:*:*:*:*:*:*:*"),
+            ),
+            (
+                "Microsoft Windows",
+                "10.0.21999",
+                Some("cpe:2.3:o:microsoft:windows_10:10.0.21999:*:*:*:*:*:*:*"),
+            ),
+            // older versions are not in the table
+            ("Microsoft Windows", "6.1.7601", None),
+            (
+                "macOS",
+                "12.5.1 (21G83)",
+                Some("cpe:2.3:o:apple:macos:12.5.1:*:*:*:*:*:*:*"),
+            ),
+            (
+                "macOS",
+                "10.11.6",
+                Some("cpe:2.3:o:apple:mac_os_x:10.11.6:*:*:*:*:*:*:*"),
+            ),
+            (
+                "Linux",
+                "6.1.0-13-amd64",
+                Some("cpe:2.3:o:linux:linux_kernel:6.1.0:*:*:*:*:*:*:*"),
+            ),
+            (
+                "Fedora Linux",
+                "39",
+                Some("cpe:2.3:o:fedora_linux:fedora_linux:39:*:*:*:*:*:*:*"),
+            ),
+        ] {
+            let os = component(Kind::OS, name, version, None);
+            let expected: Vec<String> = cpe.into_iter().map(|cpe| cpe.to_owned()).collect();
+            assert_eq!(derive(&os), expected, "{} {}", name, version);
+        }
+    }
+
+    #[test]
+    fn derives_application_names() {
+        let app = component(
+            Kind::Application,
+            "Google Chrome",
+            "120.0.6099.109",
+            Some("Developer ID Application: Google LLC (EQHXZ8M8AV)"),
+        );
+        assert_eq!(
+            derive(&app),
+            vec![
+                "cpe:2.3:a:google:google_chrome:120.0.6099.109:*:*:*:*:*:*:*",
+                "cpe:2.3:a:google_chrome:google_chrome:120.0.6099.109:*:*:*:*:*:*:*",
+            ]
+        );
+
+        // maintainers are not vendors
+        let package = component(
+            Kind::Application,
+            "openssl",
+            "",
+            Some("Debian OpenSSL Team <pkg-openssl-devel@alioth-lists.debian.net>"),
+        );
+        assert_eq!(
+            derive(&package),
+            vec!["cpe:2.3:a:openssl:openssl:*:*:*:*:*:*:*:*"]
+        );
+
+        for name in [
+            "cloud.google.com/go",
+            "github.com/spf13/cobra",
+            "@angular/core",
+        ] {
+            let module = component(Kind::Dependency, name, "1.0.0", None);
+            assert!(derive(&module).is_empty(), "{}", name);
+        }
+
+        let mut declared = component(Kind::Application, "app", "1.0", None);
+        declared.cpes = vec!["cpe:2.3:a:example:app:1.0:*:*:*:*:*:*:*".to_owned()];
+        assert_eq!(derive(&declared), declared.cpes);
+    }
+
+    #[test]
+    fn converts_uri_bindings() {
+        assert_eq!(
+            from_uri("cpe:/o:fedoraproject:fedora:39").as_deref(),
+            Some("cpe:2.3:o:fedoraproject:fedora:39:*:*:*:*:*:*:*")
+        );
+        assert_eq!(
+            from_uri("cpe:/a:vendor:my%20product::update1").as_deref(),
+            Some("cpe:2.3:a:vendor:my_product:*:update1:*:*:*:*:*:*")
+        );
+        assert_eq!(
+            from_uri("cpe:2.3:o:redhat:enterprise_linux:9:*:*:*:*:*:*:*").as_deref(),
+            Some("cpe:2.3:o:redhat:enterprise_linux:9:*:*:*:*:*:*:*")
+        );
+        assert_eq!(from_uri("cpe:/"), None);
+        assert_eq!(from_uri("fedora 39"), None);
+    }
+}
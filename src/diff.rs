@@ -0,0 +1,457 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::Serialize;
+
+use crate::component::Component;
+use crate::version::{self, Scheme};
+use crate::Error;
+
+/// Kind of difference between two scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+    Publishers,
+}
+
+/// A difference between two scans.
+#[derive(Serialize)]
+pub struct Change<'a> {
+    pub change: ChangeKind,
+    pub name: String,
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<&'a Component>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<&'a Component>,
+    // position of the old component, used to build the patch
+    #[serde(skip)]
+    old_index: Option<usize>,
+}
+
+#[derive(Default, Serialize)]
+struct Summary {
+    added: usize,
+    removed: usize,
+    upgraded: usize,
+    downgraded: usize,
+    publishers: usize,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    summary: Summary,
+    changes: &'a [Change<'a>],
+}
+
+// components are the same if they have the same package URL regardless of the version, the
+// architecture is part of the identity since multiarch packages can be installed side by side
+fn key(comp: &Component) -> String {
+    match &comp.purl {
+        Some(purl) => {
+            let mut purl = purl.clone();
+            purl.version = None;
+            purl.qualifiers.retain(|key, _| key == "arch");
+            purl.to_string()
+        }
+        None => comp.id.to_lowercase(),
+    }
+}
+
+fn scheme(comp: &Component) -> Scheme {
+    comp.purl
+        .as_ref()
+        .map(|purl| Scheme::for_purl_type(&purl.kind))
+        .unwrap_or(Scheme::Generic)
+}
+
+fn publishers(comp: &Component) -> BTreeSet<&str> {
+    comp.publishers.iter().map(|p| p.as_str()).collect()
+}
+
+fn group(components: &[Component]) -> BTreeMap<String, Vec<usize>> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, comp) in components.iter().enumerate() {
+        groups.entry(key(comp)).or_default().push(idx);
+    }
+    groups
+}
+
+/// Compares two scans, components are matched by package URL or by id when they don't have one.
+pub fn compare<'a>(old: &'a [Component], new: &'a [Component]) -> Vec<Change<'a>> {
+    let old_groups = group(old);
+    let new_groups = group(new);
+    let keys: BTreeSet<&String> = old_groups.keys().chain(new_groups.keys()).collect();
+
+    let mut changes = vec![];
+    let mut pairs = vec![];
+
+    for key in keys {
+        let mut olds = old_groups.get(key).cloned().unwrap_or_default();
+        let mut news = new_groups.get(key).cloned().unwrap_or_default();
+
+        // the same version can be installed more than once (for instance in different paths)
+        // and different versions side by side, identical versions are paired first
+        olds.retain(
+            |o| match news.iter().position(|n| new[*n].version == old[*o].version) {
+                Some(pos) => {
+                    pairs.push((key.clone(), *o, news.remove(pos)));
+                    false
+                }
+                None => true,
+            },
+        );
+
+        if olds.len() == news.len() {
+            // the remaining versions are paired in order
+            olds.sort_by(|a, b| {
+                version::compare(scheme(&old[*a]), &old[*a].version, &old[*b].version)
+            });
+            news.sort_by(|a, b| {
+                version::compare(scheme(&new[*a]), &new[*a].version, &new[*b].version)
+            });
+            pairs.extend(olds.into_iter().zip(news).map(|(o, n)| (key.clone(), o, n)));
+        } else {
+            changes.extend(olds.into_iter().map(|o| Change {
+                change: ChangeKind::Removed,
+                name: old[o].name.clone(),
+                key: key.clone(),
+                old: Some(&old[o]),
+                new: None,
+                old_index: Some(o),
+            }));
+            changes.extend(news.into_iter().map(|n| Change {
+                change: ChangeKind::Added,
+                name: new[n].name.clone(),
+                key: key.clone(),
+                old: None,
+                new: Some(&new[n]),
+                old_index: None,
+            }));
+        }
+    }
+
+    for (key, o, n) in pairs {
+        let (before, after) = (&old[o], &new[n]);
+        let change = |change| Change {
+            change,
+            name: after.name.clone(),
+            key: key.clone(),
+            old: Some(before),
+            new: Some(after),
+            old_index: Some(o),
+        };
+
+        match version::compare(scheme(after), &before.version, &after.version) {
+            Ordering::Less => changes.push(change(ChangeKind::Upgraded)),
+            Ordering::Greater => changes.push(change(ChangeKind::Downgraded)),
+            Ordering::Equal => {}
+        }
+
+        if publishers(before) != publishers(after) {
+            changes.push(change(ChangeKind::Publishers));
+        }
+    }
+
+    changes.sort_by(|a, b| {
+        a.change
+            .cmp(&b.change)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+
+    changes
+}
+
+fn summary(changes: &[Change]) -> Summary {
+    let mut summary = Summary::default();
+    for change in changes {
+        match change.change {
+            ChangeKind::Added => summary.added += 1,
+            ChangeKind::Removed => summary.removed += 1,
+            ChangeKind::Upgraded => summary.upgraded += 1,
+            ChangeKind::Downgraded => summary.downgraded += 1,
+            ChangeKind::Publishers => summary.publishers += 1,
+        }
+    }
+    summary
+}
+
+/// Writes one line per change and a summary.
+pub fn to_text<T: std::io::Write>(changes: &[Change], mut writer: T) -> Result<(), Error> {
+    let mut text = String::new();
+
+    for change in changes {
+        let (old, new) = (change.old, change.new);
+        let version =
+            |comp: Option<&Component>| comp.map(|c| c.version.clone()).unwrap_or_default();
+
+        text += &match change.change {
+            ChangeKind::Added => format!("+ {} {}\n", change.name, version(new)),
+            ChangeKind::Removed => format!("- {} {}\n", change.name, version(old)),
+            ChangeKind::Upgraded => {
+                format!("> {} {} -> {}\n", change.name, version(old), version(new))
+            }
+            ChangeKind::Downgraded => {
+                format!("< {} {} -> {}\n", change.name, version(old), version(new))
+            }
+            ChangeKind::Publishers => {
+                let list = |comp: Option<&Component>| {
+                    comp.map(|c| c.publishers.join(", ")).unwrap_or_default()
+                };
+                format!(
+                    "~ {} publishers [{}] -> [{}]\n",
+                    change.name,
+                    list(old),
+                    list(new)
+                )
+            }
+        };
+    }
+
+    let summary = summary(changes);
+    if changes.is_empty() {
+        text += "no changes\n";
+    } else {
+        text += &format!(
+            "\n{} added, {} removed, {} upgraded, {} downgraded, {} publisher changes\n",
+            summary.added,
+            summary.removed,
+            summary.upgraded,
+            summary.downgraded,
+            summary.publishers
+        );
+    }
+
+    writer.write_all(text.as_bytes()).map_err(Error::Write)
+}
+
+/// Writes a summary and the changes with the full components as JSON.
+pub fn to_json<T: std::io::Write>(changes: &[Change], mut writer: T) -> Result<(), Error> {
+    let report = Report {
+        summary: summary(changes),
+        changes,
+    };
+
+    let json = serde_json::to_string(&report).map_err(|e| Error::Write(e.into()))?;
+
+    writer.write_all(json.as_bytes()).map_err(Error::Write)
+}
+
+/// Writes the changes as a JSON Patch (RFC 6902) that turns the components of the old scan, in the
+/// unisbom JSON format, into the new ones.
+pub fn to_patch<T: std::io::Write>(changes: &[Change], mut writer: T) -> Result<(), Error> {
+    let mut ops = vec![];
+    let mut replaced = HashSet::new();
+
+    // replacements first, while the indexes of the old components are still valid
+    for change in changes {
+        if let (Some(idx), Some(new)) = (change.old_index, change.new) {
+            if replaced.insert(idx) {
+                ops.push(serde_json::json!({
+                    "op": "replace",
+                    "path": format!("/components/{}", idx),
+                    "value": new,
+                }));
+            }
+        }
+    }
+
+    // removals from the last one so that they don't shift each other
+    let mut removed: Vec<usize> = changes
+        .iter()
+        .filter(|change| change.change == ChangeKind::Removed)
+        .filter_map(|change| change.old_index)
+        .collect();
+    removed.sort_unstable_by(|a, b| b.cmp(a));
+    for idx in removed {
+        ops.push(serde_json::json!({
+            "op": "remove",
+            "path": format!("/components/{}", idx),
+        }));
+    }
+
+    for change in changes {
+        if let (ChangeKind::Added, Some(new)) = (change.change, change.new) {
+            ops.push(serde_json::json!({
+                "op": "add",
+                "path": "/components/-",
+                "value": new,
+            }));
+        }
+    }
+
+    let json = serde_json::to_string(&ops).map_err(|e| Error::Write(e.into()))?;
+
+    writer.write_all(json.as_bytes()).map_err(Error::Write)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::purl::Purl;
+
+    fn component(name: &str, version: &str, purl: Option<Purl>, publishers: &[&str]) -> Component {
+        Component {
+            name: name.to_owned(),
+            id: name.to_owned(),
+            version: version.to_owned(),
+            publishers: publishers.iter().map(|p| p.to_string()).collect(),
+            purl: purl.map(|purl| purl.with_version(version)),
+            ..Default::default()
+        }
+    }
+
+    fn deb(name: &str, version: &str, arch: &str) -> Component {
+        let purl = Purl::new("deb", name)
+            .with_namespace("debian")
+            .with_qualifier("arch", arch);
+        component(name, version, Some(purl), &[])
+    }
+
+    fn generic(name: &str, version: &str) -> Component {
+        component(name, version, Some(Purl::new("generic", name)), &[])
+    }
+
+    fn old() -> Vec<Component> {
+        vec![
+            deb("libc6", "2.36-9", "amd64"),
+            deb("libc6", "2.36-9", "i386"),
+            deb("curl", "7.88.1-10", "amd64"),
+            component("six", "1.16.0.post1", Some(Purl::new("pypi", "six")), &[]),
+            generic("linux", "6.1.0"),
+            generic("linux", "6.1.1"),
+            generic("jdk", "17.0.9"),
+            generic("jdk", "21.0.1"),
+            component("Tool", "1.0", None, &["Example Ltd"]),
+            deb("telnet", "0.17+2.4-2", "amd64"),
+        ]
+    }
+
+    fn new() -> Vec<Component> {
+        let mut libc = deb("libc6", "2.36-9+deb12u4", "amd64");
+        // the other qualifiers are not part of the identity
+        libc.purl = libc
+            .purl
+            .map(|purl| purl.with_qualifier("distro", "debian-12"));
+        vec![
+            deb("telnet-ssl", "0.17.41+0.2-3.3", "amd64"),
+            component("tool", "1.0", None, &["Example Inc."]),
+            generic("jdk", "21.0.2"),
+            generic("jdk", "17.0.10"),
+            generic("linux", "6.6.0"),
+            generic("linux", "6.5.0"),
+            generic("linux", "6.1.1"),
+            component("six", "1.16.0", Some(Purl::new("pypi", "six")), &[]),
+            deb("curl", "7.74.0-1.3", "amd64"),
+            deb("libc6", "2.36-9", "i386"),
+            libc,
+        ]
+    }
+
+    fn summarize<'a>(changes: &'a [Change]) -> Vec<(ChangeKind, &'a str, &'a str, &'a str)> {
+        changes
+            .iter()
+            .map(|change| {
+                let version = |comp: Option<&'a Component>| comp.map_or("", |c| c.version.as_str());
+                (
+                    change.change,
+                    change.name.as_str(),
+                    version(change.old),
+                    version(change.new),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let (old, new) = (old(), new());
+        let changes = compare(&old, &new);
+        assert_eq!(
+            summarize(&changes),
+            vec![
+                // in the order of the scan
+                (ChangeKind::Added, "linux", "", "6.6.0"),
+                (ChangeKind::Added, "linux", "", "6.5.0"),
+                (ChangeKind::Added, "telnet-ssl", "", "0.17.41+0.2-3.3"),
+                (ChangeKind::Removed, "linux", "6.1.0", ""),
+                (ChangeKind::Removed, "telnet", "0.17+2.4-2", ""),
+                // the remaining versions of a group are paired in order
+                (ChangeKind::Upgraded, "jdk", "17.0.9", "17.0.10"),
+                (ChangeKind::Upgraded, "jdk", "21.0.1", "21.0.2"),
+                (ChangeKind::Upgraded, "libc6", "2.36-9", "2.36-9+deb12u4"),
+                (ChangeKind::Downgraded, "curl", "7.88.1-10", "7.74.0-1.3"),
+                // a post release is newer
+                (ChangeKind::Downgraded, "six", "1.16.0.post1", "1.16.0"),
+                (ChangeKind::Publishers, "tool", "1.0", "1.0"),
+            ]
+        );
+        assert_eq!(changes[7].key, "pkg:deb/debian/libc6?arch=amd64");
+        assert_eq!(changes[10].key, "tool");
+    }
+
+    #[test]
+    fn writes_changes() {
+        let (old, new) = (old(), new());
+        let changes = compare(&old, &new);
+
+        let mut text = vec![];
+        to_text(&changes, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("> libc6 2.36-9 -> 2.36-9+deb12u4\n"));
+        assert!(text.contains("~ tool publishers [Example Ltd] -> [Example Inc.]\n"));
+        assert!(
+            text.ends_with("\n3 added, 2 removed, 3 upgraded, 2 downgraded, 1 publisher changes\n")
+        );
+
+        let mut patch = vec![];
+        to_patch(&changes, &mut patch).unwrap();
+        let patch: Vec<serde_json::Value> = serde_json::from_slice(&patch).unwrap();
+        let ops: Vec<(&str, &str)> = patch
+            .iter()
+            .map(|op| (op["op"].as_str().unwrap(), op["path"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                ("replace", "/components/6"),
+                ("replace", "/components/7"),
+                ("replace", "/components/0"),
+                ("replace", "/components/2"),
+                ("replace", "/components/3"),
+                ("replace", "/components/8"),
+                ("remove", "/components/9"),
+                ("remove", "/components/4"),
+                ("add", "/components/-"),
+                ("add", "/components/-"),
+                ("add", "/components/-"),
+            ]
+        );
+    }
+
+    // the diff command exits with 1 when there are changes and 0 otherwise
+    #[test]
+    fn same_scans_have_no_changes() {
+        let scan = old();
+        let mut shuffled = old();
+        shuffled.reverse();
+        assert!(compare(&scan, &scan).is_empty());
+        assert!(compare(&scan, &shuffled).is_empty());
+        assert!(compare(&[], &[]).is_empty());
+
+        let mut text = vec![];
+        to_text(&[], &mut text).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), "no changes\n");
+
+        // a single difference is enough
+        let mut changed = old();
+        changed[0].publishers = vec!["GNU Libc Maintainers".to_owned()];
+        assert_eq!(compare(&scan, &changed).len(), 1);
+        assert_eq!(compare(&scan, &scan[1..]).len(), 1);
+    }
+}
@@ -0,0 +1,324 @@
+use std::io::{Cursor, Read, Seek};
+
+use crate::binary::{self, SectionHeader};
+use crate::Error;
+
+// https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+// sections without data in the file, such as .bss
+const SHT_NOBITS: u32 = 8;
+const ET_EXEC: u16 = 2;
+// shared libraries and position independent executables
+const ET_DYN: u16 = 3;
+// of the 64 bits class, the 32 bits one is shorter
+const HEADER_SIZE: u64 = 0x40;
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("ELF image", message)
+}
+
+/// Returns true if the data starts with the ELF signature.
+pub(crate) fn is_elf(data: &[u8]) -> bool {
+    data.starts_with(ELF_MAGIC)
+}
+
+/// Returns true if the header is the one of an executable or of a shared object.
+pub(crate) fn is_executable(header: &[u8]) -> bool {
+    let e_type = match (header.get(5), header.get(16..18)) {
+        (Some(&ELFDATA2MSB), Some(b)) => u16::from_be_bytes([b[0], b[1]]),
+        (Some(_), Some(b)) => u16::from_le_bytes([b[0], b[1]]),
+        _ => return false,
+    };
+    is_elf(header) && (e_type == ET_EXEC || e_type == ET_DYN)
+}
+
+// offsets and sizes in the file, the name is an offset in the section names table
+struct Entry {
+    name: usize,
+    kind: u32,
+    address: u64,
+    offset: u64,
+    size: u64,
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl<'a> Image<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if !is_elf(data) {
+            return Err(malformed("not an ELF file: missing signature"));
+        }
+
+        let is_64 = match data.get(4) {
+            Some(&ELFCLASS32) => false,
+            Some(&ELFCLASS64) => true,
+            class => return Err(malformed(format!("unsupported class {:?}", class))),
+        };
+        let big_endian = match data.get(5) {
+            Some(&ELFDATA2LSB) => false,
+            Some(&ELFDATA2MSB) => true,
+            encoding => return Err(malformed(format!("unsupported encoding {:?}", encoding))),
+        };
+
+        Ok(Self {
+            data,
+            is_64,
+            big_endian,
+        })
+    }
+
+    // the same class and encoding, for the data read from another part of the file
+    fn with_data<'b>(&self, data: &'b [u8]) -> Image<'b> {
+        Image {
+            data,
+            is_64: self.is_64,
+            big_endian: self.big_endian,
+        }
+    }
+
+    fn uint(&self, offset: usize, size: usize) -> Result<u64, Error> {
+        let bytes = offset
+            .checked_add(size)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| malformed(format!("truncated at offset {:#x}", offset)))?;
+
+        let mut value = 0u64;
+        for idx in 0..size {
+            let byte = if self.big_endian {
+                bytes[idx]
+            } else {
+                bytes[size - 1 - idx]
+            };
+            value = (value << 8) | byte as u64;
+        }
+        Ok(value)
+    }
+
+    fn u16(&self, offset: usize) -> Result<usize, Error> {
+        self.uint(offset, 2).map(|v| v as usize)
+    }
+
+    fn u32(&self, offset: usize) -> Result<usize, Error> {
+        self.uint(offset, 4).map(|v| v as usize)
+    }
+
+    // addresses and offsets are 32 or 64 bits wide depending on the class
+    fn word(&self, offset: usize) -> Result<u64, Error> {
+        if self.is_64 {
+            self.uint(offset, 8)
+        } else {
+            self.uint(offset, 4)
+        }
+    }
+
+    // the table is read on its own, the entries are relative to its start
+    fn entries(&self, count: usize, entry_size: usize) -> Result<Vec<Entry>, Error> {
+        let field = if self.is_64 { 8 } else { 4 };
+
+        let mut entries = vec![];
+        for idx in 0..count {
+            // the sizes come from the file, they can be anything
+            let at = |delta: usize| {
+                idx.checked_mul(entry_size)
+                    .and_then(|entry| entry.checked_add(delta))
+                    .ok_or_else(|| malformed(format!("section header {} is out of bounds", idx)))
+            };
+            entries.push(Entry {
+                name: self.u32(at(0)?)?,
+                kind: self.u32(at(4)?)? as u32,
+                address: self.word(at(8 + field)?)?,
+                offset: self.word(at(8 + 2 * field)?)?,
+                size: self.word(at(8 + 3 * field)?)?,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+fn read<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+    binary::read_at(reader, offset, size).map_err(malformed)
+}
+
+/// Returns where the sections of an ELF image are, only the headers and the section names are read.
+pub(crate) fn section_headers<R: Read + Seek>(reader: &mut R) -> Result<Vec<SectionHeader>, Error> {
+    let header = read(reader, 0, HEADER_SIZE)?;
+    let image = Image::parse(&header)?;
+    let (shoff, shentsize, shnum, shstrndx) = if image.is_64 {
+        (
+            image.word(0x28)?,
+            image.u16(0x3a)?,
+            image.u16(0x3c)?,
+            image.u16(0x3e)?,
+        )
+    } else {
+        (
+            image.word(0x20)?,
+            image.u16(0x2e)?,
+            image.u16(0x30)?,
+            image.u16(0x32)?,
+        )
+    };
+
+    let table = read(reader, shoff, (shentsize * shnum) as u64)?;
+    let entries = image.with_data(&table).entries(shnum, shentsize)?;
+    let names = match entries.get(shstrndx) {
+        Some(entry) => read(reader, entry.offset, entry.size)?,
+        None => vec![],
+    };
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let name = names
+                .get(entry.name..)
+                .and_then(|s| s.split(|b| *b == 0).next())
+                .unwrap_or_default();
+            SectionHeader {
+                name: String::from_utf8_lossy(name).into_owned(),
+                address: entry.address,
+                offset: entry.offset,
+                // sections without data in the file are empty
+                size: if entry.kind == SHT_NOBITS {
+                    0
+                } else {
+                    entry.size
+                },
+            }
+        })
+        .collect())
+}
+
+/// Returns the content of the section with the given name, None if the image doesn't have it.
+pub(crate) fn read_section<'a>(data: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Error> {
+    let header = match section_headers(&mut Cursor::new(data))?
+        .into_iter()
+        .find(|header| header.name == name && header.size > 0)
+    {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    usize::try_from(header.offset)
+        .ok()
+        .zip(usize::try_from(header.size).ok())
+        .and_then(|(offset, size)| data.get(offset..offset.checked_add(size)?))
+        .map(Some)
+        .ok_or_else(|| malformed(format!("section at {:#x} is out of bounds", header.offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_SIZE: usize = 0x40;
+    const SECTION_HEADER_SIZE: usize = 0x40;
+
+    fn sections(data: &[u8]) -> Result<Vec<SectionHeader>, Error> {
+        section_headers(&mut Cursor::new(data))
+    }
+
+    fn put(data: &mut [u8], offset: usize, value: u64, size: usize) {
+        data[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+
+    // a 64 bits little endian image with a null section, a .comment section and the names table
+    fn image() -> Vec<u8> {
+        let names = b"\0.comment\0.shstrtab\0";
+        let comment = b"GCC: (Debian 12.2.0-14) 12.2.0\0";
+        let mut data = vec![0; HEADER_SIZE];
+        data[..4].copy_from_slice(ELF_MAGIC);
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        put(&mut data, 16, ET_DYN as u64, 2);
+
+        let comment_offset = data.len();
+        data.extend_from_slice(comment);
+        let names_offset = data.len();
+        data.extend_from_slice(names);
+        let shoff = data.len();
+        data.resize(shoff + 3 * SECTION_HEADER_SIZE, 0);
+
+        for (idx, (name, offset, size)) in [
+            (1, comment_offset, comment.len()),
+            (10, names_offset, names.len()),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let header = shoff + (idx + 1) * SECTION_HEADER_SIZE;
+            put(&mut data, header, name, 4);
+            put(&mut data, header + 4, 1, 4);
+            put(&mut data, header + 0x18, offset as u64, 8);
+            put(&mut data, header + 0x20, size as u64, 8);
+        }
+
+        put(&mut data, 0x28, shoff as u64, 8);
+        put(&mut data, 0x3a, SECTION_HEADER_SIZE as u64, 2);
+        put(&mut data, 0x3c, 3, 2);
+        put(&mut data, 0x3e, 2, 2);
+        data
+    }
+
+    #[test]
+    fn reads_sections() {
+        let data = image();
+        assert!(is_executable(&data));
+
+        let names: Vec<String> = sections(&data)
+            .unwrap()
+            .into_iter()
+            .map(|section| section.name)
+            .collect();
+        assert_eq!(names, vec!["", ".comment", ".shstrtab"]);
+        assert_eq!(
+            read_section(&data, ".comment").unwrap(),
+            Some(&b"GCC: (Debian 12.2.0-14) 12.2.0\0"[..])
+        );
+        assert_eq!(read_section(&data, ".go.buildinfo").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_overflowing_headers() {
+        let mut data = vec![0; 128];
+        data[..4].copy_from_slice(ELF_MAGIC);
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        put(&mut data, 0x28, 0xffff_ffff_ffff_fff8, 8);
+        put(&mut data, 0x3a, SECTION_HEADER_SIZE as u64, 2);
+        put(&mut data, 0x3c, 2, 2);
+        assert!(sections(&data).is_err());
+
+        let mut data = image();
+        put(&mut data, 0x3a, 0xffff, 2);
+        put(&mut data, 0x3c, 0xffff, 2);
+        assert!(sections(&data).is_err());
+
+        // a section past the end of the file
+        let mut data = image();
+        let shoff = data.len() - 3 * SECTION_HEADER_SIZE;
+        put(
+            &mut data,
+            shoff + SECTION_HEADER_SIZE + 0x18,
+            u64::MAX - 4,
+            8,
+        );
+        assert!(read_section(&data, ".comment").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_images() {
+        let data = image();
+        assert!(sections(&data[..0x30]).is_err());
+        assert!(sections(&data[..data.len() - 0x20]).is_err());
+        assert!(sections(b"\x7fELF\x03\x01").is_err());
+        assert!(!is_executable(b"\x7fELF"));
+    }
+}
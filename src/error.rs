@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by the scanner, the collectors and the serializers.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A file or a folder could not be accessed.
+    Io { path: PathBuf, source: io::Error },
+    /// The output could not be serialized or written.
+    Write(io::Error),
+    /// An external command could not be started or exited with an error, status is None if it didn't run.
+    CommandFailed {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// Malformed data, source is the file (or the kind of data) being parsed.
+    Parse {
+        source: String,
+        line: Option<usize>,
+        message: String,
+    },
+    /// The system, the input or the selected collectors are not supported.
+    Unsupported(String),
+}
+
+impl Error {
+    pub(crate) fn io<P: Into<PathBuf>>(path: P, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn parse<S: fmt::Display, M: fmt::Display>(source: S, message: M) -> Self {
+        Error::Parse {
+            source: source.to_string(),
+            line: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn parse_line<S: fmt::Display, M: fmt::Display>(
+        source: S,
+        line: usize,
+        message: M,
+    ) -> Self {
+        Error::Parse {
+            source: source.to_string(),
+            line: Some(line),
+            message: message.to_string(),
+        }
+    }
+
+    pub(crate) fn unsupported<M: fmt::Display>(message: M) -> Self {
+        Error::Unsupported(message.to_string())
+    }
+
+    pub(crate) fn command<C: fmt::Display>(command: C, status: Option<i32>, stderr: &[u8]) -> Self {
+        Error::CommandFailed {
+            command: command.to_string(),
+            status,
+            stderr: String::from_utf8_lossy(stderr).trim().to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Write(source) => write!(f, "can't write output: {}", source),
+            Error::CommandFailed {
+                command,
+                status: None,
+                stderr,
+            } => write!(f, "can't run {}: {}", command, stderr),
+            Error::CommandFailed {
+                command,
+                status: Some(status),
+                stderr,
+            } => {
+                write!(f, "{} exited with status {}", command, status)?;
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+            Error::Parse {
+                source,
+                line,
+                message,
+            } => match line {
+                Some(line) => write!(f, "{}:{}: {}", source, line, message),
+                None => write!(f, "{}: {}", source, message),
+            },
+            Error::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Write(source) => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::component::{self, ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::purl::Purl;
+use crate::sbom::{Diagnostic, Sbom};
+use crate::Error;
+
+// https://cyclonedx.org/docs/1.5/json/
+const BOM_FORMAT: &str = "CycloneDX";
+const SPEC_VERSION: &str = "1.5";
+const PROPERTY_PREFIX: &str = "unisbom:";
+const DIAGNOSTIC_PROPERTY: &str = "unisbom:diagnostic";
+// relationships that don't fit the dependency graph
+const CONTAINS_PROPERTY: &str = "unisbom:contains";
+const PROVIDED_BY_PROPERTY: &str = "unisbom:provided-by";
+// properties with a dedicated field of the component
+const BUILTIN_PROPERTIES: &[&str] = &["path", "modified", "origin", "contains", "provided-by"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: Metadata,
+    components: Vec<Component>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Dependency {
+    #[serde(rename = "ref")]
+    bom_ref: String,
+    #[serde(default)]
+    depends_on: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Metadata {
+    timestamp: String,
+    tools: Tools,
+    component: Component,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Property>,
+}
+
+#[derive(Serialize)]
+struct Tools {
+    components: Vec<Component>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OrganizationalEntity {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct License {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct LicenseChoice {
+    license: License,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Hash {
+    alg: String,
+    content: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Property {
+    name: String,
+    value: String,
+}
+
+#[derive(Default, Serialize)]
+struct Component {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    supplier: Option<OrganizationalEntity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publisher: Option<String>,
+    name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<Hash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<LicenseChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpe: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Property>,
+}
+
+// subset of a BOM needed to load its components back, documents of other tools are accepted too
+#[derive(Deserialize)]
+struct InputBom {
+    metadata: Option<InputMetadata>,
+    #[serde(default)]
+    components: Vec<InputComponent>,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct InputMetadata {
+    component: Option<InputComponent>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize)]
+struct InputLicense {
+    id: Option<String>,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InputLicenseChoice {
+    license: Option<InputLicense>,
+    expression: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InputComponent {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(rename = "bom-ref")]
+    bom_ref: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    version: String,
+    supplier: Option<OrganizationalEntity>,
+    publisher: Option<String>,
+    #[serde(default)]
+    hashes: Vec<Hash>,
+    #[serde(default)]
+    licenses: Vec<InputLicenseChoice>,
+    cpe: Option<String>,
+    purl: Option<String>,
+    #[serde(default)]
+    properties: Vec<Property>,
+    // components can be nested, for instance the files of a package
+    #[serde(default)]
+    components: Vec<InputComponent>,
+}
+
+fn component_type(kind: Kind) -> &'static str {
+    match kind {
+        Kind::OS => "operating-system",
+        Kind::Application => "application",
+        Kind::Driver => "device-driver",
+        Kind::Dependency => "library",
+        Kind::Other => "file",
+    }
+}
+
+fn component_kind(kind: &str) -> Kind {
+    match kind {
+        "operating-system" => Kind::OS,
+        "application" => Kind::Application,
+        "device-driver" => Kind::Driver,
+        "library" => Kind::Dependency,
+        _ => Kind::Other,
+    }
+}
+
+// sha256 is SHA-256, algorithms not supported by CycloneDX are skipped
+fn hash_alg(name: &str) -> Option<&'static str> {
+    match name {
+        "md5" => Some("MD5"),
+        "sha1" => Some("SHA-1"),
+        "sha256" => Some("SHA-256"),
+        "sha384" => Some("SHA-384"),
+        "sha512" => Some("SHA-512"),
+        _ => None,
+    }
+}
+
+// bom-refs must be unique within the document, the same component can be reported
+// more than once (for instance multiarch packages) so a counter is appended to duplicates.
+fn bom_ref(comp: &dyn ComponentTrait, seen: &mut HashMap<String, usize>) -> String {
+    let base = match comp.purl() {
+        Some(purl) => purl.to_string(),
+        None => format!("{:?}/{}@{}", comp.kind(), comp.id(), comp.version()).to_lowercase(),
+    };
+    let count = seen.entry(base.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        base
+    } else {
+        format!("{}#{}", base, count)
+    }
+}
+
+fn from_trait(comp: &component::Component, seen: &mut HashMap<String, usize>) -> Component {
+    let publisher = comp.publishers().first().cloned();
+
+    let mut properties = vec![Property {
+        name: "unisbom:path".to_owned(),
+        value: comp.path().to_owned(),
+    }];
+    if comp.modified().timestamp() != 0 {
+        properties.push(Property {
+            name: "unisbom:modified".to_owned(),
+            value: comp.modified().to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+    }
+    if let Some(origin) = comp.origin() {
+        properties.push(Property {
+            name: "unisbom:origin".to_owned(),
+            value: origin.to_owned(),
+        });
+    }
+    for relationship in &comp.relationships {
+        let name = match relationship.kind {
+            RelationshipKind::DependsOn => continue,
+            RelationshipKind::Contains => CONTAINS_PROPERTY,
+            RelationshipKind::ProvidedBy => PROVIDED_BY_PROPERTY,
+        };
+        properties.push(Property {
+            name: name.to_owned(),
+            value: relationship.purl.to_string(),
+        });
+    }
+    for (name, value) in &comp.properties {
+        properties.push(Property {
+            name: format!("{}{}", PROPERTY_PREFIX, name),
+            value: value.clone(),
+        });
+    }
+
+    Component {
+        kind: component_type(comp.kind()),
+        bom_ref: bom_ref(comp, seen),
+        supplier: publisher.clone().map(|name| OrganizationalEntity { name }),
+        publisher,
+        name: comp.name().to_owned(),
+        version: comp.version().to_owned(),
+        hashes: comp
+            .hashes
+            .iter()
+            .filter_map(|(name, digest)| {
+                hash_alg(name).map(|alg| Hash {
+                    alg: alg.to_owned(),
+                    content: digest.clone(),
+                })
+            })
+            .collect(),
+        licenses: comp
+            .license()
+            .map(|name| {
+                vec![LicenseChoice {
+                    license: License {
+                        name: name.to_owned(),
+                    },
+                }]
+            })
+            .unwrap_or_default(),
+        cpe: comp.cpes.first().cloned(),
+        purl: comp.purl().map(|purl| purl.to_string()),
+        properties,
+    }
+}
+
+// the dependency graph only references the components of the document, a package depends on
+// the ones it contains or provides
+fn dependencies(sbom: &Sbom, components: &[Component]) -> Vec<Dependency> {
+    let mut refs: HashMap<String, usize> = HashMap::new();
+    for (idx, comp) in sbom.components.iter().enumerate() {
+        if let Some(purl) = &comp.purl {
+            refs.entry(purl.to_string()).or_insert(idx);
+        }
+    }
+
+    let mut graph: Vec<Vec<String>> = vec![vec![]; components.len()];
+    for (idx, comp) in sbom.components.iter().enumerate() {
+        for relationship in &comp.relationships {
+            let target = match refs.get(&relationship.purl.to_string()) {
+                Some(target) => *target,
+                None => continue,
+            };
+            let (from, to) = match relationship.kind {
+                RelationshipKind::DependsOn | RelationshipKind::Contains => (idx, target),
+                RelationshipKind::ProvidedBy => (target, idx),
+            };
+            let to = &components[to].bom_ref;
+            if !graph[from].contains(to) {
+                graph[from].push(to.clone());
+            }
+        }
+    }
+
+    components
+        .iter()
+        .zip(graph)
+        .filter(|(_, depends_on)| !depends_on.is_empty())
+        .map(|(output, depends_on)| Dependency {
+            bom_ref: output.bom_ref.clone(),
+            depends_on,
+        })
+        .collect()
+}
+
+/// Writes a CycloneDX 1.5 JSON BOM, the diagnostics are metadata properties. The dependency graph
+/// also links the packages to the ones they contain or provide, which are component properties too.
+pub fn to_json<T: std::io::Write>(sbom: &Sbom, mut writer: T) -> Result<(), Error> {
+    let mut seen = HashMap::new();
+    let host = &sbom.host;
+    let components: Vec<Component> = sbom
+        .components
+        .iter()
+        .map(|c| from_trait(c, &mut seen))
+        .collect();
+
+    let bom = Bom {
+        bom_format: BOM_FORMAT,
+        spec_version: SPEC_VERSION,
+        serial_number: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        version: 1,
+        metadata: Metadata {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            tools: Tools {
+                components: vec![Component {
+                    kind: "application",
+                    bom_ref: env!("CARGO_PKG_NAME").to_owned(),
+                    name: env!("CARGO_PKG_NAME").to_owned(),
+                    version: env!("CARGO_PKG_VERSION").to_owned(),
+                    ..Default::default()
+                }],
+            },
+            component: Component {
+                kind: "device",
+                bom_ref: format!("host/{}", host),
+                name: host.to_owned(),
+                ..Default::default()
+            },
+            properties: sbom
+                .diagnostics
+                .iter()
+                .map(|diagnostic| Property {
+                    name: DIAGNOSTIC_PROPERTY.to_owned(),
+                    value: format!("{}: {}", diagnostic.collector, diagnostic.message),
+                })
+                .collect(),
+        },
+        dependencies: dependencies(sbom, &components),
+        components,
+    };
+
+    let json = serde_json::to_string(&bom).map_err(|e| Error::Write(e.into()))?;
+
+    writer.write_all(json.as_bytes()).map_err(Error::Write)
+}
+
+// the index of each component is recorded by bom-ref to resolve the dependency graph
+fn to_component(
+    input: InputComponent,
+    components: &mut Vec<component::Component>,
+    refs: &mut HashMap<String, usize>,
+) {
+    let property = |name: &str| {
+        input
+            .properties
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.value.clone())
+    };
+
+    let purl = input
+        .purl
+        .as_ref()
+        .and_then(|purl| match purl.parse::<Purl>() {
+            Ok(purl) => Some(purl),
+            Err(e) => {
+                log::warn!("ignoring purl of {}: {}", &input.name, e);
+                None
+            }
+        });
+
+    let relationships = input
+        .properties
+        .iter()
+        .filter_map(|p| {
+            let kind = match p.name.as_str() {
+                CONTAINS_PROPERTY => RelationshipKind::Contains,
+                PROVIDED_BY_PROPERTY => RelationshipKind::ProvidedBy,
+                _ => return None,
+            };
+            p.value
+                .parse::<Purl>()
+                .ok()
+                .map(|purl| Relationship::new(kind, purl))
+        })
+        .collect();
+
+    if let Some(bom_ref) = &input.bom_ref {
+        refs.insert(bom_ref.clone(), components.len());
+    }
+    components.push(component::Component {
+        kind: component_kind(&input.kind),
+        id: input.name.clone(),
+        name: input.name.clone(),
+        version: input.version.clone(),
+        path: property("unisbom:path").unwrap_or_default(),
+        modified: property("unisbom:modified")
+            .and_then(|modified| DateTime::parse_from_rfc3339(&modified).ok())
+            .map(|modified| modified.with_timezone(&Utc))
+            .unwrap_or_default(),
+        publishers: input
+            .publisher
+            .clone()
+            .or_else(|| input.supplier.as_ref().map(|s| s.name.clone()))
+            .into_iter()
+            .collect(),
+        purl,
+        cpes: input.cpe.clone().into_iter().collect(),
+        license: input.licenses.iter().find_map(|choice| {
+            choice.expression.clone().or_else(|| {
+                choice
+                    .license
+                    .as_ref()
+                    .and_then(|l| l.id.clone().or_else(|| l.name.clone()))
+            })
+        }),
+        origin: property("unisbom:origin"),
+        properties: input
+            .properties
+            .iter()
+            .filter_map(|p| {
+                p.name
+                    .strip_prefix(PROPERTY_PREFIX)
+                    .filter(|name| !BUILTIN_PROPERTIES.contains(name))
+                    .map(|name| (name.to_owned(), p.value.clone()))
+            })
+            .collect(),
+        relationships,
+        hashes: input
+            .hashes
+            .iter()
+            .map(|h| (h.alg.to_lowercase().replace('-', ""), h.content.clone()))
+            .collect(),
+        files: vec![],
+        vulnerabilities: vec![],
+    });
+
+    for child in input.components {
+        to_component(child, components, refs);
+    }
+}
+
+/// Loads the components of a CycloneDX JSON BOM.
+pub fn from_json(data: &str) -> Result<Sbom, Error> {
+    let bom: InputBom =
+        serde_json::from_str(data).map_err(|e| Error::parse("cyclonedx json", e))?;
+
+    let mut sbom = Sbom::default();
+    if let Some(metadata) = bom.metadata {
+        sbom.host = metadata.component.map(|host| host.name).unwrap_or_default();
+        sbom.diagnostics = metadata
+            .properties
+            .into_iter()
+            .filter(|p| p.name == DIAGNOSTIC_PROPERTY)
+            .filter_map(|p| {
+                p.value
+                    .split_once(": ")
+                    .map(|(collector, message)| Diagnostic {
+                        collector: collector.to_owned(),
+                        message: message.to_owned(),
+                    })
+            })
+            .collect();
+    }
+
+    let mut refs = HashMap::new();
+    for input in bom.components {
+        to_component(input, &mut sbom.components, &mut refs);
+    }
+
+    for dependency in bom.dependencies {
+        let idx = match refs.get(&dependency.bom_ref) {
+            Some(idx) => *idx,
+            None => continue,
+        };
+        for target in &dependency.depends_on {
+            let target = match refs.get(target) {
+                Some(target) => &sbom.components[*target],
+                None => continue,
+            };
+            let purl = match &target.purl {
+                Some(purl) => purl.clone(),
+                None => continue,
+            };
+            // contained and provided packages are in the graph too
+            let source = &sbom.components[idx];
+            let contains = source
+                .relationships
+                .iter()
+                .any(|r| r.kind == RelationshipKind::Contains && r.purl == purl);
+            let provides = source.purl.as_ref().is_some_and(|source| {
+                target
+                    .relationships
+                    .iter()
+                    .any(|r| r.kind == RelationshipKind::ProvidedBy && r.purl == *source)
+            });
+            if !contains && !provides {
+                sbom.components[idx]
+                    .relationships
+                    .push(Relationship::new(RelationshipKind::DependsOn, purl));
+            }
+        }
+    }
+
+    Ok(sbom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn component(kind: Kind, name: &str, purl: Purl) -> component::Component {
+        component::Component {
+            kind,
+            name: name.to_owned(),
+            id: name.to_owned(),
+            version: purl.version.clone().unwrap_or_default(),
+            path: format!("/opt/{}", name),
+            purl: Some(purl),
+            ..Default::default()
+        }
+    }
+
+    fn write(sbom: &Sbom) -> serde_json::Value {
+        let mut json = vec![];
+        to_json(sbom, &mut json).unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    fn relationships(comp: &component::Component) -> Vec<(RelationshipKind, String)> {
+        let mut relationships: Vec<(RelationshipKind, String)> = comp
+            .relationships
+            .iter()
+            .map(|rel| (rel.kind, rel.purl.to_string()))
+            .collect();
+        relationships.sort_by_key(|(kind, purl)| (format!("{:?}", kind), purl.clone()));
+        relationships
+    }
+
+    #[test]
+    fn writes_bom() {
+        let openssl = Purl::new("deb", "openssl")
+            .with_namespace("debian")
+            .with_version("3.0.11-1")
+            .with_qualifier("arch", "amd64");
+        let libssl = Purl::new("deb", "libssl3")
+            .with_namespace("debian")
+            .with_version("3.0.11-1")
+            .with_qualifier("arch", "amd64");
+        let mut sbom = Sbom {
+            host: "build-01".to_owned(),
+            components: vec![
+                component(
+                    Kind::OS,
+                    "debian",
+                    Purl::new("generic", "debian").with_version("12"),
+                ),
+                component(Kind::Application, "openssl", openssl),
+                component(Kind::Dependency, "libssl3", libssl.clone()),
+                component(Kind::Dependency, "libssl3", libssl.clone()),
+                component(Kind::Driver, "e1000e", Purl::new("generic", "e1000e")),
+                component(Kind::Other, "notes", Purl::new("generic", "notes")),
+            ],
+            diagnostics: vec![Diagnostic {
+                collector: "dpkg".to_owned(),
+                message: "no status file".to_owned(),
+            }],
+        };
+        sbom.components[1].relationships =
+            vec![Relationship::new(RelationshipKind::DependsOn, libssl)];
+        sbom.components[1].hashes = BTreeMap::from([
+            ("sha256".to_owned(), "ab".repeat(32)),
+            ("crc32".to_owned(), "deadbeef".to_owned()),
+        ]);
+        sbom.components[5].purl = None;
+        sbom.components[5].version = "1.0".to_owned();
+
+        let bom = write(&sbom);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], "1.5");
+        assert_eq!(
+            bom["metadata"]["component"],
+            serde_json::json!({"type": "device", "bom-ref": "host/build-01", "name": "build-01"})
+        );
+        assert_eq!(
+            bom["metadata"]["properties"],
+            serde_json::json!([{"name": "unisbom:diagnostic", "value": "dpkg: no status file"}])
+        );
+
+        let components = bom["components"].as_array().unwrap();
+        let fields = |name: &str| -> Vec<&str> {
+            components
+                .iter()
+                .map(|c| c[name].as_str().unwrap())
+                .collect()
+        };
+        assert_eq!(
+            fields("type"),
+            vec![
+                "operating-system",
+                "application",
+                "library",
+                "library",
+                "device-driver",
+                "file"
+            ]
+        );
+        // duplicates are numbered, components without a purl use their kind and id
+        let refs = fields("bom-ref");
+        assert_eq!(
+            refs,
+            vec![
+                "pkg:generic/debian@12",
+                "pkg:deb/debian/openssl@3.0.11-1?arch=amd64",
+                "pkg:deb/debian/libssl3@3.0.11-1?arch=amd64",
+                "pkg:deb/debian/libssl3@3.0.11-1?arch=amd64#2",
+                "pkg:generic/e1000e",
+                "other/notes@1.0",
+            ]
+        );
+        let again = write(&sbom);
+        assert_eq!(again["components"], bom["components"]);
+        assert_ne!(again["serialNumber"], bom["serialNumber"]);
+
+        // unsupported algorithms are skipped
+        assert_eq!(
+            components[1]["hashes"],
+            serde_json::json!([{"alg": "SHA-256", "content": "ab".repeat(32)}])
+        );
+        assert!(components[0].get("hashes").is_none());
+        assert_eq!(
+            bom["dependencies"],
+            serde_json::json!([{"ref": refs[1], "dependsOn": [refs[2]]}])
+        );
+
+        let loaded = from_json(&bom.to_string()).unwrap();
+        let kinds: Vec<Kind> = loaded.components.iter().map(|c| c.kind).collect();
+        let expected: Vec<Kind> = sbom.components.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, expected);
+    }
+
+    #[test]
+    fn graph_has_contained_and_provided_packages() {
+        let deb = Purl::new("deb", "python3-six").with_version("1.16.0-4");
+        let six = Purl::new("pypi", "six").with_version("1.16.0");
+        let app = Purl::new("generic", "app").with_version("2.0");
+        let lib = Purl::new("npm", "lib").with_version("1.0.0");
+        let dep = Purl::new("npm", "dep").with_version("1.0.0");
+
+        let mut sbom = Sbom {
+            host: "host".to_owned(),
+            components: vec![
+                component(Kind::Application, "python3-six", deb.clone()),
+                component(Kind::Dependency, "six", six),
+                component(Kind::Application, "app", app),
+                component(Kind::Dependency, "lib", lib.clone()),
+                component(Kind::Dependency, "dep", dep.clone()),
+            ],
+            diagnostics: vec![],
+        };
+        sbom.components[1].relationships =
+            vec![Relationship::new(RelationshipKind::ProvidedBy, deb)];
+        sbom.components[2].relationships = vec![
+            Relationship::new(RelationshipKind::Contains, lib.clone()),
+            Relationship::new(RelationshipKind::Contains, dep.clone()),
+        ];
+        sbom.components[3].relationships =
+            vec![Relationship::new(RelationshipKind::DependsOn, dep)];
+
+        let bom = write(&sbom);
+        assert_eq!(
+            bom["dependencies"],
+            serde_json::json!([
+                {"ref": "pkg:deb/python3-six@1.16.0-4", "dependsOn": ["pkg:pypi/six@1.16.0"]},
+                {"ref": "pkg:generic/app@2.0", "dependsOn": ["pkg:npm/lib@1.0.0", "pkg:npm/dep@1.0.0"]},
+                {"ref": "pkg:npm/lib@1.0.0", "dependsOn": ["pkg:npm/dep@1.0.0"]},
+            ])
+        );
+
+        // the graph doesn't add dependencies to the contained and provided packages
+        let loaded = from_json(&bom.to_string()).unwrap();
+        assert_eq!(loaded.components.len(), sbom.components.len());
+        for (loaded, comp) in loaded.components.iter().zip(&sbom.components) {
+            assert_eq!(relationships(loaded), relationships(comp), "{}", comp.name);
+        }
+    }
+}
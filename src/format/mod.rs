@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::component::{Component, ComponentTrait};
+use crate::sbom::Sbom;
+use crate::utils;
+use crate::Error;
+
+pub mod cyclonedx;
+pub mod spdx;
+
+/// Writes a summary of each component followed by the problems reported by the collectors.
+pub fn to_text<T: std::io::Write>(sbom: &Sbom, mut writer: T) -> Result<(), Error> {
+    let mut total = 0;
+    let mut vulnerable = 0;
+
+    for comp in &sbom.components {
+        writer
+            .write_all(
+                format!(
+                    "<{}> [{:?}] name={} version={} path={}\n",
+                    comp.modified(),
+                    comp.kind(),
+                    comp.name(),
+                    comp.version(),
+                    comp.path()
+                )
+                .as_bytes(),
+            )
+            .map_err(Error::Write)?;
+
+        for finding in comp.vulnerabilities() {
+            let fixed = match &finding.fixed {
+                Some(fixed) => format!(" (fixed in {})", fixed),
+                None => "".to_owned(),
+            };
+            writer
+                .write_all(format!("  ! {}{} {}\n", finding.id, fixed, finding.summary).as_bytes())
+                .map_err(Error::Write)?;
+        }
+
+        if !comp.vulnerabilities().is_empty() {
+            total += comp.vulnerabilities().len();
+            vulnerable += 1;
+        }
+    }
+
+    if total > 0 {
+        writer
+            .write_all(
+                format!(
+                    "\n{} known vulnerabilities in {} components\n",
+                    total, vulnerable
+                )
+                .as_bytes(),
+            )
+            .map_err(Error::Write)?;
+    }
+
+    if !sbom.diagnostics.is_empty() {
+        let mut text = format!(
+            "\n{} problems prevented collecting some components:\n",
+            sbom.diagnostics.len()
+        );
+        for diagnostic in &sbom.diagnostics {
+            text += &format!("  {}: {}\n", diagnostic.collector, diagnostic.message);
+        }
+        writer.write_all(text.as_bytes()).map_err(Error::Write)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the host, the components with all their details and the diagnostics as JSON.
+pub fn to_json<T: std::io::Write>(sbom: &Sbom, mut writer: T) -> Result<(), Error> {
+    let json = serde_json::to_string(sbom).map_err(|e| Error::Write(e.into()))?;
+
+    writer.write_all(json.as_bytes()).map_err(Error::Write)
+}
+
+// older versions wrote the components only
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Document {
+    Sbom(Sbom),
+    Components(Vec<Component>),
+}
+
+/// Parses the document written by [`to_json`].
+pub fn from_json(data: &str) -> Result<Sbom, Error> {
+    let document: Document =
+        serde_json::from_str(data).map_err(|e| Error::parse("unisbom json", e))?;
+
+    Ok(match document {
+        Document::Sbom(sbom) => sbom,
+        Document::Components(components) => Sbom {
+            components,
+            ..Default::default()
+        },
+    })
+}
+
+/// Loads a document produced by unisbom or by another tool, the format is detected from the content:
+/// native JSON, CycloneDX JSON, SPDX JSON or SPDX tag-value.
+pub fn parse(data: &str) -> Result<Sbom, Error> {
+    let data = data.trim_start();
+    if data.starts_with("SPDXVersion:") {
+        return spdx::from_tag_value(data);
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_str(data).map_err(|e| Error::parse("json document", e))?;
+
+    if value.get("bomFormat").and_then(|f| f.as_str()) == Some("CycloneDX") {
+        cyclonedx::from_json(data)
+    } else if value.get("spdxVersion").is_some() {
+        spdx::from_json(data)
+    } else if value.is_array() || value.get("components").is_some() {
+        from_json(data)
+    } else {
+        Err(Error::unsupported(
+            "unsupported document, expected unisbom, CycloneDX or SPDX json",
+        ))
+    }
+}
+
+/// Loads a file in any of the formats accepted by [`parse`].
+pub fn load(path: &Path) -> Result<Sbom, Error> {
+    parse(&utils::read_text(path)?).map_err(|e| match e {
+        Error::Parse { line, message, .. } => Error::Parse {
+            source: path.display().to_string(),
+            line,
+            message,
+        },
+        e => e,
+    })
+}
@@ -0,0 +1,999 @@
+use std::collections::HashMap;
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::component::{self, Component, ComponentTrait, Kind, RelationshipKind};
+use crate::purl::Purl;
+use crate::sbom::{Diagnostic, Sbom};
+use crate::Error;
+
+// https://spdx.github.io/spdx-spec/v2.3/
+const SPDX_VERSION: &str = "SPDX-2.3";
+const DATA_LICENSE: &str = "CC0-1.0";
+const DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+const HOST_ID: &str = "SPDXRef-Host";
+const NOASSERTION: &str = "NOASSERTION";
+// first line of the creation comment listing the diagnostics, one per line
+const DIAGNOSTICS_HEADER: &str = "Problems reported by the collectors:";
+// tag-value has no escaping, the end of a text value is written as an entity
+const TEXT_END: &str = "</text>";
+const ESCAPED_TEXT_END: &str = "&lt;/text&gt;";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreationInfo {
+    created: String,
+    creators: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalRef {
+    reference_category: &'static str,
+    reference_type: &'static str,
+    reference_locator: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Checksum {
+    algorithm: &'static str,
+    checksum_value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Package {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    version_info: String,
+    supplier: String,
+    download_location: &'static str,
+    files_analyzed: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<Checksum>,
+    primary_package_purpose: &'static str,
+    license_declared: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license_comments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    external_refs: Vec<ExternalRef>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Relationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+/// Shared model of the document, serialized either as JSON or as tag-value.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    document_namespace: String,
+    creation_info: CreationInfo,
+    packages: Vec<Package>,
+    relationships: Vec<Relationship>,
+}
+
+// subset of a document needed to load its packages back, documents of other tools are accepted too
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputDocument {
+    creation_info: Option<InputCreationInfo>,
+    #[serde(default)]
+    packages: Vec<InputPackage>,
+    #[serde(default)]
+    relationships: Vec<InputRelationship>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputRelationship {
+    spdx_element_id: String,
+    relationship_type: String,
+    related_spdx_element: String,
+}
+
+#[derive(Deserialize)]
+struct InputCreationInfo {
+    comment: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputExternalRef {
+    reference_type: String,
+    reference_locator: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputChecksum {
+    algorithm: String,
+    checksum_value: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct InputPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    version_info: String,
+    supplier: Option<String>,
+    checksums: Vec<InputChecksum>,
+    primary_package_purpose: Option<String>,
+    license_declared: Option<String>,
+    license_comments: Option<String>,
+    comment: Option<String>,
+    external_refs: Vec<InputExternalRef>,
+}
+
+fn package_purpose(kind: Kind) -> &'static str {
+    match kind {
+        Kind::OS => "OPERATING-SYSTEM",
+        Kind::Application => "APPLICATION",
+        Kind::Dependency => "LIBRARY",
+        Kind::Driver | Kind::Other => "OTHER",
+    }
+}
+
+fn package_kind(purpose: Option<&str>) -> Kind {
+    match purpose {
+        Some("OPERATING-SYSTEM") => Kind::OS,
+        Some("APPLICATION") => Kind::Application,
+        Some("LIBRARY") => Kind::Dependency,
+        _ => Kind::Other,
+    }
+}
+
+// sha256 is SHA256, algorithms not supported by SPDX are skipped
+fn checksum_algorithm(name: &str) -> Option<&'static str> {
+    match name {
+        "md5" => Some("MD5"),
+        "sha1" => Some("SHA1"),
+        "sha256" => Some("SHA256"),
+        "sha384" => Some("SHA384"),
+        "sha512" => Some("SHA512"),
+        _ => None,
+    }
+}
+
+// the installing package contains the files of the component
+fn relationship_type(kind: RelationshipKind) -> &'static str {
+    match kind {
+        RelationshipKind::DependsOn => "DEPENDS_ON",
+        RelationshipKind::Contains => "CONTAINS",
+        RelationshipKind::ProvidedBy => "CONTAINED_BY",
+    }
+}
+
+fn relationship_kind(kind: &str) -> Option<RelationshipKind> {
+    match kind {
+        "DEPENDS_ON" => Some(RelationshipKind::DependsOn),
+        "CONTAINS" => Some(RelationshipKind::Contains),
+        "CONTAINED_BY" => Some(RelationshipKind::ProvidedBy),
+        _ => None,
+    }
+}
+
+// NOASSERTION and NONE are the same as a missing value
+fn assertion(value: Option<&str>) -> Option<&str> {
+    value.filter(|v| !v.is_empty() && *v != NOASSERTION && *v != "NONE")
+}
+
+// "Organization: name (email)", maintainers are written as "Name <email>" by the package managers
+fn supplier(publisher: &str) -> String {
+    match publisher
+        .strip_suffix('>')
+        .and_then(|rest| rest.rsplit_once('<'))
+    {
+        Some((name, email)) => format!("Organization: {} ({})", name.trim(), email.trim()),
+        None => format!("Organization: {}", publisher),
+    }
+}
+
+fn publisher(supplier: &str) -> String {
+    let name = supplier
+        .trim_start_matches("Organization:")
+        .trim_start_matches("Person:")
+        .trim();
+    match name
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .filter(|(_, email)| email.contains('@'))
+    {
+        Some((name, email)) => format!("{} <{}>", name.trim(), email.trim()),
+        None => name.to_owned(),
+    }
+}
+
+// free form values of the tag-value format, which can span multiple lines
+fn text(value: &str) -> String {
+    format!(
+        "<text>{}{}",
+        value.replace(TEXT_END, ESCAPED_TEXT_END),
+        TEXT_END
+    )
+}
+
+// the namespace is a URI, the host name can contain any character
+fn namespace(host: &str) -> String {
+    let host: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    format!(
+        "https://spdx.org/spdxdocs/{}-{}-{}",
+        env!("CARGO_PKG_NAME"),
+        host,
+        uuid::Uuid::new_v4()
+    )
+}
+
+// SPDX identifiers can only contain letters, numbers, dots and dashes and must be unique
+// within the document, a counter is appended to duplicates.
+fn spdx_id(comp: &dyn ComponentTrait, seen: &mut HashMap<String, usize>) -> String {
+    let base: String = format!("SPDXRef-{:?}-{}-{}", comp.kind(), comp.id(), comp.version())
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+
+    let count = seen.entry(base.clone()).or_insert(0);
+    *count += 1;
+    if *count == 1 {
+        base
+    } else {
+        format!("{}-{}", base, count)
+    }
+}
+
+fn external_refs(comp: &Component) -> Vec<ExternalRef> {
+    let mut refs = vec![];
+
+    if let Some(purl) = comp.purl() {
+        refs.push(ExternalRef {
+            reference_category: "PACKAGE-MANAGER",
+            reference_type: "purl",
+            reference_locator: purl.to_string(),
+        });
+    }
+
+    for cpe in &comp.cpes {
+        refs.push(ExternalRef {
+            reference_category: "SECURITY",
+            reference_type: "cpe23Type",
+            reference_locator: cpe.clone(),
+        });
+    }
+
+    refs
+}
+
+fn diagnostics_comment(diagnostics: &[Diagnostic]) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let mut lines = vec![DIAGNOSTICS_HEADER.to_owned()];
+    for diagnostic in diagnostics {
+        lines.push(format!("{}: {}", diagnostic.collector, diagnostic.message));
+    }
+    Some(lines.join("\n"))
+}
+
+fn parse_diagnostics(comment: &str) -> Vec<Diagnostic> {
+    let mut lines = comment.lines();
+    if lines.next().map(|line| line.trim()) != Some(DIAGNOSTICS_HEADER) {
+        return vec![];
+    }
+
+    lines
+        .filter_map(|line| line.split_once(": "))
+        .map(|(collector, message)| Diagnostic {
+            collector: collector.trim().to_owned(),
+            message: message.to_owned(),
+        })
+        .collect()
+}
+
+impl Document {
+    fn new(sbom: &Sbom) -> Self {
+        let host = &sbom.host;
+        let mut seen = HashMap::new();
+        // first package of each package URL
+        let mut ids: HashMap<String, String> = HashMap::new();
+        let mut packages = vec![Package {
+            name: host.to_owned(),
+            spdx_id: HOST_ID.to_owned(),
+            version_info: "".to_owned(),
+            supplier: NOASSERTION.to_owned(),
+            download_location: NOASSERTION,
+            files_analyzed: false,
+            checksums: vec![],
+            primary_package_purpose: "DEVICE",
+            license_declared: NOASSERTION,
+            license_comments: None,
+            comment: Some("The system scanned by unisbom.".to_owned()),
+            external_refs: vec![],
+        }];
+        let mut relationships = vec![Relationship {
+            spdx_element_id: DOCUMENT_ID.to_owned(),
+            relationship_type: "DESCRIBES",
+            related_spdx_element: HOST_ID.to_owned(),
+        }];
+
+        for comp in &sbom.components {
+            let package = Package {
+                name: comp.name().to_owned(),
+                spdx_id: spdx_id(comp, &mut seen),
+                version_info: comp.version().to_owned(),
+                supplier: comp
+                    .publishers()
+                    .first()
+                    .map(|publisher| supplier(publisher))
+                    .unwrap_or_else(|| NOASSERTION.to_owned()),
+                download_location: NOASSERTION,
+                files_analyzed: false,
+                checksums: comp
+                    .hashes
+                    .iter()
+                    .filter_map(|(name, digest)| {
+                        checksum_algorithm(name).map(|algorithm| Checksum {
+                            algorithm,
+                            checksum_value: digest.clone(),
+                        })
+                    })
+                    .collect(),
+                primary_package_purpose: package_purpose(comp.kind()),
+                // declared licenses are free form and not necessarily valid SPDX expressions
+                license_declared: NOASSERTION,
+                license_comments: comp
+                    .license()
+                    .map(|license| format!("Declared license: {}", license)),
+                comment: Some(format!("Installed at {}", comp.path())),
+                external_refs: external_refs(comp),
+            };
+
+            relationships.push(Relationship {
+                spdx_element_id: HOST_ID.to_owned(),
+                relationship_type: "CONTAINS",
+                related_spdx_element: package.spdx_id.clone(),
+            });
+            if let Some(purl) = comp.purl() {
+                ids.entry(purl.to_string())
+                    .or_insert_with(|| package.spdx_id.clone());
+            }
+            packages.push(package);
+        }
+
+        // only the relationships between the packages of the document
+        for (comp, package) in sbom.components.iter().zip(&packages[1..]) {
+            for relationship in &comp.relationships {
+                if let Some(related) = ids.get(&relationship.purl.to_string()) {
+                    relationships.push(Relationship {
+                        spdx_element_id: package.spdx_id.clone(),
+                        relationship_type: relationship_type(relationship.kind),
+                        related_spdx_element: related.clone(),
+                    });
+                }
+            }
+        }
+
+        Self {
+            spdx_version: SPDX_VERSION,
+            data_license: DATA_LICENSE,
+            spdx_id: DOCUMENT_ID,
+            name: format!("{}-sbom", host),
+            document_namespace: namespace(host),
+            creation_info: CreationInfo {
+                created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                creators: vec![format!(
+                    "Tool: {}-{}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                )],
+                comment: diagnostics_comment(&sbom.diagnostics),
+            },
+            packages,
+            relationships,
+        }
+    }
+
+    fn to_tag_value(&self) -> String {
+        let mut lines = vec![
+            format!("SPDXVersion: {}", self.spdx_version),
+            format!("DataLicense: {}", self.data_license),
+            format!("SPDXID: {}", self.spdx_id),
+            format!("DocumentName: {}", self.name),
+            format!("DocumentNamespace: {}", self.document_namespace),
+        ];
+        for creator in &self.creation_info.creators {
+            lines.push(format!("Creator: {}", creator));
+        }
+        lines.push(format!("Created: {}", self.creation_info.created));
+        if let Some(comment) = &self.creation_info.comment {
+            lines.push(format!("CreatorComment: {}", text(comment)));
+        }
+
+        for package in &self.packages {
+            lines.push("".to_owned());
+            lines.push(format!("PackageName: {}", package.name));
+            lines.push(format!("SPDXID: {}", package.spdx_id));
+            if !package.version_info.is_empty() {
+                lines.push(format!("PackageVersion: {}", package.version_info));
+            }
+            lines.push(format!("PackageSupplier: {}", package.supplier));
+            lines.push(format!(
+                "PackageDownloadLocation: {}",
+                package.download_location
+            ));
+            lines.push(format!("FilesAnalyzed: {}", package.files_analyzed));
+            for checksum in &package.checksums {
+                lines.push(format!(
+                    "PackageChecksum: {}: {}",
+                    checksum.algorithm, checksum.checksum_value
+                ));
+            }
+            lines.push(format!(
+                "PrimaryPackagePurpose: {}",
+                package.primary_package_purpose
+            ));
+            lines.push(format!(
+                "PackageLicenseDeclared: {}",
+                package.license_declared
+            ));
+            if let Some(comments) = &package.license_comments {
+                lines.push(format!("PackageLicenseComments: {}", text(comments)));
+            }
+            if let Some(comment) = &package.comment {
+                lines.push(format!("PackageComment: {}", text(comment)));
+            }
+            for external_ref in &package.external_refs {
+                lines.push(format!(
+                    "ExternalRef: {} {} {}",
+                    external_ref.reference_category,
+                    external_ref.reference_type,
+                    external_ref.reference_locator
+                ));
+            }
+        }
+
+        lines.push("".to_owned());
+        for relationship in &self.relationships {
+            lines.push(format!(
+                "Relationship: {} {} {}",
+                relationship.spdx_element_id,
+                relationship.relationship_type,
+                relationship.related_spdx_element
+            ));
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Writes an SPDX 2.3 JSON document, the diagnostics are listed in the creation comment.
+pub fn to_json<T: std::io::Write>(sbom: &Sbom, mut writer: T) -> Result<(), Error> {
+    let document = Document::new(sbom);
+
+    let json = serde_json::to_string(&document).map_err(|e| Error::Write(e.into()))?;
+
+    writer.write_all(json.as_bytes()).map_err(Error::Write)
+}
+
+/// Writes an SPDX 2.3 tag-value document, the diagnostics are listed in the creator comment.
+pub fn to_tag_value<T: std::io::Write>(sbom: &Sbom, mut writer: T) -> Result<(), Error> {
+    let document = Document::new(sbom);
+
+    writer
+        .write_all(document.to_tag_value().as_bytes())
+        .map_err(Error::Write)
+}
+
+impl InputPackage {
+    // the scanned system itself is not a component
+    fn is_host(&self) -> bool {
+        self.spdx_id == HOST_ID || self.primary_package_purpose.as_deref() == Some("DEVICE")
+    }
+
+    fn into_component(self) -> Component {
+        let reference = |kind: &str| {
+            self.external_refs
+                .iter()
+                .filter(|r| r.reference_type == kind)
+                .map(|r| r.reference_locator.clone())
+                .collect::<Vec<String>>()
+        };
+
+        let purl =
+            reference("purl")
+                .into_iter()
+                .next()
+                .and_then(|purl| match purl.parse::<Purl>() {
+                    Ok(purl) => Some(purl),
+                    Err(e) => {
+                        log::warn!("ignoring purl of {}: {}", &self.name, e);
+                        None
+                    }
+                });
+
+        Component {
+            kind: package_kind(self.primary_package_purpose.as_deref()),
+            id: self.name.clone(),
+            name: self.name.clone(),
+            version: self.version_info.clone(),
+            path: self
+                .comment
+                .as_deref()
+                .and_then(|c| c.strip_prefix("Installed at "))
+                .unwrap_or_default()
+                .to_owned(),
+            modified: Default::default(),
+            publishers: assertion(self.supplier.as_deref())
+                .map(publisher)
+                .into_iter()
+                .collect(),
+            purl,
+            cpes: reference("cpe23Type"),
+            license: assertion(self.license_declared.as_deref())
+                .or_else(|| {
+                    self.license_comments
+                        .as_deref()
+                        .and_then(|c| c.strip_prefix("Declared license: "))
+                })
+                .map(|l| l.to_owned()),
+            origin: None,
+            properties: Default::default(),
+            relationships: vec![],
+            hashes: self
+                .checksums
+                .iter()
+                .map(|c| {
+                    (
+                        c.algorithm.to_lowercase().replace('-', ""),
+                        c.checksum_value.to_lowercase(),
+                    )
+                })
+                .collect(),
+            files: vec![],
+            vulnerabilities: vec![],
+        }
+    }
+}
+
+fn to_sbom(
+    packages: Vec<InputPackage>,
+    relationships: Vec<InputRelationship>,
+    comment: Option<&str>,
+) -> Sbom {
+    let mut sbom = Sbom {
+        diagnostics: comment.map(parse_diagnostics).unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let mut ids = HashMap::new();
+    for package in packages {
+        if package.is_host() {
+            sbom.host = package.name;
+        } else {
+            ids.insert(package.spdx_id.clone(), sbom.components.len());
+            sbom.components.push(package.into_component());
+        }
+    }
+
+    // the host contains every package, only the relationships between packages are kept
+    for relationship in relationships {
+        let kind = match relationship_kind(&relationship.relationship_type) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        if let (Some(source), Some(target)) = (
+            ids.get(&relationship.spdx_element_id),
+            ids.get(&relationship.related_spdx_element),
+        ) {
+            if let Some(purl) = sbom.components[*target].purl.clone() {
+                sbom.components[*source]
+                    .relationships
+                    .push(component::Relationship::new(kind, purl));
+            }
+        }
+    }
+
+    sbom
+}
+
+/// Loads the packages of an SPDX JSON document.
+pub fn from_json(data: &str) -> Result<Sbom, Error> {
+    let document: InputDocument =
+        serde_json::from_str(data).map_err(|e| Error::parse("spdx json", e))?;
+
+    let comment = document.creation_info.and_then(|info| info.comment);
+
+    Ok(to_sbom(
+        document.packages,
+        document.relationships,
+        comment.as_deref(),
+    ))
+}
+
+/// Loads the packages of an SPDX tag-value document.
+pub fn from_tag_value(data: &str) -> Result<Sbom, Error> {
+    let mut packages: Vec<InputPackage> = vec![];
+    let mut relationships = vec![];
+    let mut comment = None;
+    let mut lines = data.lines();
+
+    while let Some(line) = lines.next() {
+        let (tag, value) = match line.split_once(':') {
+            Some((tag, value)) => (tag.trim(), value.trim()),
+            None => continue,
+        };
+
+        // <text> values can span multiple lines
+        let mut value = value.to_owned();
+        if value.starts_with("<text>") {
+            while !value.contains(TEXT_END) {
+                match lines.next() {
+                    Some(next) => {
+                        value.push('\n');
+                        value.push_str(next);
+                    }
+                    None => break,
+                }
+            }
+            value = value
+                .trim_start_matches("<text>")
+                .trim_end_matches(TEXT_END)
+                .replace(ESCAPED_TEXT_END, TEXT_END);
+        }
+
+        if tag == "CreatorComment" {
+            comment = Some(value);
+            continue;
+        }
+
+        if tag == "Relationship" {
+            if let [element, kind, related] = value.split_whitespace().collect::<Vec<_>>()[..] {
+                relationships.push(InputRelationship {
+                    spdx_element_id: element.to_owned(),
+                    relationship_type: kind.to_owned(),
+                    related_spdx_element: related.to_owned(),
+                });
+            }
+            continue;
+        }
+
+        if tag == "PackageName" {
+            packages.push(InputPackage {
+                name: value,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        // tags before the first package describe the document, the ones after the last package
+        // describe files and relationships
+        let package = match packages.last_mut() {
+            Some(package) => package,
+            None => continue,
+        };
+
+        match tag {
+            "SPDXID" => package.spdx_id = value,
+            "PackageVersion" => package.version_info = value,
+            "PackageSupplier" => package.supplier = Some(value),
+            "PackageChecksum" => {
+                if let Some((algorithm, digest)) = value.split_once(':') {
+                    package.checksums.push(InputChecksum {
+                        algorithm: algorithm.trim().to_owned(),
+                        checksum_value: digest.trim().to_owned(),
+                    });
+                }
+            }
+            "PrimaryPackagePurpose" => package.primary_package_purpose = Some(value),
+            "PackageLicenseDeclared" => package.license_declared = Some(value),
+            "PackageLicenseComments" => package.license_comments = Some(value),
+            "PackageComment" => package.comment = Some(value),
+            "ExternalRef" => {
+                let mut parts = value.split_whitespace();
+                if let (Some(_), Some(kind), Some(locator)) =
+                    (parts.next(), parts.next(), parts.next())
+                {
+                    package.external_refs.push(InputExternalRef {
+                        reference_type: kind.to_owned(),
+                        reference_locator: locator.to_owned(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(to_sbom(packages, relationships, comment.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    use crate::component::Relationship;
+
+    fn component(kind: Kind, name: &str, purl: Purl) -> Component {
+        Component {
+            kind,
+            name: name.to_owned(),
+            id: name.to_owned(),
+            version: purl.version.clone().unwrap_or_default(),
+            path: format!("/opt/{}", name),
+            purl: Some(purl),
+            ..Default::default()
+        }
+    }
+
+    fn sbom() -> Sbom {
+        let openssl = Purl::new("deb", "openssl")
+            .with_namespace("debian")
+            .with_version("3.0.11-1")
+            .with_qualifier("arch", "amd64");
+        let libssl = Purl::new("deb", "libssl3")
+            .with_namespace("debian")
+            .with_version("3.0.11-1")
+            .with_qualifier("arch", "amd64");
+        let mut sbom = Sbom {
+            host: "build 01/lab".to_owned(),
+            components: vec![
+                component(
+                    Kind::OS,
+                    "debian",
+                    Purl::new("generic", "debian").with_version("12"),
+                ),
+                component(Kind::Application, "openssl", openssl),
+                component(Kind::Dependency, "libssl3", libssl.clone()),
+            ],
+            diagnostics: vec![Diagnostic {
+                collector: "dpkg".to_owned(),
+                message: "no status file".to_owned(),
+            }],
+        };
+
+        let openssl = &mut sbom.components[1];
+        openssl.publishers =
+            vec!["Debian OpenSSL Team <pkg-openssl-devel@lists.debian.org>".to_owned()];
+        openssl.license = Some("Apache-2.0".to_owned());
+        openssl.cpes = vec!["cpe:2.3:a:openssl:openssl:3.0.11:*:*:*:*:*:*:*".to_owned()];
+        openssl.relationships = vec![Relationship::new(RelationshipKind::DependsOn, libssl)];
+        openssl.hashes = BTreeMap::from([
+            ("sha256".to_owned(), "ab".repeat(32)),
+            ("crc32".to_owned(), "deadbeef".to_owned()),
+        ]);
+        sbom
+    }
+
+    fn write_json(sbom: &Sbom) -> serde_json::Value {
+        let mut json = vec![];
+        to_json(sbom, &mut json).unwrap();
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    fn write_tag_value(sbom: &Sbom) -> String {
+        let mut data = vec![];
+        to_tag_value(sbom, &mut data).unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    fn assert_round_trip(loaded: &Sbom, sbom: &Sbom) {
+        assert_eq!(loaded.host, sbom.host);
+        assert_eq!(loaded.diagnostics.len(), sbom.diagnostics.len());
+        assert_eq!(loaded.diagnostics[0].collector, "dpkg");
+        assert_eq!(loaded.diagnostics[0].message, "no status file");
+        assert_eq!(loaded.components.len(), sbom.components.len());
+        for (loaded, comp) in loaded.components.iter().zip(&sbom.components) {
+            assert_eq!(loaded.kind, comp.kind);
+            assert_eq!(loaded.name, comp.name);
+            assert_eq!(loaded.version, comp.version);
+            assert_eq!(loaded.path, comp.path);
+            assert_eq!(loaded.publishers, comp.publishers);
+            assert_eq!(loaded.purl, comp.purl);
+            assert_eq!(loaded.cpes, comp.cpes);
+            assert_eq!(loaded.license, comp.license);
+            assert_eq!(loaded.relationships, comp.relationships);
+        }
+        // unsupported algorithms are skipped
+        assert_eq!(
+            loaded.components[1].hashes,
+            BTreeMap::from([("sha256".to_owned(), "ab".repeat(32))])
+        );
+    }
+
+    #[test]
+    fn writes_json_document() {
+        let sbom = sbom();
+        let document = write_json(&sbom);
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["SPDXID"], DOCUMENT_ID);
+        assert_eq!(
+            document["creationInfo"]["comment"],
+            format!("{}\ndpkg: no status file", DIAGNOSTICS_HEADER)
+        );
+        // the host name is not a valid part of a URI
+        let namespace = document["documentNamespace"].as_str().unwrap();
+        assert!(
+            namespace.starts_with("https://spdx.org/spdxdocs/unisbom-build-01-lab-"),
+            "{}",
+            namespace
+        );
+        assert_ne!(write_json(&sbom)["documentNamespace"], namespace);
+
+        let packages = document["packages"].as_array().unwrap();
+        let ids: Vec<&str> = packages
+            .iter()
+            .map(|p| p["SPDXID"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                HOST_ID,
+                "SPDXRef-OS-debian-12",
+                "SPDXRef-Application-openssl-3.0.11-1",
+                "SPDXRef-Dependency-libssl3-3.0.11-1"
+            ]
+        );
+        assert_eq!(packages[0]["primaryPackagePurpose"], "DEVICE");
+        assert_eq!(packages[0]["name"], "build 01/lab");
+
+        let openssl = &packages[2];
+        assert_eq!(
+            openssl["supplier"],
+            "Organization: Debian OpenSSL Team (pkg-openssl-devel@lists.debian.org)"
+        );
+        assert_eq!(
+            openssl["checksums"],
+            serde_json::json!([{"algorithm": "SHA256", "checksumValue": "ab".repeat(32)}])
+        );
+        assert_eq!(
+            openssl["externalRefs"],
+            serde_json::json!([
+                {
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": "pkg:deb/debian/openssl@3.0.11-1?arch=amd64"
+                },
+                {
+                    "referenceCategory": "SECURITY",
+                    "referenceType": "cpe23Type",
+                    "referenceLocator": "cpe:2.3:a:openssl:openssl:3.0.11:*:*:*:*:*:*:*"
+                }
+            ])
+        );
+        assert_eq!(openssl["licenseDeclared"], NOASSERTION);
+        assert_eq!(openssl["licenseComments"], "Declared license: Apache-2.0");
+        assert!(packages[1].get("checksums").is_none());
+
+        let relationships: Vec<(&str, &str, &str)> = document["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap(),
+                    r["relationshipType"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            relationships,
+            vec![
+                (DOCUMENT_ID, "DESCRIBES", HOST_ID),
+                (HOST_ID, "CONTAINS", ids[1]),
+                (HOST_ID, "CONTAINS", ids[2]),
+                (HOST_ID, "CONTAINS", ids[3]),
+                (ids[2], "DEPENDS_ON", ids[3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_tag_value_document() {
+        let mut sbom = sbom();
+        sbom.components[0].path = "/opt/</text>\nPackageName: injected".to_owned();
+        let document = write_tag_value(&sbom);
+
+        let lines: Vec<&str> = document.lines().collect();
+        for expected in [
+            "SPDXVersion: SPDX-2.3",
+            "SPDXID: SPDXRef-DOCUMENT",
+            "DocumentName: build 01/lab-sbom",
+            "CreatorComment: <text>Problems reported by the collectors:",
+            "dpkg: no status file</text>",
+            "PackageName: openssl",
+            "SPDXID: SPDXRef-Application-openssl-3.0.11-1",
+            "PackageVersion: 3.0.11-1",
+            "PackageSupplier: Organization: Debian OpenSSL Team (pkg-openssl-devel@lists.debian.org)",
+            "PackageChecksum: SHA256: abababababababababababababababababababababababababababababababab",
+            "PrimaryPackagePurpose: APPLICATION",
+            "PackageLicenseComments: <text>Declared license: Apache-2.0</text>",
+            "PackageComment: <text>Installed at /opt/openssl</text>",
+            "ExternalRef: PACKAGE-MANAGER purl pkg:deb/debian/openssl@3.0.11-1?arch=amd64",
+            "ExternalRef: SECURITY cpe23Type cpe:2.3:a:openssl:openssl:3.0.11:*:*:*:*:*:*:*",
+            "Relationship: SPDXRef-DOCUMENT DESCRIBES SPDXRef-Host",
+            "Relationship: SPDXRef-Application-openssl-3.0.11-1 DEPENDS_ON SPDXRef-Dependency-libssl3-3.0.11-1",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, document);
+        }
+        let namespace = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("DocumentNamespace: "))
+            .unwrap();
+        assert!(!namespace.contains(' '), "{}", namespace);
+
+        // the end of the text is escaped, the value can't add tags
+        assert!(lines.contains(&"PackageComment: <text>Installed at /opt/&lt;/text&gt;"));
+        assert!(lines.contains(&"PackageName: injected</text>"));
+        let loaded = from_tag_value(&document).unwrap();
+        assert_eq!(loaded.components.len(), 3);
+        assert_eq!(loaded.components[0].path, sbom.components[0].path);
+    }
+
+    #[test]
+    fn reads_json_document() {
+        let sbom = sbom();
+        let loaded = from_json(&write_json(&sbom).to_string()).unwrap();
+        assert_round_trip(&loaded, &sbom);
+    }
+
+    #[test]
+    fn reads_tag_value_document() {
+        let sbom = sbom();
+        let loaded = from_tag_value(&write_tag_value(&sbom)).unwrap();
+        assert_round_trip(&loaded, &sbom);
+    }
+
+    #[test]
+    fn supplier_email_is_in_parentheses() {
+        let maintainer = "GNU Libc Maintainers <debian-glibc@lists.debian.org>";
+        assert_eq!(
+            supplier(maintainer),
+            "Organization: GNU Libc Maintainers (debian-glibc@lists.debian.org)"
+        );
+        assert_eq!(publisher(&supplier(maintainer)), maintainer);
+
+        let company = "Oracle America, Inc. (VB5E2TV963)";
+        assert_eq!(supplier(company), format!("Organization: {}", company));
+        assert_eq!(publisher(&supplier(company)), company);
+        assert_eq!(
+            publisher("Person: Jane Doe (jane@example.com)"),
+            "Jane Doe <jane@example.com>"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::collector::Owners;
+use crate::component::{self, Component, Relationship, RelationshipKind, EMBEDDED_IN_PROPERTY};
+use crate::purl::Purl;
+
+/// Links the components found by different collectors: bundles contain the components installed
+/// in their folder, such as the kernel extensions and the npm packages of an application, and the
+/// components whose path is owned by a single package are provided by it.
+pub(crate) fn link(components: &mut [Component], owners: &Owners) {
+    let mut bundles: Vec<(usize, String)> = components
+        .iter()
+        .enumerate()
+        .filter(|(_, comp)| component::is_bundle(Path::new(&comp.path)))
+        .map(|(idx, comp)| (idx, format!("{}/", comp.path.trim_end_matches('/'))))
+        .collect();
+    // the innermost bundle has the longest path
+    bundles.sort_by_key(|(_, path)| std::cmp::Reverse(path.len()));
+    // the packages themselves are not provided by another one
+    let packages: HashSet<&Purl> = owners.values().flatten().collect();
+
+    let mut links = vec![];
+    for (idx, comp) in components.iter().enumerate() {
+        let purl = match &comp.purl {
+            Some(purl) => purl,
+            None => continue,
+        };
+        // the modules of an executable share its path, they are linked into it rather than installed
+        if comp.path.is_empty() || comp.properties.contains_key(EMBEDDED_IN_PROPERTY) {
+            continue;
+        }
+
+        if let Some((bundle, _)) = bundles
+            .iter()
+            .find(|(bundle, path)| *bundle != idx && comp.path.starts_with(path.as_str()))
+        {
+            links.push((
+                *bundle,
+                Relationship::new(RelationshipKind::Contains, purl.clone()),
+            ));
+        }
+
+        if packages.contains(purl)
+            || comp
+                .relationships
+                .iter()
+                .any(|r| r.kind == RelationshipKind::ProvidedBy)
+        {
+            continue;
+        }
+        if let Some(Some(owner)) = owners.get(&comp.path) {
+            links.push((
+                idx,
+                Relationship::new(RelationshipKind::ProvidedBy, owner.clone()),
+            ));
+        }
+    }
+
+    for (idx, relationship) in links {
+        components[idx].relationships.push(relationship);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, path: &str, purl: Purl) -> Component {
+        Component {
+            name: name.to_owned(),
+            id: name.to_owned(),
+            path: path.to_owned(),
+            purl: Some(purl),
+            ..Default::default()
+        }
+    }
+
+    fn links(comp: &Component) -> Vec<(RelationshipKind, String)> {
+        comp.relationships
+            .iter()
+            .map(|rel| (rel.kind, rel.purl.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn links_sibling_packages() {
+        let sdk = Purl::new("deb", "google-cloud-cli");
+        let site_packages = "/usr/lib/google-cloud-sdk/lib/site-packages";
+        let mut components = vec![
+            component("google-cloud-cli", "/usr/lib/google-cloud-sdk", sdk.clone()),
+            component("cffi", site_packages, Purl::new("pypi", "cffi")),
+            component("pip", site_packages, Purl::new("pypi", "pip")),
+            component("Foo", "/Applications/Foo.app", Purl::new("generic", "foo")),
+            component(
+                "left-pad",
+                "/Applications/Foo.app/Contents/Resources/app",
+                Purl::new("npm", "left-pad"),
+            ),
+            component(
+                "is-odd",
+                "/Applications/Foo.app/Contents/Resources/app",
+                Purl::new("npm", "is-odd"),
+            ),
+        ];
+        let owners = Owners::from([
+            ("/usr/lib/google-cloud-sdk".to_owned(), Some(sdk.clone())),
+            (site_packages.to_owned(), Some(sdk)),
+        ]);
+
+        link(&mut components, &owners);
+
+        assert!(links(&components[0]).is_empty());
+        for comp in &components[1..3] {
+            assert_eq!(
+                links(comp),
+                vec![(
+                    RelationshipKind::ProvidedBy,
+                    "pkg:deb/google-cloud-cli".to_owned()
+                )]
+            );
+        }
+        assert_eq!(
+            links(&components[3]),
+            vec![
+                (RelationshipKind::Contains, "pkg:npm/left-pad".to_owned()),
+                (RelationshipKind::Contains, "pkg:npm/is-odd".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_embedded_modules() {
+        let tool = Purl::new("deb", "tool");
+        let mut module = component(
+            "golang.org/x/sys",
+            "/usr/bin/tool",
+            Purl::new("golang", "sys"),
+        );
+        module.properties.insert(
+            EMBEDDED_IN_PROPERTY.to_owned(),
+            "pkg:golang/tool".to_owned(),
+        );
+        let mut components = vec![
+            component("tool", "/usr/bin/tool", Purl::new("golang", "tool")),
+            module,
+        ];
+        let owners = Owners::from([("/usr/bin/tool".to_owned(), Some(tool))]);
+
+        link(&mut components, &owners);
+
+        assert_eq!(
+            links(&components[0]),
+            vec![(RelationshipKind::ProvidedBy, "pkg:deb/tool".to_owned())]
+        );
+        assert!(links(&components[1]).is_empty());
+    }
+}
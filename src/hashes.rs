@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use walkdir::WalkDir;
+
+use crate::collector::Context;
+use crate::component::{self, Component};
+use crate::sbom::Diagnostic;
+use crate::windows;
+use crate::Error;
+
+const MAX_WORKERS: usize = 8;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Hash algorithm of the files of the components.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Name used as the key of the hashes of a component.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+}
+
+enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Self::Sha512(Sha512::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn hex(self) -> String {
+        match self {
+            Self::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+// every algorithm is computed while reading the file once
+fn hash_file(path: &Path, algorithms: &[HashAlgorithm]) -> Result<Vec<String>, Error> {
+    let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|alg| Hasher::new(*alg)).collect();
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| Error::io(path, e))?;
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+    }
+
+    Ok(hashers.into_iter().map(|hasher| hasher.hex()).collect())
+}
+
+// the digest of a bundle is the one of the list of its regular files, one "<hex digest>  <relative path>\n"
+// line each like the output of sha256sum, walked sorted by name so that it doesn't depend on the order of
+// the directory entries nor on their timestamps
+fn hash_bundle(path: &Path, algorithms: &[HashAlgorithm]) -> Result<Vec<String>, Error> {
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|alg| Hasher::new(*alg)).collect();
+
+    for entry in WalkDir::new(path).sort_by_file_name() {
+        let entry = entry.map_err(|e| Error::io(path, e.into()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(path)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|comp| comp.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let digests = hash_file(entry.path(), algorithms)?;
+        for (hasher, digest) in hashers.iter_mut().zip(digests) {
+            hasher.update(format!("{}  {}\n", digest, relative).as_bytes());
+        }
+    }
+
+    Ok(hashers.into_iter().map(|hasher| hasher.hex()).collect())
+}
+
+// windows components are installed at C:\..., which is the root of an inspected image
+fn host_path(ctx: &Context, path: &str) -> PathBuf {
+    let drive = path.as_bytes().get(..2);
+    match drive {
+        Some([letter, b':']) if letter.is_ascii_alphabetic() && !ctx.is_live() => {
+            windows::image_path(ctx, &path[2..].replace('\\', "/"))
+        }
+        _ => ctx.path(path),
+    }
+}
+
+struct Job {
+    component: usize,
+    path: PathBuf,
+    bundle: bool,
+}
+
+/// Hashes the file of each component, or the whole directory of application and kernel
+/// extension bundles, with a bounded number of threads. The files that can't be read and the
+/// components installed in other directories, such as the packages of the system, are returned
+/// as diagnostics.
+pub(crate) fn compute(
+    ctx: &Context,
+    algorithms: &[HashAlgorithm],
+    components: &mut [Component],
+) -> Vec<Diagnostic> {
+    // the modules of an executable share its path, it's only hashed once
+    let mut seen = HashSet::new();
+    let mut jobs = vec![];
+    let mut directories = 0;
+    for (idx, comp) in components.iter().enumerate() {
+        if comp.path.is_empty() || !seen.insert(comp.path.clone()) {
+            continue;
+        }
+        let path = host_path(ctx, &comp.path);
+        let bundle = component::is_bundle(&path) && path.is_dir();
+        if bundle || path.is_file() {
+            jobs.push(Job {
+                component: idx,
+                path,
+                bundle,
+            });
+        } else if path.is_dir() {
+            // the install directory of a package is shared with other packages
+            directories += 1;
+        }
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+        .min(jobs.len());
+    log::debug!("hashing {} files with {} threads", jobs.len(), workers);
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (jobs, next) = (&jobs, &next);
+            scope.spawn(move || {
+                while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let digests = if job.bundle {
+                        hash_bundle(&job.path, algorithms)
+                    } else {
+                        hash_file(&job.path, algorithms)
+                    };
+                    if sender.send((job.component, digests)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut diagnostics = vec![];
+    for (idx, digests) in receiver {
+        match digests {
+            Ok(digests) => {
+                components[idx].hashes = algorithms
+                    .iter()
+                    .map(|alg| alg.name().to_owned())
+                    .zip(digests)
+                    .collect();
+            }
+            Err(e) => diagnostics.push(Diagnostic {
+                collector: "hashes".to_owned(),
+                message: e.to_string(),
+            }),
+        }
+    }
+    // the workers finish in any order
+    diagnostics.sort_by(|a, b| a.message.cmp(&b.message));
+    if directories > 0 {
+        diagnostics.push(Diagnostic {
+            collector: "hashes".to_owned(),
+            message: format!(
+                "components installed in a directory are not hashed: {}",
+                directories
+            ),
+        });
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const HELLO_SHA1: &str = "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d";
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const HELLO_SHA512: &str = "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca7\
+                                2323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043";
+    const ALGORITHMS: &[HashAlgorithm] = &[
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+    ];
+
+    fn component(path: &str) -> Component {
+        Component {
+            name: path.to_owned(),
+            id: path.to_owned(),
+            path: path.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn hashes(comp: &Component) -> Vec<(&str, &str)> {
+        comp.hashes
+            .iter()
+            .map(|(alg, digest)| (alg.as_str(), digest.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn hashes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hello");
+        fs::write(&path, "hello").unwrap();
+
+        let mut components = vec![component(&path.to_string_lossy())];
+        let diagnostics = compute(&Context::default(), ALGORITHMS, &mut components);
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            hashes(&components[0]),
+            vec![
+                ("sha1", HELLO_SHA1),
+                ("sha256", HELLO_SHA256),
+                ("sha512", HELLO_SHA512)
+            ]
+        );
+    }
+
+    #[test]
+    fn hashes_bundles_in_name_order() {
+        let dir = tempfile::tempdir().unwrap();
+        // the same files created in a different order
+        for (bundle, names) in [("A.app", ["b", "a"]), ("B.app", ["a", "b"])] {
+            for name in names {
+                let path = dir.path().join(bundle).join("Contents").join(name);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "hello").unwrap();
+            }
+        }
+
+        let first = hash_bundle(&dir.path().join("A.app"), &[HashAlgorithm::Sha256]).unwrap();
+        let second = hash_bundle(&dir.path().join("B.app"), &[HashAlgorithm::Sha256]).unwrap();
+        assert_eq!(first, second);
+
+        let listing = format!(
+            "{}  Contents/a\n{}  Contents/b\n",
+            HELLO_SHA256, HELLO_SHA256
+        );
+        let mut hasher = Hasher::new(HashAlgorithm::Sha256);
+        hasher.update(listing.as_bytes());
+        assert_eq!(first, vec![hasher.hex()]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reports_unreadable_files() {
+        // reading the memory of the process at address 0 always fails
+        let mut components = vec![component("/proc/self/mem")];
+        let diagnostics = compute(&Context::default(), ALGORITHMS, &mut components);
+        assert!(components[0].hashes.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].collector, "hashes");
+        assert!(diagnostics[0].message.contains("/proc/self/mem"));
+    }
+
+    #[test]
+    fn resolves_paths_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("usr/bin")).unwrap();
+        fs::write(root.path().join("usr/bin/hello"), "hello").unwrap();
+        fs::create_dir_all(root.path().join("Windows/System32/drivers")).unwrap();
+        fs::write(
+            root.path().join("Windows/System32/drivers/disk.sys"),
+            "hello",
+        )
+        .unwrap();
+
+        let ctx = Context::new(Some(root.path().to_path_buf())).unwrap();
+        let mut components = vec![
+            component("/usr/bin/hello"),
+            component("C:\\WINDOWS\\System32\\drivers\\disk.sys"),
+            component("/usr/bin"),
+            component("/missing"),
+        ];
+        let diagnostics = compute(&ctx, &[HashAlgorithm::Sha256], &mut components);
+
+        assert_eq!(hashes(&components[0]), vec![("sha256", HELLO_SHA256)]);
+        assert_eq!(hashes(&components[1]), vec![("sha256", HELLO_SHA256)]);
+        assert!(components[2].hashes.is_empty());
+        assert!(components[3].hashes.is_empty());
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["components installed in a directory are not hashed: 1"]
+        );
+    }
+}
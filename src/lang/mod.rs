@@ -0,0 +1,81 @@
+use std::path::{Component, Path, PathBuf};
+
+use walkdir::{DirEntry, WalkDir};
+
+use crate::collector::{self, CollectorKind};
+
+mod npm;
+mod python;
+
+// folders that can be huge and never contain installed packages
+const SKIPPED_DIRS: &[&str] = &[".git", ".cache", "__pycache__", ".cargo", ".rustup"];
+
+/// Collectors of the packages installed by the package managers of programming languages, they
+/// search the same folders on every system.
+pub(crate) fn collectors(ctx: &collector::Context) -> Vec<Box<dyn collector::Collector>> {
+    let mut collectors: Vec<Box<dyn collector::Collector>> = vec![];
+
+    if ctx.is_enabled(CollectorKind::Python) {
+        collectors.push(Box::new(python::Collector::new(ctx)));
+    }
+    if ctx.is_enabled(CollectorKind::Npm) {
+        collectors.push(Box::new(npm::Collector::new(ctx)));
+    }
+
+    collectors
+}
+
+/// A folder of the inspected system being searched for packages, the paths matching the exclude
+/// patterns of the context are skipped.
+pub(crate) struct SearchPath {
+    ctx: collector::Context,
+    path: String,
+    on_host: PathBuf,
+}
+
+impl SearchPath {
+    pub fn new(ctx: &collector::Context, path: &str) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            path: path.to_owned(),
+            on_host: ctx.path(path),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = walkdir::Result<DirEntry>> + '_ {
+        let mut walker = WalkDir::new(&self.on_host).sort_by_file_name();
+        if let Some(max_depth) = self.ctx.max_depth() {
+            walker = walker.max_depth(max_depth);
+        }
+
+        walker.into_iter().filter_entry(|entry| {
+            let skipped = entry.file_type().is_dir()
+                && SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref());
+            !skipped && !self.ctx.is_excluded(&self.inspected(entry.path()))
+        })
+    }
+
+    /// Returns the path on the inspected system of a file found on the host.
+    pub fn inspected(&self, on_host: &Path) -> String {
+        let relative = on_host.strip_prefix(&self.on_host).unwrap_or(on_host);
+        Path::new(&self.path)
+            .join(relative)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Removes the . and .. components of a path without accessing the filesystem.
+pub(crate) fn normalize(path: &Path) -> String {
+    let mut normalized = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            comp => normalized.push(comp),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
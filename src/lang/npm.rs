@@ -0,0 +1,1246 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use super::SearchPath;
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::purl::Purl;
+use crate::Error;
+
+// global installs (<prefix>/lib/node_modules, ~/.npm-global), projects and electron apps, whose
+// app.asar is often extracted to resources/app or has native modules in resources/app.asar.unpacked
+const DEFAULT_PATHS: &[&str] = &[
+    "/usr/lib",
+    "/usr/local/lib",
+    "/usr/share",
+    "/opt",
+    "/home",
+    "/root",
+    "/Applications",
+    "/Users",
+    "/Program Files",
+    "/ProgramData",
+];
+const NODE_MODULES: &str = "node_modules";
+const MANIFEST: &str = "package.json";
+const NPM_LOCKFILES: &[&str] = &["package-lock.json", "npm-shrinkwrap.json"];
+const YARN_LOCKFILE: &str = "yarn.lock";
+const PNPM_LOCKFILE: &str = "pnpm-lock.yaml";
+// dependencies of a package entry, devDependencies are only installed for the project itself
+const DEPENDENCY_FIELDS: &[&str] = &["dependencies", "optionalDependencies"];
+const PROJECT_DEPENDENCY_FIELDS: &[&str] =
+    &["dependencies", "optionalDependencies", "devDependencies"];
+
+fn modified(path: &Path) -> DateTime<Utc> {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(DateTime::from)
+        .unwrap_or_default()
+}
+
+fn purl(name: &str, version: &str) -> Purl {
+    match name.split_once('/') {
+        Some((scope, name)) if scope.starts_with('@') => {
+            Purl::new("npm", name).with_namespace(scope)
+        }
+        _ => Purl::new("npm", name),
+    }
+    .with_version(version)
+}
+
+// "name@range", scoped names start with @
+fn split_spec(spec: &str) -> Option<(&str, &str)> {
+    let idx = spec.get(1..)?.find('@')? + 1;
+    Some((&spec[..idx], &spec[idx + 1..]))
+}
+
+// "Jane Doe <jane@example.com> (https://example.com)" or {"name": "Jane Doe", ...}
+fn person(value: &Value) -> Option<String> {
+    let name = match value {
+        Value::String(person) => person.split(['<', '(']).next().unwrap_or_default(),
+        Value::Object(person) => person.get("name")?.as_str()?,
+        _ => return None,
+    };
+    Some(name.trim().to_owned()).filter(|name| !name.is_empty())
+}
+
+// an SPDX expression, or the deprecated {"type": "MIT"} and "licenses": [{"type": "MIT"}, ...]
+fn license(manifest: &Value) -> Option<String> {
+    let id = |license: &Value| {
+        license
+            .as_str()
+            .or_else(|| license.get("type")?.as_str())
+            .map(|id| id.to_owned())
+    };
+
+    manifest.get("license").and_then(id).or_else(|| {
+        let ids: Vec<String> = manifest
+            .get("licenses")?
+            .as_array()?
+            .iter()
+            .filter_map(id)
+            .collect();
+        Some(ids.join(" OR ")).filter(|expression| !expression.is_empty())
+    })
+}
+
+// (name, range) of the dependencies listed in the given fields
+fn dependency_specs(entry: &Value, fields: &[&str]) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .filter_map(|field| entry.get(field)?.as_object())
+        .flat_map(|dependencies| {
+            dependencies
+                .iter()
+                .map(|(name, range)| (name.clone(), range.as_str().unwrap_or_default().to_owned()))
+        })
+        .collect()
+}
+
+fn read_manifest(path: &Path) -> Result<Value, Error> {
+    let data = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    serde_json::from_str(&data).map_err(|e| Error::parse(path.display(), e))
+}
+
+// the folder containing the outermost node_modules of a path
+fn project_of(path: &Path) -> Option<PathBuf> {
+    let mut project = PathBuf::new();
+    for comp in path.components() {
+        if comp.as_os_str() == NODE_MODULES {
+            return Some(project);
+        }
+        project.push(comp);
+    }
+    None
+}
+
+// node_modules/<name>/package.json or node_modules/@scope/<name>/package.json
+fn is_installed_manifest(path: &Path) -> bool {
+    let name_of = |path: Option<&Path>| {
+        path.and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let parent = path.parent().and_then(|package| package.parent());
+    match name_of(parent).as_str() {
+        NODE_MODULES => true,
+        scope if scope.starts_with('@') => {
+            name_of(parent.and_then(|scope| scope.parent())) == NODE_MODULES
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Package {
+    name: String,
+    version: String,
+    path: String,
+    modified: DateTime<Utc>,
+    publishers: Vec<String>,
+    license: Option<String>,
+    kind: Kind,
+    properties: BTreeMap<String, String>,
+    dependencies: Vec<Purl>,
+}
+
+impl Package {
+    fn from_manifest(manifest: &Value) -> Self {
+        let field = |name: &str| {
+            manifest
+                .get(name)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_owned()
+        };
+
+        Self {
+            name: field("name"),
+            version: field("version"),
+            publishers: manifest
+                .get("author")
+                .and_then(person)
+                .into_iter()
+                .collect(),
+            license: license(manifest),
+            ..Default::default()
+        }
+    }
+
+    fn from_lockfile(name: &str, version: &str, entry: &Value) -> Self {
+        let mut package = Self {
+            name: name.to_owned(),
+            version: version.to_owned(),
+            kind: Kind::Dependency,
+            license: entry
+                .get("license")
+                .and_then(|l| l.as_str())
+                .map(|l| l.to_owned()),
+            ..Default::default()
+        };
+        if entry.get("dev").and_then(|dev| dev.as_bool()) == Some(true) {
+            package
+                .properties
+                .insert("dev".to_owned(), "true".to_owned());
+        }
+        if let Some(integrity) = entry.get("integrity").and_then(|i| i.as_str()) {
+            package
+                .properties
+                .insert("integrity".to_owned(), integrity.to_owned());
+        }
+        package
+    }
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(purl(&self.name, &self.version))
+    }
+
+    fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect()
+    }
+}
+
+/// Packages of a lockfile, and the ones the project depends on by name or by name@range.
+#[derive(Debug, Default)]
+struct Lockfile {
+    packages: Vec<Package>,
+    root: HashMap<String, Purl>,
+}
+
+impl Lockfile {
+    fn resolve(&self, name: &str, range: &str) -> Option<Purl> {
+        [
+            format!("{}@{}", name, range),
+            format!("{}@npm:{}", name, range),
+            name.to_owned(),
+        ]
+        .iter()
+        .find_map(|key| self.root.get(key).cloned())
+    }
+
+    // lockfileVersion 2 and 3, packages are keyed by their install path
+    fn from_npm_packages(packages: &Map<String, Value>, project: &Path) -> Self {
+        let mut lockfile = Self::default();
+        // name and version of each installed package, links point to workspaces
+        let installed: HashMap<&str, (&str, &str)> = packages
+            .iter()
+            .filter(|(key, entry)| {
+                key.starts_with(NODE_MODULES) && entry.get("link") != Some(&Value::Bool(true))
+            })
+            .map(|(key, entry)| {
+                let name = entry
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .or_else(|| key.rsplit("node_modules/").next())
+                    .unwrap_or(key);
+                let version = entry
+                    .get("version")
+                    .and_then(|version| version.as_str())
+                    .unwrap_or_default();
+                (key.as_str(), (name, version))
+            })
+            .collect();
+
+        // same as node, the node_modules of the package and then the ones of its parents
+        let resolve = |key: &str, name: &str| {
+            let mut base = key;
+            loop {
+                let candidate = if base.is_empty() {
+                    format!("{}/{}", NODE_MODULES, name)
+                } else {
+                    format!("{}/{}/{}", base, NODE_MODULES, name)
+                };
+                if let Some((name, version)) = installed.get(candidate.as_str()) {
+                    return Some(purl(name, version));
+                }
+                if base.is_empty() {
+                    return None;
+                }
+                base = base
+                    .rfind(&format!("/{}/", NODE_MODULES))
+                    .map(|idx| &base[..idx])
+                    .unwrap_or_default();
+            }
+        };
+
+        for (key, entry) in packages {
+            let (name, version) = match installed.get(key.as_str()) {
+                Some(installed) => *installed,
+                None => continue,
+            };
+            if !key.contains(&format!("/{}/", NODE_MODULES)) {
+                lockfile.root.insert(name.to_owned(), purl(name, version));
+            }
+
+            let mut package = Package::from_lockfile(name, version, entry);
+            package.path = project.join(key).to_string_lossy().into_owned();
+            package.dependencies = dependency_specs(entry, DEPENDENCY_FIELDS)
+                .iter()
+                .filter_map(|(name, _)| resolve(key, name))
+                .collect();
+            lockfile.packages.push(package);
+        }
+
+        lockfile
+    }
+
+    // lockfileVersion 1, nested dependencies are the ones installed in the node_modules of a package
+    fn add_npm_dependencies<'a>(
+        &mut self,
+        dependencies: &'a Map<String, Value>,
+        scopes: &mut Vec<&'a Map<String, Value>>,
+    ) {
+        scopes.push(dependencies);
+
+        for (name, entry) in dependencies {
+            let version = entry
+                .get("version")
+                .and_then(|version| version.as_str())
+                .unwrap_or_default();
+            let nested = entry.get("dependencies").and_then(|deps| deps.as_object());
+
+            let mut package = Package::from_lockfile(name, version, entry);
+            if let Some(requires) = entry.get("requires").and_then(|r| r.as_object()) {
+                package.dependencies = requires
+                    .keys()
+                    .filter_map(|required| {
+                        nested
+                            .into_iter()
+                            .chain(scopes.iter().rev().copied())
+                            .find_map(|scope| scope.get(required))
+                            .and_then(|entry| entry.get("version")?.as_str())
+                            .map(|version| purl(required, version))
+                    })
+                    .collect();
+            }
+            if scopes.len() == 1 {
+                self.root.insert(name.clone(), purl(name, version));
+            }
+            self.packages.push(package);
+
+            if let Some(nested) = nested {
+                self.add_npm_dependencies(nested, scopes);
+            }
+        }
+
+        scopes.pop();
+    }
+
+    fn from_npm(data: &str, project: &Path) -> Result<Self, serde_json::Error> {
+        let lock: Value = serde_json::from_str(data)?;
+
+        if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+            return Ok(Self::from_npm_packages(packages, project));
+        }
+
+        let mut lockfile = Self::default();
+        if let Some(dependencies) = lock.get("dependencies").and_then(|d| d.as_object()) {
+            lockfile.add_npm_dependencies(dependencies, &mut vec![]);
+        }
+        Ok(lockfile)
+    }
+
+    // yarn v1 and berry, blocks of "name@range, name@other-range:" followed by indented fields:
+    //
+    // "@babel/code-frame@^7.0.0", "@babel/code-frame@^7.10.4":
+    //   version "7.12.13"
+    //   dependencies:
+    //     "@babel/highlight" "^7.12.13"
+    fn from_yarn(data: &str) -> Self {
+        // (specs, version, integrity, dependencies)
+        let mut blocks: Vec<(Vec<String>, String, String, Vec<String>)> = vec![];
+        let mut in_dependencies = false;
+
+        let unquote = |s: &str| s.trim().trim_matches('"').to_owned();
+        // `name "range"` in v1, `name: range` in berry
+        let key_value = |line: &str| -> (String, String) {
+            let line = line.trim();
+            let (key, value) = if let Some(quoted) = line.strip_prefix('"') {
+                quoted.split_once('"').unwrap_or((quoted, ""))
+            } else {
+                line.split_once([' ', ':']).unwrap_or((line, ""))
+            };
+            (key.to_owned(), unquote(value.trim_start_matches(':')))
+        };
+
+        for line in data.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+
+            if indent == 0 {
+                let specs = line
+                    .trim_end_matches(':')
+                    .split(',')
+                    .map(unquote)
+                    .filter(|spec| !spec.is_empty())
+                    .collect();
+                blocks.push((specs, String::new(), String::new(), vec![]));
+                in_dependencies = false;
+                continue;
+            }
+
+            let block = match blocks.last_mut() {
+                Some(block) => block,
+                None => continue,
+            };
+            let (key, value) = key_value(line);
+            if indent <= 2 {
+                in_dependencies = matches!(key.as_str(), "dependencies" | "optionalDependencies");
+                match key.as_str() {
+                    "version" => block.1 = value,
+                    "integrity" | "checksum" => block.2 = value,
+                    _ => {}
+                }
+            } else if in_dependencies {
+                block.3.push(format!("{}@{}", key, value));
+            }
+        }
+
+        let mut lockfile = Self::default();
+        for (specs, version, _, _) in &blocks {
+            if let Some((name, _)) = specs.first().and_then(|spec| split_spec(spec)) {
+                for spec in specs {
+                    lockfile.root.insert(spec.clone(), purl(name, version));
+                }
+            }
+        }
+
+        for (specs, version, integrity, dependencies) in &blocks {
+            let name = match specs.first().and_then(|spec| split_spec(spec)) {
+                Some((name, _)) => name,
+                None => continue,
+            };
+            // the project itself and the workspaces, berry also has a __metadata block
+            if specs
+                .iter()
+                .any(|spec| spec.contains("@workspace:") || spec.contains("@link:"))
+            {
+                continue;
+            }
+
+            let mut package = Package::from_lockfile(name, version, &Value::Null);
+            if !integrity.is_empty() {
+                package
+                    .properties
+                    .insert("integrity".to_owned(), integrity.clone());
+            }
+            package.dependencies = dependencies
+                .iter()
+                .filter_map(|spec| {
+                    let (name, range) = split_spec(spec)?;
+                    let npm_spec = format!("{}@npm:{}", name, range);
+                    lockfile
+                        .root
+                        .get(spec)
+                        .or_else(|| lockfile.root.get(&npm_spec))
+                        .cloned()
+                })
+                .collect();
+            lockfile.packages.push(package);
+        }
+
+        lockfile
+    }
+
+    // packages are keyed by "/name/version_peers" up to lockfileVersion 5 and by "name@version(peers)"
+    // since then, version 9 moved their dependencies to the snapshots
+    fn from_pnpm(data: &str) -> Result<Self, serde_yaml::Error> {
+        use serde_yaml::Value as Yaml;
+
+        let lock: Yaml = serde_yaml::from_str(data)?;
+        let version = match lock.get("lockfileVersion") {
+            Some(Yaml::Number(version)) => version.as_f64(),
+            Some(Yaml::String(version)) => version.parse().ok(),
+            _ => None,
+        };
+        let legacy = version.unwrap_or_default() < 6.0;
+
+        let clean = |version: &str| {
+            let separator = if legacy { '_' } else { '(' };
+            version
+                .split(separator)
+                .next()
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let parse_key = |key: &str| {
+            let key = key.trim_start_matches('/');
+            let (name, version) = if legacy {
+                key.rsplit_once('/')?
+            } else {
+                split_spec(key)?
+            };
+            Some((name.to_owned(), clean(version)))
+        };
+        // a version, an alias to another package or a link to a workspace, importers of
+        // version 6 and later have the version in a map along with the specifier
+        let resolve = |name: &str, value: &Yaml| {
+            let value = value.as_str().or_else(|| value.get("version")?.as_str())?;
+            if value.starts_with("link:") || value.starts_with("file:") {
+                None
+            } else if !value.starts_with(|c: char| c.is_ascii_digit()) {
+                parse_key(value)
+            } else {
+                Some((name.to_owned(), clean(value)))
+            }
+        };
+
+        let mut lockfile = Self::default();
+
+        // single projects of version 5 have their dependencies at the top level
+        let importer = lock
+            .get("importers")
+            .and_then(|importers| importers.get("."))
+            .unwrap_or(&lock);
+        for field in PROJECT_DEPENDENCY_FIELDS {
+            for (name, value) in importer
+                .get(field)
+                .and_then(|deps| deps.as_mapping())
+                .into_iter()
+                .flatten()
+            {
+                let name = name.as_str().unwrap_or_default();
+                if let Some((resolved, version)) = resolve(name, value) {
+                    lockfile
+                        .root
+                        .insert(name.to_owned(), purl(&resolved, &version));
+                }
+            }
+        }
+
+        let mut packages: BTreeMap<(String, String), Package> = BTreeMap::new();
+        for (key, entry) in ["packages", "snapshots"]
+            .iter()
+            .filter_map(|section| lock.get(section)?.as_mapping())
+            .flatten()
+        {
+            let (name, version) = match key.as_str().and_then(parse_key) {
+                Some(key) => key,
+                None => continue,
+            };
+            let package = packages
+                .entry((name.clone(), version.clone()))
+                .or_insert_with(|| Package::from_lockfile(&name, &version, &Value::Null));
+
+            if entry.get("dev").and_then(|dev| dev.as_bool()) == Some(true) {
+                package
+                    .properties
+                    .insert("dev".to_owned(), "true".to_owned());
+            }
+            if let Some(integrity) = entry
+                .get("resolution")
+                .and_then(|resolution| resolution.get("integrity"))
+                .and_then(|integrity| integrity.as_str())
+            {
+                package
+                    .properties
+                    .insert("integrity".to_owned(), integrity.to_owned());
+            }
+            for field in DEPENDENCY_FIELDS {
+                for (name, value) in entry
+                    .get(field)
+                    .and_then(|deps| deps.as_mapping())
+                    .into_iter()
+                    .flatten()
+                {
+                    let name = name.as_str().unwrap_or_default();
+                    if let Some((resolved, version)) = resolve(name, value) {
+                        let dependency = purl(&resolved, &version);
+                        if !package.dependencies.contains(&dependency) {
+                            package.dependencies.push(dependency);
+                        }
+                    }
+                }
+            }
+        }
+        lockfile.packages = packages.into_values().collect();
+
+        Ok(lockfile)
+    }
+
+    fn parse(name: &str, data: &str, project: &Path) -> Result<Self, String> {
+        match name {
+            YARN_LOCKFILE => Ok(Self::from_yarn(data)),
+            PNPM_LOCKFILE => Self::from_pnpm(data).map_err(|e| e.to_string()),
+            _ => Self::from_npm(data, project).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    paths: Vec<String>,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            paths: vec![],
+        }
+    }
+
+    // node's resolution, the node_modules of the package and then the ones of its parent folders,
+    // linked packages (pnpm, workspaces) are not walked and are read from their manifest
+    fn resolve(
+        &self,
+        dir: &Path,
+        project: &Path,
+        name: &str,
+        installed: &HashMap<PathBuf, Purl>,
+    ) -> Option<Purl> {
+        for ancestor in dir.ancestors().take_while(|a| a.starts_with(project)) {
+            let folder = ancestor
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            if folder == NODE_MODULES || folder.starts_with('@') {
+                continue;
+            }
+
+            let candidate = ancestor.join(NODE_MODULES).join(name);
+            if let Some(purl) = installed.get(&candidate) {
+                return Some(purl.clone());
+            }
+            if let Ok(manifest) = read_manifest(&self.ctx.path(candidate.join(MANIFEST))) {
+                return Some(purl(name, &Package::from_manifest(&manifest).version));
+            }
+        }
+        None
+    }
+
+    // the manifests and the lockfiles are resolved in the inspected system as they can be symlinks
+    fn read_package(&self, dir: &Path) -> Result<(Package, Value), Error> {
+        let on_host = self.ctx.path(dir.join(MANIFEST));
+        let manifest = read_manifest(&on_host)?;
+        let mut package = Package::from_manifest(&manifest);
+        // the name is only required for published packages
+        if package.name.is_empty() {
+            package.name = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        package.path = dir.to_string_lossy().into_owned();
+        package.modified = modified(&on_host);
+        Ok((package, manifest))
+    }
+
+    fn read_lockfile(&self, path: &Path) -> Result<Lockfile, Error> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let project = path.parent().unwrap_or(path);
+        let on_host = self.ctx.path(path);
+        let data = fs::read_to_string(&on_host).map_err(|e| Error::io(&on_host, e))?;
+        let mut lockfile = Lockfile::parse(&name, &data, project)
+            .map_err(|e| Error::parse(on_host.display(), e))?;
+
+        let modified = modified(&on_host);
+        for package in &mut lockfile.packages {
+            if package.path.is_empty() {
+                package.path = path.to_string_lossy().into_owned();
+            }
+            package.modified = modified;
+            package
+                .properties
+                .insert("lockfile".to_owned(), path.to_string_lossy().into_owned());
+        }
+        Ok(lockfile)
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        self.paths = self.ctx.search_paths(DEFAULT_PATHS);
+        if self.paths.is_empty() {
+            return Err(Error::unsupported("no node.js search path found"));
+        }
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        log::info!(
+            "searching node.js packages in {} ...",
+            self.paths.join(", ")
+        );
+
+        let mut collected = Collected::new("npm");
+        let mut manifests = vec![];
+        let mut lockfiles = vec![];
+        // the same folder can be reached from more than one search path
+        let mut seen = HashSet::new();
+
+        for path in &self.paths {
+            let search = SearchPath::new(&self.ctx, path);
+            for entry in search.entries() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        continue;
+                    }
+                };
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let name = entry.file_name().to_string_lossy();
+                let inspected = PathBuf::from(search.inspected(entry.path()));
+                let found = if name == MANIFEST && is_installed_manifest(&inspected) {
+                    &mut manifests
+                } else if (NPM_LOCKFILES.contains(&name.as_ref())
+                    || name == YARN_LOCKFILE
+                    || name == PNPM_LOCKFILE)
+                    && project_of(&inspected).is_none()
+                {
+                    &mut lockfiles
+                } else {
+                    continue;
+                };
+
+                let on_host = self.ctx.path(&inspected);
+                if seen.insert(fs::canonicalize(&on_host).unwrap_or(on_host)) {
+                    found.push(inspected);
+                }
+            }
+        }
+
+        // installed packages by folder, along with the names of their dependencies
+        let mut installed = BTreeMap::new();
+        for path in &manifests {
+            let dir = path.parent().unwrap_or(path);
+            match self.read_package(dir) {
+                Ok((package, manifest)) => {
+                    let names: Vec<String> = dependency_specs(&manifest, DEPENDENCY_FIELDS)
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect();
+                    installed.insert(dir.to_path_buf(), (package, names));
+                }
+                Err(e) => collected.warn(e),
+            }
+        }
+        let purls: HashMap<PathBuf, Purl> = installed
+            .iter()
+            .map(|(dir, (package, _))| (dir.clone(), purl(&package.name, &package.version)))
+            .collect();
+
+        let mut locked: HashMap<PathBuf, Vec<Lockfile>> = HashMap::new();
+        for path in &lockfiles {
+            match self.read_lockfile(path) {
+                Ok(lockfile) => locked
+                    .entry(path.parent().unwrap_or(path).to_path_buf())
+                    .or_default()
+                    .push(lockfile),
+                Err(e) => collected.warn(e),
+            }
+        }
+
+        let projects: BTreeSet<PathBuf> = installed
+            .keys()
+            .filter_map(|dir| project_of(dir))
+            .chain(locked.keys().cloned())
+            .collect();
+
+        for project in projects {
+            let lockfiles = locked.remove(&project).unwrap_or_default();
+            let manifest = self.read_package(&project).ok();
+            let mut reported = HashSet::new();
+
+            if let Some((mut package, manifest)) = manifest.clone() {
+                package.dependencies = dependency_specs(&manifest, PROJECT_DEPENDENCY_FIELDS)
+                    .iter()
+                    .filter_map(|(name, range)| {
+                        self.resolve(&project, &project, name, &purls)
+                            .or_else(|| lockfiles.iter().find_map(|l| l.resolve(name, range)))
+                    })
+                    .collect();
+                collected.push(package);
+            }
+
+            let node_modules = project.join(NODE_MODULES);
+            let packages: Vec<PathBuf> = installed
+                .keys()
+                .filter(|dir| project_of(dir).as_ref() == Some(&project))
+                .cloned()
+                .collect();
+            for dir in packages {
+                let (mut package, names) = installed.remove(&dir).unwrap_or_default();
+                package.dependencies = names
+                    .iter()
+                    .filter_map(|name| self.resolve(&dir, &project, name, &purls))
+                    .collect();
+                // global installs, such as <prefix>/lib/node_modules, are the only ones without a project
+                let is_top_level = dir.parent() == Some(&node_modules)
+                    || dir.parent().and_then(|scope| scope.parent()) == Some(&node_modules);
+                package.kind = if manifest.is_none() && is_top_level {
+                    Kind::Application
+                } else {
+                    Kind::Dependency
+                };
+
+                reported.insert(purl(&package.name, &package.version));
+                collected.push(package);
+            }
+
+            // lockfiles of projects that are not installed, or only partially
+            for lockfile in lockfiles {
+                for package in lockfile.packages {
+                    if reported.insert(purl(&package.name, &package.version)) {
+                        collected.push(package);
+                    }
+                }
+            }
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collector::Collector as _;
+
+    // the graph shared by the lockfile fixtures, with a nested b and a scoped package
+    const GRAPH: &[(&str, &[&str])] = &[
+        ("pkg:npm/%40scope/c@3.0.0", &["pkg:npm/b@1.0.0"]),
+        ("pkg:npm/a@1.0.0", &["pkg:npm/b@2.0.0"]),
+        ("pkg:npm/b@1.0.0", &[]),
+        ("pkg:npm/b@2.0.0", &[]),
+    ];
+
+    fn graph<'a>(
+        packages: impl Iterator<Item = &'a dyn ComponentTrait>,
+    ) -> Vec<(String, Vec<String>)> {
+        let mut graph: Vec<(String, Vec<String>)> = packages
+            .map(|package| {
+                let mut dependencies: Vec<String> = package
+                    .relationships()
+                    .iter()
+                    .map(|rel| rel.purl.to_string())
+                    .collect();
+                dependencies.sort();
+                (package.purl().unwrap().to_string(), dependencies)
+            })
+            .collect();
+        graph.sort();
+        graph
+    }
+
+    fn expected_graph() -> Vec<(String, Vec<String>)> {
+        GRAPH
+            .iter()
+            .map(|(purl, dependencies)| {
+                let dependencies = dependencies.iter().map(|d| d.to_string()).collect();
+                (purl.to_string(), dependencies)
+            })
+            .collect()
+    }
+
+    fn assert_lockfile(lockfile: &Lockfile) {
+        let expected = expected_graph();
+        let packages = lockfile.packages.iter().map(|p| p as &dyn ComponentTrait);
+        assert_eq!(graph(packages), expected);
+        assert_eq!(
+            lockfile.resolve("a", "^1.0.0").unwrap().to_string(),
+            "pkg:npm/a@1.0.0"
+        );
+    }
+
+    const NPM_PACKAGES: &str = r#"
+        "": {"name": "app", "dependencies": {"a": "^1.0.0", "@scope/c": "^3.0.0"}},
+        "node_modules/a": {"version": "1.0.0", "dependencies": {"b": "^2.0.0"}},
+        "node_modules/a/node_modules/b": {"version": "2.0.0", "dev": true},
+        "node_modules/b": {"version": "1.0.0", "integrity": "sha512-b"},
+        "node_modules/@scope/c": {"version": "3.0.0", "dependencies": {"b": "^1.0.0"}},
+        "node_modules/workspace": {"resolved": "packages/workspace", "link": true}
+    "#;
+
+    #[test]
+    fn parses_npm_v1() {
+        let data = r#"{
+            "lockfileVersion": 1,
+            "dependencies": {
+                "@scope/c": {"version": "3.0.0", "requires": {"b": "^1.0.0"}},
+                "a": {
+                    "version": "1.0.0",
+                    "requires": {"b": "^2.0.0"},
+                    "dependencies": {"b": {"version": "2.0.0", "dev": true}}
+                },
+                "b": {"version": "1.0.0", "integrity": "sha512-b"}
+            }
+        }"#;
+        let lockfile = Lockfile::from_npm(data, Path::new("/app")).unwrap();
+        assert_lockfile(&lockfile);
+        assert!(lockfile
+            .packages
+            .iter()
+            .any(|p| p.version == "2.0.0" && p.properties.contains_key("dev")));
+    }
+
+    #[test]
+    fn parses_npm_packages() {
+        // version 2 keeps the dependencies of version 1 for older clients
+        for data in [
+            format!(
+                r#"{{"lockfileVersion": 3, "packages": {{{}}}}}"#,
+                NPM_PACKAGES
+            ),
+            format!(
+                r#"{{"lockfileVersion": 2, "packages": {{{}}}, "dependencies": {{"d": {{"version": "1.0.0"}}}}}}"#,
+                NPM_PACKAGES
+            ),
+        ] {
+            let lockfile = Lockfile::from_npm(&data, Path::new("/app")).unwrap();
+            assert_lockfile(&lockfile);
+
+            let nested = lockfile
+                .packages
+                .iter()
+                .find(|p| p.version == "2.0.0")
+                .unwrap();
+            assert_eq!(nested.path, "/app/node_modules/a/node_modules/b");
+            assert_eq!(nested.properties.get("dev").unwrap(), "true");
+            // only the packages installed at the top level are resolved by name
+            assert_eq!(
+                lockfile.resolve("b", "^2.0.0").unwrap().to_string(),
+                "pkg:npm/b@1.0.0"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_yarn_v1() {
+        let data = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@scope/c@^3.0.0":
+  version "3.0.0"
+  resolved "https://registry.yarnpkg.com/@scope/c/-/c-3.0.0.tgz"
+  integrity sha512-c
+  dependencies:
+    b "^1.0.0"
+
+a@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    b "^2.0.0"
+
+b@^1.0.0, b@^1.0.1:
+  version "1.0.0"
+
+b@^2.0.0:
+  version "2.0.0"
+"#;
+        let lockfile = Lockfile::from_yarn(data);
+        assert_lockfile(&lockfile);
+        assert_eq!(
+            lockfile.resolve("b", "^1.0.1").unwrap().to_string(),
+            "pkg:npm/b@1.0.0"
+        );
+        let scoped = lockfile.packages.iter().find(|p| p.name == "@scope/c");
+        assert_eq!(
+            scoped.unwrap().properties.get("integrity").unwrap(),
+            "sha512-c"
+        );
+    }
+
+    #[test]
+    fn parses_yarn_berry() {
+        let data = r#"# This file is generated by running "yarn install" inside your project.
+
+__metadata:
+  version: 6
+  cacheKey: 8
+
+"@scope/c@npm:^3.0.0":
+  version: 3.0.0
+  resolution: "@scope/c@npm:3.0.0"
+  dependencies:
+    b: ^1.0.0
+  checksum: 0c
+  languageName: node
+  linkType: hard
+
+"a@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  dependencies:
+    b: ^2.0.0
+
+"app@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "app@workspace:."
+  dependencies:
+    "@scope/c": ^3.0.0
+    a: ^1.0.0
+
+"b@npm:^1.0.0":
+  version: 1.0.0
+
+"b@npm:^2.0.0":
+  version: 2.0.0
+"#;
+        let lockfile = Lockfile::from_yarn(data);
+        assert_lockfile(&lockfile);
+        let scoped = lockfile.packages.iter().find(|p| p.name == "@scope/c");
+        assert_eq!(scoped.unwrap().properties.get("integrity").unwrap(), "0c");
+    }
+
+    #[test]
+    fn parses_pnpm_v6() {
+        let data = r#"lockfileVersion: '6.0'
+
+dependencies:
+  '@scope/c':
+    specifier: ^3.0.0
+    version: 3.0.0(b@1.0.0)
+  a:
+    specifier: ^1.0.0
+    version: 1.0.0
+
+packages:
+
+  /@scope/c@3.0.0(b@1.0.0):
+    resolution: {integrity: sha512-c}
+    peerDependencies:
+      b: ^1.0.0
+    dependencies:
+      b: 1.0.0
+    dev: false
+
+  /a@1.0.0:
+    resolution: {integrity: sha512-a}
+    dependencies:
+      b: 2.0.0
+    dev: false
+
+  /b@1.0.0:
+    resolution: {integrity: sha512-b}
+    dev: false
+
+  /b@2.0.0:
+    resolution: {integrity: sha512-b2}
+    dev: true
+"#;
+        let lockfile = Lockfile::from_pnpm(data).unwrap();
+        assert_lockfile(&lockfile);
+        assert_eq!(
+            lockfile.resolve("@scope/c", "^3.0.0").unwrap().to_string(),
+            "pkg:npm/%40scope/c@3.0.0"
+        );
+    }
+
+    #[test]
+    fn parses_pnpm_v9() {
+        let data = r#"lockfileVersion: '9.0'
+
+importers:
+
+  .:
+    dependencies:
+      a:
+        specifier: ^1.0.0
+        version: 1.0.0
+      alias:
+        specifier: npm:b@^2.0.0
+        version: b@2.0.0
+      local:
+        specifier: link:../local
+        version: link:../local
+
+packages:
+
+  '@scope/c@3.0.0':
+    resolution: {integrity: sha512-c}
+
+  a@1.0.0:
+    resolution: {integrity: sha512-a}
+
+  b@1.0.0:
+    resolution: {integrity: sha512-b}
+
+  b@2.0.0:
+    resolution: {integrity: sha512-b2}
+
+snapshots:
+
+  '@scope/c@3.0.0':
+    dependencies:
+      b: 1.0.0
+
+  a@1.0.0:
+    dependencies:
+      b: 2.0.0
+
+  b@1.0.0: {}
+
+  b@2.0.0: {}
+"#;
+        let lockfile = Lockfile::from_pnpm(data).unwrap();
+        assert_lockfile(&lockfile);
+        assert_eq!(
+            lockfile.resolve("alias", "").unwrap().to_string(),
+            "pkg:npm/b@2.0.0"
+        );
+        assert!(lockfile.resolve("local", "").is_none());
+        let a = lockfile.packages.iter().find(|p| p.name == "a").unwrap();
+        assert_eq!(a.properties.get("integrity").unwrap(), "sha512-a");
+    }
+
+    fn write_manifest(dir: &Path, manifest: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(MANIFEST), manifest).unwrap();
+    }
+
+    #[test]
+    fn resolves_node_modules() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("opt/app");
+        write_manifest(
+            &project,
+            r#"{"name": "app", "version": "0.1.0", "dependencies": {"a": "^1.0.0"},
+                "devDependencies": {"@scope/c": "^3.0.0"}}"#,
+        );
+        let modules = project.join(NODE_MODULES);
+        write_manifest(
+            &modules.join("a"),
+            r#"{"name": "a", "version": "1.0.0", "author": "Jane Doe <jane@example.com>",
+                "license": "MIT", "dependencies": {"b": "^2.0.0"}}"#,
+        );
+        write_manifest(
+            &modules.join("a/node_modules/b"),
+            r#"{"name": "b", "version": "2.0.0"}"#,
+        );
+        write_manifest(&modules.join("b"), r#"{"name": "b", "version": "1.0.0"}"#);
+        write_manifest(
+            &modules.join("@scope/c"),
+            r#"{"name": "@scope/c", "version": "3.0.0", "dependencies": {"b": "^1.0.0"}}"#,
+        );
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf()))
+            .unwrap()
+            .with_paths(&["/opt".to_owned()]);
+        let mut collector = Collector::new(&ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect().unwrap();
+        assert!(collected.diagnostics.is_empty());
+
+        let mut expected = expected_graph();
+        expected.push((
+            "pkg:npm/app@0.1.0".to_owned(),
+            vec![
+                "pkg:npm/%40scope/c@3.0.0".to_owned(),
+                "pkg:npm/a@1.0.0".to_owned(),
+            ],
+        ));
+        expected.sort();
+        assert_eq!(
+            graph(collected.components.iter().map(|c| c.as_ref())),
+            expected
+        );
+
+        let a = collected
+            .components
+            .iter()
+            .find(|c| c.name() == "a")
+            .unwrap();
+        assert_eq!(a.kind(), Kind::Dependency);
+        assert_eq!(a.path(), "/opt/app/node_modules/a");
+        assert_eq!(a.publishers(), &vec!["Jane Doe".to_owned()]);
+        assert_eq!(a.license(), Some("MIT"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinked_manifests_in_root() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        write_manifest(
+            outside.path(),
+            r#"{"name": "host-secret-leaked", "version": "9"}"#,
+        );
+        let secret = outside.path().join(MANIFEST);
+
+        let project = root.path().join("opt/app");
+        let modules = project.join(NODE_MODULES);
+        write_manifest(
+            &modules.join("a"),
+            r#"{"name": "a", "version": "1.0.0", "dependencies": {"b": "^2.0.0"}}"#,
+        );
+        fs::create_dir_all(modules.join("b")).unwrap();
+        symlink(&secret, modules.join("b").join(MANIFEST)).unwrap();
+        symlink(&secret, project.join(MANIFEST)).unwrap();
+        symlink(&secret, project.join("package-lock.json")).unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf()))
+            .unwrap()
+            .with_paths(&["/opt".to_owned()]);
+        let mut collector = Collector::new(&ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect().unwrap();
+
+        assert_eq!(
+            graph(collected.components.iter().map(|c| c.as_ref())),
+            vec![("pkg:npm/a@1.0.0".to_owned(), vec![])]
+        );
+    }
+}
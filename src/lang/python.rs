@@ -0,0 +1,727 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose};
+use base64::{alphabet, Engine};
+use chrono::{DateTime, Utc};
+
+use super::{normalize, SearchPath};
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, OwnedFile};
+use crate::purl::Purl;
+use crate::Error;
+
+// interpreter prefixes, virtual environments and user site-packages (~/.local/lib/pythonX.Y) of
+// linux, macOS (python.org installer, homebrew under /opt and /usr/local) and windows
+const DEFAULT_PATHS: &[&str] = &[
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/local/lib",
+    "/opt",
+    "/home",
+    "/root",
+    "/Library/Frameworks/Python.framework",
+    "/Users",
+    "/Program Files",
+    "/ProgramData",
+];
+// https://packaging.python.org/en/latest/specifications/recording-installed-packages/
+const DIST_INFO_EXTENSION: &str = ".dist-info";
+const EGG_INFO_EXTENSION: &str = ".egg-info";
+const VENV_CONFIG: &str = "pyvenv.cfg";
+// how far a site-packages folder can be from the root of its environment, lib/pythonX.Y/site-packages
+const MAX_ENVIRONMENT_DEPTH: usize = 4;
+// RECORD hashes are urlsafe base64, padding is not supposed to be there but some tools add it
+const RECORD_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    general_purpose::NO_PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// RFC 822 style headers, the description follows the first empty line
+fn parse_headers(data: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = vec![];
+
+    for line in data.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+
+    headers
+}
+
+// "Jane Doe <jane@example.com>, bob@example.com"
+fn people(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter_map(|person| {
+            let person = person.trim();
+            let name = match person.split_once('<') {
+                Some((name, email)) if name.trim().is_empty() => email.trim_end_matches('>'),
+                Some((name, _)) => name,
+                None => person,
+            };
+            Some(name.trim().trim_matches('"').to_owned()).filter(|name| !name.is_empty())
+        })
+        .collect()
+}
+
+#[derive(Debug, Default)]
+struct Package {
+    name: String,
+    version: String,
+    path: String,
+    modified: DateTime<Utc>,
+    publishers: Vec<String>,
+    license: Option<String>,
+    requested: bool,
+    files: Vec<OwnedFile>,
+    properties: BTreeMap<String, String>,
+}
+
+impl Package {
+    fn set_metadata(&mut self, data: &str) {
+        let mut authors = vec![];
+        let mut author_emails = vec![];
+        let mut expression = None;
+        let mut license = None;
+        let mut classifier = None;
+
+        for (key, value) in parse_headers(data) {
+            if value.is_empty() || value == "UNKNOWN" {
+                continue;
+            }
+            match key.as_str() {
+                "Name" => self.name = value,
+                "Version" => self.version = value,
+                "Author" => authors.push(value),
+                "Author-email" => author_emails.append(&mut people(&value)),
+                "License-Expression" => expression = Some(value),
+                // some packages paste the whole license text
+                "License" if !value.contains('\n') => license = Some(value),
+                "Classifier" if classifier.is_none() => {
+                    classifier = value
+                        .strip_prefix("License :: ")
+                        .and_then(|license| license.rsplit(" :: ").next())
+                        .map(|license| license.to_owned());
+                }
+                _ => {}
+            }
+        }
+
+        self.publishers = if authors.is_empty() {
+            author_emails
+        } else {
+            authors
+        };
+        self.license = expression.or(license).or(classifier);
+    }
+
+    fn set_environment(&mut self, site: &Path, ctx: &collector::Context) {
+        for path in site.ancestors().take(MAX_ENVIRONMENT_DEPTH + 1) {
+            if let Ok(config) = fs::read_to_string(ctx.path(path.join(VENV_CONFIG))) {
+                self.properties.insert(
+                    "environment".to_owned(),
+                    path.to_string_lossy().into_owned(),
+                );
+                self.properties.insert("venv".to_owned(), "true".to_owned());
+                for (key, value) in config.lines().filter_map(|line| line.split_once('=')) {
+                    if matches!(key.trim(), "version" | "version_info") {
+                        self.properties
+                            .insert("python".to_owned(), value.trim().to_owned());
+                    }
+                }
+                return;
+            }
+        }
+
+        // <prefix>/lib/pythonX.Y/site-packages, <prefix>/lib/python3/dist-packages or <prefix>\Lib\site-packages
+        for path in site.ancestors().take(MAX_ENVIRONMENT_DEPTH) {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let prefix = if let Some(version) = name.strip_prefix("python") {
+                if !version.starts_with(|c: char| c.is_ascii_digit()) {
+                    continue;
+                }
+                self.properties
+                    .insert("python".to_owned(), version.to_owned());
+                path.parent().and_then(|lib| lib.parent())
+            } else if name == "Lib" {
+                path.parent()
+            } else {
+                continue;
+            };
+
+            if let Some(prefix) = prefix {
+                self.properties.insert(
+                    "environment".to_owned(),
+                    prefix.to_string_lossy().into_owned(),
+                );
+            }
+            return;
+        }
+    }
+
+    // path,algorithm=urlsafe-base64-digest,size, a bad line or hash is reported without failing the package
+    fn set_record(&mut self, site: &Path, data: &str, source: &Path, collected: &mut Collected) {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data.as_bytes());
+
+        for (idx, record) in reader.records().enumerate() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    collected.warn(Error::parse_line(source.display(), idx + 1, e));
+                    continue;
+                }
+            };
+            let path = match record.get(0) {
+                Some(path) if !path.is_empty() => path,
+                _ => continue,
+            };
+
+            let mut file = OwnedFile {
+                path: normalize(&site.join(path)),
+                size: record.get(2).and_then(|size| size.parse().ok()),
+                ..Default::default()
+            };
+            if let Some((algorithm, digest)) = record.get(1).and_then(|hash| hash.split_once('=')) {
+                match RECORD_ENGINE.decode(digest) {
+                    Ok(digest) => {
+                        file.hashes.insert(
+                            algorithm.to_owned(),
+                            digest.iter().map(|b| format!("{:02x}", b)).collect(),
+                        );
+                    }
+                    Err(e) => collected.warn(Error::parse_line(
+                        source.display(),
+                        idx + 1,
+                        format!("invalid hash: {}", e),
+                    )),
+                }
+            }
+            self.files.push(file);
+        }
+    }
+
+    // installed-files.txt of setuptools, paths are relative to the .egg-info folder
+    fn set_installed_files(&mut self, metadata_dir: &Path, data: &str) {
+        self.files = data
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| OwnedFile {
+                path: normalize(&metadata_dir.join(line)),
+                ..Default::default()
+            })
+            .collect();
+    }
+
+    // the folder shared by the installed modules, the metadata and the scripts are left out
+    fn set_path(&mut self, site: &Path, metadata_dir: &Path) {
+        let modules: Vec<String> = self
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .filter(|path| {
+                let path = Path::new(path);
+                path.starts_with(site) && !path.starts_with(metadata_dir)
+            })
+            .collect();
+
+        self.path = if modules.is_empty() {
+            site.to_string_lossy().into_owned()
+        } else {
+            crate::linux::install_path(&modules)
+        };
+    }
+}
+
+impl ComponentTrait for Package {
+    // pip writes REQUESTED for the packages installed explicitly, the others were pulled as dependencies
+    fn kind(&self) -> Kind {
+        if self.requested || self.properties.get("installer").map(|i| i.as_str()) != Some("pip") {
+            Kind::Application
+        } else {
+            Kind::Dependency
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(Purl::new("pypi", &self.name).with_version(&self.version))
+    }
+
+    fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+
+    fn files(&self) -> Vec<OwnedFile> {
+        self.files.clone()
+    }
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    paths: Vec<String>,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            paths: vec![],
+        }
+    }
+
+    // metadata_dir is either a .dist-info folder, an .egg-info folder or a single .egg-info file,
+    // each file is resolved in the inspected system as any of them can be a symlink
+    fn collect_package(
+        &self,
+        metadata_dir: &Path,
+        collected: &mut Collected,
+    ) -> Result<Package, Error> {
+        let site = metadata_dir.parent().unwrap_or(metadata_dir);
+        let file = |name: &str| self.ctx.path(metadata_dir.join(name));
+        let on_host = self.ctx.path(metadata_dir);
+        let (metadata_path, is_egg) = if on_host.is_file() {
+            (on_host, true)
+        } else if metadata_dir.to_string_lossy().ends_with(EGG_INFO_EXTENSION) {
+            (file("PKG-INFO"), true)
+        } else {
+            (file("METADATA"), false)
+        };
+
+        let metadata =
+            fs::read_to_string(&metadata_path).map_err(|e| Error::io(&metadata_path, e))?;
+        let mut package = Package {
+            modified: fs::metadata(&metadata_path)
+                .and_then(|meta| meta.modified())
+                .map(DateTime::from)
+                .unwrap_or_default(),
+            requested: file("REQUESTED").exists(),
+            ..Default::default()
+        };
+        package.set_metadata(&metadata);
+        if package.name.is_empty() {
+            return Err(Error::parse(metadata_path.display(), "missing Name field"));
+        }
+
+        if let Ok(installer) = fs::read_to_string(file("INSTALLER")) {
+            package
+                .properties
+                .insert("installer".to_owned(), installer.trim().to_owned());
+        }
+        package.set_environment(site, &self.ctx);
+
+        if is_egg {
+            if let Ok(data) = fs::read_to_string(file("installed-files.txt")) {
+                package.set_installed_files(metadata_dir, &data);
+            }
+        } else {
+            let record_path = file("RECORD");
+            // RECORD is optional, distributions can remove it
+            if let Ok(data) = fs::read_to_string(&record_path) {
+                package.set_record(site, &data, &record_path, collected);
+            }
+        }
+        package.set_path(site, metadata_dir);
+
+        Ok(package)
+    }
+}
+
+fn is_metadata(name: &str, is_dir: bool) -> bool {
+    (is_dir && name.ends_with(DIST_INFO_EXTENSION)) || name.ends_with(EGG_INFO_EXTENSION)
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        self.paths = self.ctx.search_paths(DEFAULT_PATHS);
+        if self.paths.is_empty() {
+            return Err(Error::unsupported("no python search path found"));
+        }
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        log::info!("searching python packages in {} ...", self.paths.join(", "));
+
+        let mut collected = Collected::new("python");
+        // the same environment can be reached from more than one search path, such as /usr/lib64
+        let mut seen = HashSet::new();
+
+        for path in &self.paths {
+            let search = SearchPath::new(&self.ctx, path);
+            for entry in search.entries() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        continue;
+                    }
+                };
+                let name = entry.file_name().to_string_lossy();
+                if !is_metadata(&name, entry.file_type().is_dir()) {
+                    continue;
+                }
+                let metadata_dir = search.inspected(entry.path());
+                let on_host = self.ctx.path(&metadata_dir);
+                if !seen.insert(fs::canonicalize(&on_host).unwrap_or(on_host)) {
+                    continue;
+                }
+
+                match self.collect_package(Path::new(&metadata_dir), &mut collected) {
+                    Ok(package) => collected.push(package),
+                    Err(e) => collected.warn(e),
+                }
+            }
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::Collector as _;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn write(path: &Path, data: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    fn file_hashes(package: &Package) -> Vec<(&str, Option<&str>)> {
+        package
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.path.as_str(),
+                    file.hashes.get("sha256").map(|h| h.as_str()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_metadata() {
+        let mut package = Package::default();
+        package.set_metadata(
+            "Metadata-Version: 2.1\n\
+             Name: requests\n\
+             Version: 2.31.0\n\
+             Author-email: Kenneth Reitz <me@kennethreitz.org>, \"Nate\" <nate@example.com>\n\
+             License: Apache 2.0\n\
+             Classifier: License :: OSI Approved :: Apache Software License\n\
+             Description: first line\n        \
+             second line\n\
+             \n\
+             Author: not a header\n",
+        );
+        assert_eq!(package.name, "requests");
+        assert_eq!(package.version, "2.31.0");
+        assert_eq!(package.publishers, vec!["Kenneth Reitz", "Nate"]);
+        assert_eq!(package.license.as_deref(), Some("Apache 2.0"));
+
+        let mut package = Package::default();
+        package.set_metadata(
+            "Name: six\nVersion: 1.16.0\nAuthor: UNKNOWN\nLicense: UNKNOWN\n\
+             Classifier: License :: OSI Approved :: MIT License\n",
+        );
+        assert!(package.publishers.is_empty());
+        assert_eq!(package.license.as_deref(), Some("MIT License"));
+    }
+
+    #[test]
+    fn decodes_record_hashes() {
+        let site = Path::new("/usr/lib/python3/dist-packages");
+        let mut collected = Collected::new("python");
+        let mut package = Package::default();
+        package.set_record(
+            site,
+            "six.py,sha256=LPJNul-wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ,5\n\
+             six-1.16.0.dist-info/METADATA,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU=,0\n\
+             six-1.16.0.dist-info/WHEEL,sha256=not*base64,12\n\
+             six-1.16.0.dist-info/RECORD,,\n\
+             ,,\n",
+            Path::new("/usr/lib/python3/dist-packages/six-1.16.0.dist-info/RECORD"),
+            &mut collected,
+        );
+
+        // the file with a bad hash is kept without it
+        assert_eq!(
+            file_hashes(&package),
+            vec![
+                ("/usr/lib/python3/dist-packages/six.py", Some(HELLO_SHA256)),
+                (
+                    "/usr/lib/python3/dist-packages/six-1.16.0.dist-info/METADATA",
+                    Some(EMPTY_SHA256)
+                ),
+                (
+                    "/usr/lib/python3/dist-packages/six-1.16.0.dist-info/WHEEL",
+                    None
+                ),
+                (
+                    "/usr/lib/python3/dist-packages/six-1.16.0.dist-info/RECORD",
+                    None
+                ),
+            ]
+        );
+        assert_eq!(package.files[0].size, Some(5));
+        assert_eq!(package.files[3].size, None);
+        assert_eq!(collected.diagnostics.len(), 1);
+        assert!(collected.diagnostics[0]
+            .message
+            .contains("dist-info/RECORD:3"));
+    }
+
+    #[test]
+    fn collects_packages() {
+        let root = tempfile::tempdir().unwrap();
+        let path = |path: &str| root.path().join(path);
+
+        // pip in a virtual environment
+        write(
+            &path("opt/venv/pyvenv.cfg"),
+            "home = /usr/bin\nversion = 3.11.2\n",
+        );
+        let site = "opt/venv/lib/python3.11/site-packages";
+        let requests = format!("{}/requests-2.31.0.dist-info", site);
+        write(
+            &path(&format!("{}/METADATA", requests)),
+            "Name: requests\nVersion: 2.31.0\n",
+        );
+        write(&path(&format!("{}/INSTALLER", requests)), "pip\n");
+        write(&path(&format!("{}/REQUESTED", requests)), "");
+        write(
+            &path(&format!("{}/RECORD", requests)),
+            "requests/__init__.py,sha256=LPJNul-wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ,5\n\
+             requests/api.py,sha256=%%%,10\n\
+             requests-2.31.0.dist-info/RECORD,,\n\
+             ../../../bin/normalizer,sha256=47DEQpj8HBSa-_TImW-5JCeuQeRkm5NMpJWZG3hSuFU,0\n",
+        );
+        let idna = format!("{}/idna-3.6.dist-info", site);
+        write(
+            &path(&format!("{}/METADATA", idna)),
+            "Name: idna\nVersion: 3.6\n",
+        );
+        write(&path(&format!("{}/INSTALLER", idna)), "pip\n");
+
+        // distribution packages, a setuptools egg-info folder and a single egg-info file
+        write(
+            &path("usr/lib/python3/dist-packages/six-1.16.0.dist-info/METADATA"),
+            "Name: six\nVersion: 1.16.0\n",
+        );
+        let legacy = "usr/local/lib/python3.9/site-packages/legacy-1.0-py3.9.egg-info";
+        write(
+            &path(&format!("{}/PKG-INFO", legacy)),
+            "Name: legacy\nVersion: 1.0\n",
+        );
+        write(
+            &path(&format!("{}/installed-files.txt", legacy)),
+            "../legacy/__init__.py\n../legacy/core.py\nPKG-INFO\n",
+        );
+        write(
+            &path("usr/lib/python3/dist-packages/simple-0.1.egg-info"),
+            "Name: simple\nVersion: 0.1\n",
+        );
+        // missing Name field
+        write(
+            &path("usr/lib/python3/dist-packages/broken-1.0.dist-info/METADATA"),
+            "Version: 1.0\n",
+        );
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let mut collector = Collector::new(&ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect().unwrap();
+
+        let messages: Vec<&str> = collected
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages.iter().any(
+            |m| m.contains("broken-1.0.dist-info/METADATA") && m.contains("missing Name field")
+        ));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("requests-2.31.0.dist-info/RECORD:2")));
+
+        let mut packages: Vec<_> = collected
+            .components
+            .iter()
+            .map(|c| {
+                (
+                    c.name().to_owned(),
+                    c.version().to_owned(),
+                    c.kind(),
+                    c.path().to_owned(),
+                    c.properties(),
+                )
+            })
+            .collect();
+        packages.sort_by(|a, b| a.0.cmp(&b.0));
+        let properties = |props: &[(&str, &str)]| -> BTreeMap<String, String> {
+            props
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let venv = properties(&[
+            ("environment", "/opt/venv"),
+            ("installer", "pip"),
+            ("python", "3.11.2"),
+            ("venv", "true"),
+        ]);
+        assert_eq!(
+            packages,
+            vec![
+                (
+                    "idna".to_owned(),
+                    "3.6".to_owned(),
+                    Kind::Dependency,
+                    format!("/{}", site),
+                    venv.clone()
+                ),
+                (
+                    "legacy".to_owned(),
+                    "1.0".to_owned(),
+                    Kind::Application,
+                    "/usr/local/lib/python3.9/site-packages/legacy".to_owned(),
+                    properties(&[("environment", "/usr/local"), ("python", "3.9")])
+                ),
+                (
+                    "requests".to_owned(),
+                    "2.31.0".to_owned(),
+                    Kind::Application,
+                    format!("/{}/requests", site),
+                    venv
+                ),
+                (
+                    "simple".to_owned(),
+                    "0.1".to_owned(),
+                    Kind::Application,
+                    "/usr/lib/python3/dist-packages".to_owned(),
+                    properties(&[("environment", "/usr"), ("python", "3")])
+                ),
+                (
+                    "six".to_owned(),
+                    "1.16.0".to_owned(),
+                    Kind::Application,
+                    "/usr/lib/python3/dist-packages".to_owned(),
+                    properties(&[("environment", "/usr"), ("python", "3")])
+                ),
+            ]
+        );
+
+        let requests = collected
+            .components
+            .iter()
+            .find(|c| c.name() == "requests")
+            .unwrap();
+        let files: Vec<(String, Option<String>)> = requests
+            .files()
+            .into_iter()
+            .map(|file| (file.path, file.hashes.get("sha256").cloned()))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (
+                    format!("/{}/requests/__init__.py", site),
+                    Some(HELLO_SHA256.to_owned())
+                ),
+                (format!("/{}/requests/api.py", site), None),
+                (format!("/{}/requests-2.31.0.dist-info/RECORD", site), None),
+                (
+                    "/opt/venv/bin/normalizer".to_owned(),
+                    Some(EMPTY_SHA256.to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinked_metadata_in_root() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let secret = outside.path().join("secret");
+        write(&secret, "Name: host-secret-leaked\nVersion: 9\n");
+
+        let site = root.path().join("usr/lib/python3/dist-packages");
+        fs::create_dir_all(site.join("other-1.0.dist-info")).unwrap();
+        symlink(&secret, site.join("evil.egg-info")).unwrap();
+        symlink(&secret, site.join("other-1.0.dist-info/METADATA")).unwrap();
+        write(
+            &site.join("six-1.16.0.dist-info/METADATA"),
+            "Name: six\nVersion: 1.16.0\n",
+        );
+        symlink(&secret, site.join("six-1.16.0.dist-info/INSTALLER")).unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let mut collector = Collector::new(&ctx);
+        collector.setup().unwrap();
+        let collected = collector.collect().unwrap();
+
+        let names: Vec<&str> = collected.components.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["six"]);
+        assert!(!collected.components[0]
+            .properties()
+            .contains_key("installer"));
+        assert_eq!(collected.diagnostics.len(), 2);
+    }
+}
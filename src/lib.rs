@@ -0,0 +1,36 @@
+//! Build a software bill of materials (SBOM) on any platform.
+//!
+//! The [`Scanner`] runs the collectors supported by the inspected system and returns its
+//! [`Component`]s in an [`Sbom`], which can then be written in any of the [`format`]s.
+mod binary;
+mod collector;
+mod component;
+mod cpe;
+pub mod diff;
+mod elf;
+mod error;
+pub mod format;
+mod graph;
+mod hashes;
+mod lang;
+mod macho;
+mod pe;
+mod purl;
+mod sbom;
+mod scanner;
+mod utils;
+mod version;
+mod vulns;
+
+mod linux;
+mod macos;
+mod windows;
+
+pub use collector::{CollectorKind, Input};
+pub use component::{Component, ComponentTrait, Kind, OwnedFile, Relationship, RelationshipKind};
+pub use error::Error;
+pub use hashes::HashAlgorithm;
+pub use purl::Purl;
+pub use sbom::{Diagnostic, Sbom};
+pub use scanner::Scanner;
+pub use vulns::Finding;
@@ -0,0 +1,384 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::linux::{self, os::Distro, Dependent};
+use crate::purl::Purl;
+use crate::Error;
+
+const APK_INSTALLED_PATH: &str = "/lib/apk/db/installed";
+
+pub(crate) fn is_available(ctx: &collector::Context) -> bool {
+    ctx.path(APK_INSTALLED_PATH).exists()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Package {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub license: String,
+    pub origin: String,
+    pub modified: DateTime<Utc>,
+    pub files: Vec<String>,
+
+    path: String,
+    publishers: Vec<String>,
+    #[serde(skip)]
+    distro: Distro,
+    #[serde(skip)]
+    provides: Vec<String>,
+    #[serde(skip)]
+    requires: Vec<Vec<String>>,
+    #[serde(skip)]
+    dependencies: Vec<Purl>,
+}
+
+// "so:libc.musl-x86_64.so.1 musl>=1.2 !conflict", the versions are dropped and the conflicts skipped
+fn parse_names(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split_whitespace()
+        .filter(|name| !name.starts_with('!'))
+        .filter_map(|name| name.split(['<', '>', '=', '~']).next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        Kind::Application
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            self.distro.apply(
+                Purl::new("apk", &self.name)
+                    .with_namespace("alpine")
+                    .with_version(&self.version)
+                    .with_qualifier("arch", &self.arch),
+            ),
+        )
+    }
+
+    fn license(&self) -> Option<&str> {
+        if self.license.is_empty() {
+            None
+        } else {
+            Some(&self.license)
+        }
+    }
+
+    fn origin(&self) -> Option<&str> {
+        if self.origin.is_empty() {
+            None
+        } else {
+            Some(&self.origin)
+        }
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect()
+    }
+}
+
+impl Dependent for Package {
+    fn provides(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.provides.iter().map(|name| name.as_str()));
+        names
+    }
+
+    fn requires(&self) -> &[Vec<String>] {
+        &self.requires
+    }
+
+    fn set_dependencies(&mut self, dependencies: Vec<Purl>) {
+        self.dependencies = dependencies;
+    }
+}
+
+pub(crate) struct Collector {
+    installed_path: PathBuf,
+    distro: Distro,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context, distro: Distro) -> Self {
+        Self {
+            installed_path: ctx.path(APK_INSTALLED_PATH),
+            distro,
+        }
+    }
+
+    // https://wiki.alpinelinux.org/wiki/Apk_spec#Installed_Database_V2
+    // malformed lines are skipped and reported
+    fn parse_installed(&self, installed: &str, collected: &mut Collected) -> Vec<Package> {
+        let mut packages = vec![];
+        let mut package = Package::default();
+        // file records are relative to the last directory record
+        let mut directory = String::new();
+
+        for (line_num, line) in installed.lines().enumerate() {
+            if line.trim().is_empty() {
+                if !package.name.is_empty() {
+                    packages.push(package);
+                }
+                package = Package::default();
+                directory.clear();
+                continue;
+            }
+
+            let (key, value) = match line.split_once(':') {
+                Some(pair) => pair,
+                None => {
+                    collected.warn(Error::parse_line(
+                        self.installed_path.display(),
+                        line_num + 1,
+                        format!("unexpected line {:?}", line),
+                    ));
+                    continue;
+                }
+            };
+
+            match key {
+                "P" => package.name = value.to_owned(),
+                "V" => package.version = value.to_owned(),
+                "A" => package.arch = value.to_owned(),
+                "L" => package.license = value.to_owned(),
+                "o" => package.origin = value.to_owned(),
+                "m" => package.publishers = vec![value.to_owned()],
+                "D" => package
+                    .requires
+                    .extend(parse_names(value).map(|name| vec![name])),
+                "p" => package.provides.extend(parse_names(value)),
+                "t" => match value.parse::<i64>() {
+                    Ok(timestamp) => {
+                        package.modified =
+                            Utc.timestamp_opt(timestamp, 0).single().unwrap_or_default()
+                    }
+                    Err(e) => collected.warn(Error::parse_line(
+                        self.installed_path.display(),
+                        line_num + 1,
+                        format!("invalid build timestamp: {}", e),
+                    )),
+                },
+                "F" => {
+                    directory = value.to_owned();
+                    package.files.push(format!("/{}", &directory));
+                }
+                "R" => {
+                    if directory.is_empty() {
+                        package.files.push(format!("/{}", value));
+                    } else {
+                        package.files.push(format!("/{}/{}", &directory, value));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !package.name.is_empty() {
+            packages.push(package);
+        }
+
+        packages
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, installed: &str) -> Result<Collected, Error> {
+        let mut collected = Collected::new("apk");
+
+        let mut packages = self.parse_installed(installed, &mut collected);
+        for package in &mut packages {
+            package.path = linux::install_path(&package.files);
+            package.distro = self.distro.clone();
+        }
+
+        linux::resolve_dependencies(&mut packages);
+        for package in packages {
+            collected.own(&package.files, package.purl());
+            collected.push(package);
+        }
+
+        Ok(collected)
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let installed = fs::read_to_string(&self.installed_path)
+            .map_err(|e| Error::io(&self.installed_path, e))?;
+
+        self.collect_from_json(&installed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::Collector as _;
+
+    const INSTALLED: &str = "\
+C:Q1Zq8cRnLGaW2z5DWeH2+Ls+S6Ob4=
+P:musl
+V:1.2.4-r2
+A:x86_64
+T:the musl c library (libc) implementation
+L:MIT
+o:musl
+m:Natanael Copa <ncopa@alpinelinux.org>
+t:1697652467
+p:so:libc.musl-x86_64.so.1=1
+R:ld-musl-x86_64.so.1
+F:lib
+R:libc.musl-x86_64.so.1
+Z:Q1Ek8cYx5GQ5mS7mIj5Fh2GDEbDzU=
+
+P:busybox
+V:1.36.1-r5
+A:x86_64
+L:GPL-2.0-only
+t:yesterday
+D:so:libc.musl-x86_64.so.1 musl>=1.2 !busybox-extras /bin/sh
+p:/bin/sh cmd:busybox=1.36.1-r5
+F:bin
+R:busybox
+this line has no field
+F:etc
+R:securetty
+";
+
+    fn collect() -> Collected {
+        let root = tempfile::tempdir().unwrap();
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        Collector::new(&ctx, Distro::default())
+            .collect_from_json(INSTALLED)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_installed_database() {
+        let collected = collect();
+
+        let components: Vec<_> = collected
+            .components
+            .iter()
+            .map(|comp| (comp.name(), comp.version(), comp.license(), comp.origin()))
+            .collect();
+        assert_eq!(
+            components,
+            vec![
+                ("musl", "1.2.4-r2", Some("MIT"), Some("musl")),
+                ("busybox", "1.36.1-r5", Some("GPL-2.0-only"), None),
+            ]
+        );
+
+        let musl = &collected.components[0];
+        assert_eq!(
+            musl.purl().unwrap().to_string(),
+            "pkg:apk/alpine/musl@1.2.4-r2?arch=x86_64"
+        );
+        assert_eq!(
+            musl.publishers(),
+            &vec!["Natanael Copa <ncopa@alpinelinux.org>".to_owned()]
+        );
+        assert_eq!(musl.modified().timestamp(), 1697652467);
+
+        // the versions, the conflicts and the names provided by the package itself are dropped
+        let busybox = &collected.components[1];
+        let dependencies: Vec<String> = busybox
+            .relationships()
+            .iter()
+            .map(|rel| rel.purl.to_string())
+            .collect();
+        assert_eq!(dependencies, vec![musl.purl().unwrap().to_string()]);
+        assert_eq!(busybox.modified().timestamp(), 0);
+    }
+
+    #[test]
+    fn joins_file_paths() {
+        let collected = collect();
+        let musl = collected.components[0].purl();
+        let busybox = collected.components[1].purl();
+
+        let mut owned: Vec<(&str, &Option<Purl>)> = collected
+            .owned
+            .iter()
+            .map(|(path, owner)| (path.as_str(), owner))
+            .collect();
+        owned.sort();
+        assert_eq!(
+            owned,
+            vec![
+                ("/bin", &busybox),
+                ("/bin/busybox", &busybox),
+                ("/etc", &busybox),
+                ("/etc/securetty", &busybox),
+                // files before any directory are at the root
+                ("/ld-musl-x86_64.so.1", &musl),
+                ("/lib", &musl),
+                ("/lib/libc.musl-x86_64.so.1", &musl),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let collected = collect();
+
+        let messages: Vec<&str> = collected
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(
+            messages[0].contains(":20: invalid build timestamp"),
+            "{}",
+            messages[0]
+        );
+        assert!(
+            messages[1].ends_with(":25: unexpected line \"this line has no field\""),
+            "{}",
+            messages[1]
+        );
+        assert!(collected
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.collector == "apk"));
+    }
+}
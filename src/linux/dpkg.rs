@@ -0,0 +1,433 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::linux::{self, os::Distro, Dependent};
+use crate::purl::Purl;
+use crate::Error;
+
+const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+const DPKG_INFO_PATH: &str = "/var/lib/dpkg/info";
+
+pub(crate) fn is_available(ctx: &collector::Context) -> bool {
+    ctx.path(DPKG_STATUS_PATH).exists()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Package {
+    pub name: String,
+    pub version: String,
+    pub architecture: String,
+    pub status: String,
+    pub modified: DateTime<Utc>,
+    pub files: Vec<String>,
+
+    path: String,
+    publishers: Vec<String>,
+    #[serde(skip)]
+    distro: Distro,
+    source: Option<String>,
+    #[serde(skip)]
+    provides: Vec<String>,
+    #[serde(skip)]
+    requires: Vec<Vec<String>>,
+    #[serde(skip)]
+    dependencies: Vec<Purl>,
+}
+
+// "libc6 (>= 2.34), default-mta | mail-transport-agent, python3:any", the versions and the
+// architecture qualifiers are dropped
+fn parse_relations(value: &str) -> Vec<Vec<String>> {
+    value
+        .split(',')
+        .map(|dependency| {
+            dependency
+                .split('|')
+                .filter_map(|alternative| {
+                    alternative.split([' ', '(', ':']).find(|n| !n.is_empty())
+                })
+                .map(|name| name.to_owned())
+                .collect::<Vec<String>>()
+        })
+        .filter(|alternatives| !alternatives.is_empty())
+        .collect()
+}
+
+impl Package {
+    fn is_installed(&self) -> bool {
+        // "want flag status", we only care about the last one
+        self.status.split_whitespace().last() == Some("installed")
+    }
+
+    fn set_field(&mut self, name: &str, value: &str) {
+        match name {
+            "Package" => self.name = value.to_owned(),
+            "Version" => self.version = value.to_owned(),
+            "Architecture" => self.architecture = value.to_owned(),
+            "Status" => self.status = value.to_owned(),
+            "Maintainer" => self.publishers = vec![value.to_owned()],
+            // "Source: openssl (3.0.11-1)" when the source version differs from the binary one
+            "Source" => self.source = value.split_whitespace().next().map(|s| s.to_owned()),
+            "Depends" | "Pre-Depends" => self.requires.extend(parse_relations(value)),
+            "Provides" => self
+                .provides
+                .extend(parse_relations(value).into_iter().flatten()),
+            _ => {}
+        }
+    }
+
+    fn list_file(&self, ctx: &collector::Context) -> Option<PathBuf> {
+        // multi-arch packages are registered as <name>:<arch>.list, each one is resolved in the
+        // inspected system as the list can be a symlink
+        [
+            format!("{}:{}.list", &self.name, &self.architecture),
+            format!("{}.list", &self.name),
+        ]
+        .iter()
+        .map(|file_name| ctx.path(Path::new(DPKG_INFO_PATH).join(file_name)))
+        .find(|path| path.exists())
+    }
+
+    fn load_files(&mut self, ctx: &collector::Context) -> Result<(), Error> {
+        let list = match self.list_file(ctx) {
+            Some(list) => list,
+            None => {
+                log::debug!("no file list for package {}", &self.name);
+                return Ok(());
+            }
+        };
+
+        let data = fs::read_to_string(&list).map_err(|e| Error::io(&list, e))?;
+
+        self.files = data
+            .lines()
+            .filter(|line| !line.is_empty() && *line != "/.")
+            .map(|line| line.to_owned())
+            .collect();
+
+        if let Ok(modified) = fs::metadata(&list).and_then(|meta| meta.modified()) {
+            self.modified = DateTime::from(modified);
+        }
+
+        Ok(())
+    }
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        Kind::Application
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            self.distro.apply(
+                Purl::new("deb", &self.name)
+                    .with_namespace("debian")
+                    .with_version(&self.version)
+                    .with_qualifier("arch", &self.architecture),
+            ),
+        )
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect()
+    }
+}
+
+impl Dependent for Package {
+    fn provides(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.provides.iter().map(|name| name.as_str()));
+        names
+    }
+
+    fn requires(&self) -> &[Vec<String>] {
+        &self.requires
+    }
+
+    fn set_dependencies(&mut self, dependencies: Vec<Purl>) {
+        self.dependencies = dependencies;
+    }
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    status_path: PathBuf,
+    distro: Distro,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context, distro: Distro) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            status_path: ctx.path(DPKG_STATUS_PATH),
+            distro,
+        }
+    }
+
+    // malformed lines are skipped and reported
+    fn parse_status(&self, status: &str, collected: &mut Collected) -> Vec<Package> {
+        let mut packages = vec![];
+        let mut package = Package::default();
+        let mut field: Option<String> = None;
+
+        for (line_num, line) in status.lines().enumerate() {
+            if line.trim().is_empty() {
+                // end of paragraph
+                if !package.name.is_empty() {
+                    packages.push(package);
+                }
+                package = Package::default();
+                field = None;
+            } else if line.starts_with(' ') || line.starts_with('\t') {
+                // continuation of a multiline field, only used by fields we don't care about
+                if field.is_none() {
+                    collected.warn(Error::parse_line(
+                        self.status_path.display(),
+                        line_num + 1,
+                        "unexpected continuation line",
+                    ));
+                }
+            } else if let Some((name, value)) = line.split_once(':') {
+                package.set_field(name, value.trim());
+                field = Some(name.to_owned());
+            } else {
+                collected.warn(Error::parse_line(
+                    self.status_path.display(),
+                    line_num + 1,
+                    format!("unexpected line {:?}", line),
+                ));
+            }
+        }
+
+        if !package.name.is_empty() {
+            packages.push(package);
+        }
+
+        packages
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, status: &str) -> Result<Collected, Error> {
+        let mut collected = Collected::new("dpkg");
+        let mut packages = vec![];
+
+        for mut package in self.parse_status(status, &mut collected) {
+            if !package.is_installed() {
+                log::debug!("skipping package {} ({})", &package.name, &package.status);
+                continue;
+            }
+
+            // the package is still reported, only its path is unknown
+            if let Err(e) = package.load_files(&self.ctx) {
+                collected.warn(e);
+            }
+            package.path = linux::install_path(&package.files);
+            package.distro = self.distro.clone();
+            packages.push(package);
+        }
+
+        linux::resolve_dependencies(&mut packages);
+        for package in packages {
+            collected.own(&package.files, package.purl());
+            collected.push(package);
+        }
+
+        Ok(collected)
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let status =
+            fs::read_to_string(&self.status_path).map_err(|e| Error::io(&self.status_path, e))?;
+
+        self.collect_from_json(&status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::Collector as _;
+
+    const STATUS: &str = "\
+Package: libc6
+Status: install ok installed
+Architecture: amd64
+Multi-Arch: same
+Version: 2.36-9+deb12u4
+Maintainer: GNU Libc Maintainers <debian-glibc@lists.debian.org>
+Provides: libc6-amd64
+Description: GNU C Library: Shared libraries
+ Contains the standard libraries that are used by nearly all programs on
+ the system.
+
+Package: bash
+Status: install ok installed
+Architecture: amd64
+Version: 5.2.15-2+b2
+Pre-Depends: libc6 (>= 2.36), libtinfo6 (>= 6)
+Depends: base-files (>= 2.1.12), debianutils (>= 5.6-0.1)
+this line has no field
+
+Package: removed
+Status: deinstall ok config-files
+Architecture: amd64
+Version: 1.0-1
+
+ orphan continuation line
+";
+
+    fn collect(root: &Path) -> Collected {
+        let info = root.join("var/lib/dpkg/info");
+        fs::create_dir_all(&info).unwrap();
+        fs::write(
+            info.join("libc6:amd64.list"),
+            "/.\n/lib\n/lib/x86_64-linux-gnu\n/lib/x86_64-linux-gnu/libc.so.6\n",
+        )
+        .unwrap();
+        fs::write(info.join("bash.list"), "/.\n/bin\n/bin/bash\n").unwrap();
+        fs::write(info.join("removed.list"), "/usr/bin/removed\n").unwrap();
+
+        let ctx = collector::Context::new(Some(root.to_path_buf())).unwrap();
+        Collector::new(&ctx, Distro::default())
+            .collect_from_json(STATUS)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_status_file() {
+        let root = tempfile::tempdir().unwrap();
+        let collected = collect(root.path());
+
+        let components: Vec<_> = collected
+            .components
+            .iter()
+            .map(|comp| (comp.name(), comp.version(), comp.path()))
+            .collect();
+        assert_eq!(
+            components,
+            vec![
+                ("libc6", "2.36-9+deb12u4", "/lib/x86_64-linux-gnu"),
+                ("bash", "5.2.15-2+b2", "/bin"),
+            ]
+        );
+
+        let libc = &collected.components[0];
+        assert_eq!(
+            libc.publishers(),
+            &vec!["GNU Libc Maintainers <debian-glibc@lists.debian.org>".to_owned()]
+        );
+        assert_eq!(
+            libc.purl().unwrap().to_string(),
+            "pkg:deb/debian/libc6@2.36-9%2Bdeb12u4?arch=amd64"
+        );
+
+        // the other dependencies are not installed
+        let bash = &collected.components[1];
+        let dependencies: Vec<String> = bash
+            .relationships()
+            .iter()
+            .map(|rel| rel.purl.to_string())
+            .collect();
+        assert_eq!(
+            dependencies,
+            vec!["pkg:deb/debian/libc6@2.36-9%2Bdeb12u4?arch=amd64"]
+        );
+
+        assert_eq!(
+            collected.owned.get("/bin/bash"),
+            Some(&bash.purl()),
+            "files are owned by their package"
+        );
+        assert!(!collected.owned.contains_key("/usr/bin/removed"));
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let root = tempfile::tempdir().unwrap();
+        let collected = collect(root.path());
+
+        let messages: Vec<&str> = collected
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(
+            messages[0].ends_with(":18: unexpected line \"this line has no field\""),
+            "{}",
+            messages[0]
+        );
+        assert!(messages[1].contains(":25: "), "{}", messages[1]);
+        assert!(messages[1].contains("unexpected continuation line"));
+        assert!(collected
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.collector == "dpkg"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinked_lists_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("list"), "/hostsecret\n").unwrap();
+
+        let info = root.path().join("var/lib/dpkg/info");
+        fs::create_dir_all(&info).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("list"), info.join("bash.list")).unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let collected = Collector::new(&ctx, Distro::default())
+            .collect_from_json(STATUS)
+            .unwrap();
+
+        let bash = collected
+            .components
+            .iter()
+            .find(|comp| comp.name() == "bash")
+            .unwrap();
+        assert_eq!(bash.path(), "/");
+        assert!(!collected.owned.contains_key("/hostsecret"));
+    }
+}
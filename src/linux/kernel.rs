@@ -0,0 +1,814 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind};
+use crate::elf;
+use crate::purl::Purl;
+use crate::Error;
+
+const PROC_VERSION_PATH: &str = "/proc/version";
+const PROC_OSRELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+const PROC_MODULES_PATH: &str = "/proc/modules";
+const SYS_MODULE_PATH: &str = "/sys/module";
+const MODULES_PATH: &str = "/lib/modules";
+const BOOT_PATH: &str = "/boot";
+const MODULE_EXTENSIONS: &[&str] = &[".ko", ".ko.xz", ".ko.zst", ".ko.gz"];
+// https://github.com/torvalds/linux/blob/master/include/linux/module_signature.h
+const SIGNATURE_MAGIC: &[u8] = b"~Module signature appended~\n";
+const SIGNATURE_INFO_SIZE: usize = 12;
+const PKEY_ID_PKCS7: u8 = 2;
+// protects against compression bombs, well above the biggest modules such as amdgpu or nvidia
+const MAX_MODULE_SIZE: usize = 256 * 1024 * 1024;
+// commonName and organizationName
+const OID_CN: &[u8] = &[0x55, 0x04, 0x03];
+const OID_O: &[u8] = &[0x55, 0x04, 0x0a];
+
+// the running kernel, or every kernel installed in an alternate root
+fn releases(ctx: &collector::Context) -> Vec<String> {
+    if ctx.is_live() {
+        return fs::read_to_string(PROC_OSRELEASE_PATH)
+            .map(|release| vec![release.trim().to_owned()])
+            .unwrap_or_default();
+    }
+
+    let mut releases = BTreeSet::new();
+    if let Ok(entries) = fs::read_dir(ctx.path(BOOT_PATH)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(release) = name.strip_prefix("config-") {
+                releases.insert(release.to_owned());
+            }
+        }
+    }
+    if let Ok(entries) = fs::read_dir(ctx.path(MODULES_PATH)) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                releases.insert(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    releases.into_iter().collect()
+}
+
+pub(crate) fn is_available(ctx: &collector::Context) -> bool {
+    !releases(ctx).is_empty()
+}
+
+fn modified(path: &Path) -> DateTime<Utc> {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(DateTime::from)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+struct Kernel {
+    release: String,
+    path: String,
+    modified: DateTime<Utc>,
+    publishers: Vec<String>,
+    properties: BTreeMap<String, String>,
+}
+
+impl Kernel {
+    // "Linux version 6.1.0-13-amd64 (debian-kernel@lists.debian.org) (gcc-12 (Debian 12.2.0-14) 12.2.0,
+    // GNU ld (GNU Binutils for Debian) 2.40) #1 SMP PREEMPT_DYNAMIC Debian 6.1.55-1 (2023-09-29)"
+    fn set_proc_version(&mut self, version: &str) {
+        let mut groups = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        let mut end = 0;
+        for (idx, c) in version.char_indices() {
+            match c {
+                '(' => {
+                    if depth == 0 {
+                        start = idx + 1;
+                    }
+                    depth += 1;
+                }
+                ')' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        groups.push(&version[start..idx]);
+                        end = idx + 1;
+                    }
+                }
+                // the build date has parentheses too
+                '#' if depth == 0 && groups.len() >= 2 => break,
+                _ => {}
+            }
+        }
+
+        if let Some(builder) = groups.first() {
+            self.publishers = vec![builder.to_string()];
+        }
+        if let Some(compiler) = groups.get(1) {
+            self.properties
+                .insert("compiler".to_owned(), compiler.to_string());
+        }
+        let build = version[end..].trim();
+        if !build.is_empty() {
+            self.properties.insert("build".to_owned(), build.to_owned());
+        }
+    }
+
+    fn set_config(&mut self, config: &str) {
+        for line in config.lines() {
+            if let Some(compiler) = line.strip_prefix("CONFIG_CC_VERSION_TEXT=") {
+                self.properties
+                    .entry("compiler".to_owned())
+                    .or_insert_with(|| compiler.trim_matches('"').to_owned());
+            }
+        }
+    }
+}
+
+impl ComponentTrait for Kernel {
+    fn kind(&self) -> Kind {
+        Kind::OS
+    }
+
+    fn name(&self) -> &str {
+        "Linux"
+    }
+
+    fn id(&self) -> &str {
+        "linux"
+    }
+
+    fn version(&self) -> &str {
+        &self.release
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(Purl::new("generic", "linux").with_version(&self.release))
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Module {
+    name: String,
+    version: String,
+    path: String,
+    modified: DateTime<Utc>,
+    // authors followed by the signer
+    publishers: Vec<String>,
+    license: Option<String>,
+    properties: BTreeMap<String, String>,
+}
+
+impl Module {
+    fn set_modinfo(&mut self, modinfo: &[u8]) {
+        for entry in modinfo.split(|b| *b == 0) {
+            let entry = String::from_utf8_lossy(entry);
+            let (key, value) = match entry.split_once('=') {
+                Some((key, value)) if !value.is_empty() => (key, value),
+                _ => continue,
+            };
+
+            match key {
+                "name" => self.name = value.to_owned(),
+                "version" => self.version = value.to_owned(),
+                "license" => self.license = Some(value.to_owned()),
+                "author" => self.publishers.push(value.to_owned()),
+                "srcversion" | "vermagic" => {
+                    self.properties.insert(key.to_owned(), value.to_owned());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_signer(&mut self, signer: String) {
+        self.properties.insert("signer".to_owned(), signer.clone());
+        self.publishers.push(signer);
+    }
+}
+
+impl ComponentTrait for Module {
+    fn kind(&self) -> Kind {
+        Kind::Driver
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            Purl::new("generic", &self.name)
+                .with_namespace("linux")
+                .with_version(&self.version),
+        )
+    }
+
+    fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        self.properties.clone()
+    }
+}
+
+// (tag, content, rest) of a DER encoded value
+fn der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first = *data.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let size = first & 0x7f;
+        if size == 0 || size > 4 {
+            return None;
+        }
+        let len = data
+            .get(2..2 + size)?
+            .iter()
+            .fold(0, |len, b| (len << 8) | *b as usize);
+        (len, 2 + size)
+    };
+
+    let content = data.get(header..header.checked_add(len)?)?;
+    Some((tag, content, &data[header + len..]))
+}
+
+fn name_attribute(name: &[u8], oid: &[u8]) -> Option<String> {
+    let mut rdns = name;
+    while let Some((_, rdn, rest)) = der(rdns) {
+        let (_, attribute, _) = der(rdn)?;
+        let (_, attribute_oid, value) = der(attribute)?;
+        if attribute_oid == oid {
+            let (_, value, _) = der(value)?;
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        rdns = rest;
+    }
+    None
+}
+
+// same as modinfo, the signer is the issuer of the certificate used by the first signer
+// https://datatracker.ietf.org/doc/html/rfc2315#section-9.1
+fn pkcs7_signer(data: &[u8]) -> Option<String> {
+    let (_, content_info, _) = der(data)?;
+    let (_, _, explicit) = der(content_info)?;
+    let (_, explicit, _) = der(explicit)?;
+    let (_, signed_data, _) = der(explicit)?;
+
+    // the digest algorithms are a set too, the signer infos are the last field
+    let mut fields = signed_data;
+    let mut signer_infos = None;
+    while let Some((tag, content, rest)) = der(fields) {
+        if tag == 0x31 {
+            signer_infos = Some(content);
+        }
+        fields = rest;
+    }
+
+    let (_, signer_info, _) = der(signer_infos?)?;
+    let (_, _, rest) = der(signer_info)?;
+    let (tag, issuer_and_serial, _) = der(rest)?;
+    // signers identified by subject key identifier have no issuer name
+    if tag != 0x30 {
+        return None;
+    }
+    let (_, issuer, _) = der(issuer_and_serial)?;
+
+    name_attribute(issuer, OID_CN).or_else(|| name_attribute(issuer, OID_O))
+}
+
+fn module_signer(data: &[u8]) -> Option<String> {
+    let data = data.strip_suffix(SIGNATURE_MAGIC)?;
+    let info = data.get(data.len().checked_sub(SIGNATURE_INFO_SIZE)?..)?;
+    let id_type = info[2];
+    let signer_len = info[3] as usize;
+    let key_id_len = info[4] as usize;
+    let sig_len = u32::from_be_bytes([info[8], info[9], info[10], info[11]]) as usize;
+
+    let sig_end = data.len() - SIGNATURE_INFO_SIZE;
+    let sig_start = sig_end.checked_sub(sig_len)?;
+    if id_type == PKEY_ID_PKCS7 {
+        return pkcs7_signer(&data[sig_start..sig_end]);
+    }
+
+    // legacy format: signer name, key id and signature
+    let signer_start = sig_start.checked_sub(key_id_len + signer_len)?;
+    let signer = &data[signer_start..signer_start + signer_len];
+    Some(String::from_utf8_lossy(signer).into_owned()).filter(|s| !s.is_empty())
+}
+
+// the decompressed data of a module, fails once it's bigger than the limit
+struct Decompressed {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for Decompressed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(io::Error::other(format!(
+                "module is bigger than {} bytes",
+                self.limit
+            )));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// compressed and plain modules have the same size limit
+fn read_module(path: &Path, limit: usize) -> Result<Vec<u8>, Error> {
+    let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+    let mut reader = BufReader::new(file);
+    let mut data = Decompressed {
+        data: vec![],
+        limit,
+    };
+    let name = path.to_string_lossy();
+
+    if name.ends_with(".xz") {
+        lzma_rs::xz_decompress(&mut reader, &mut data)
+            .map_err(|e| Error::parse(path.display(), e))?;
+    } else if name.ends_with(".zst") {
+        let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut reader)
+            .map_err(|e| Error::parse(path.display(), e))?;
+        io::copy(&mut decoder, &mut data).map_err(|e| Error::io(path, e))?;
+    } else if name.ends_with(".gz") {
+        io::copy(&mut flate2::read::GzDecoder::new(reader), &mut data)
+            .map_err(|e| Error::io(path, e))?;
+    } else {
+        io::copy(&mut reader, &mut data).map_err(|e| Error::io(path, e))?;
+    }
+
+    Ok(data.data)
+}
+
+// module names use underscores, file names can use dashes
+fn module_name(file_name: &str) -> String {
+    let stem = MODULE_EXTENSIONS
+        .iter()
+        .rev()
+        .find_map(|ext| file_name.strip_suffix(ext))
+        .unwrap_or(file_name);
+    stem.replace('-', "_")
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    fn collect_kernel(&self, release: &str) -> Kernel {
+        let mut kernel = Kernel {
+            release: release.to_owned(),
+            path: format!("{}/{}", MODULES_PATH, release),
+            ..Default::default()
+        };
+
+        let config_path = format!("{}/config-{}", BOOT_PATH, release);
+        if let Ok(config) = fs::read_to_string(self.ctx.path(&config_path)) {
+            kernel.path = config_path;
+            kernel.set_config(&config);
+        }
+
+        let image_path = format!("{}/vmlinuz-{}", BOOT_PATH, release);
+        if self.ctx.path(&image_path).exists() {
+            kernel.path = image_path;
+        }
+        kernel.modified = modified(&self.ctx.path(&kernel.path));
+
+        if self.ctx.is_live() {
+            if let Ok(version) = fs::read_to_string(PROC_VERSION_PATH) {
+                kernel.set_proc_version(version.trim());
+            }
+        }
+
+        kernel
+    }
+
+    fn collect_module(&self, release: &str, path: &Path) -> Result<Module, Error> {
+        let data = read_module(path, MAX_MODULE_SIZE)?;
+        let modinfo = elf::read_section(&data, ".modinfo")
+            .map_err(|e| Error::parse(path.display(), e))?
+            .ok_or_else(|| Error::parse(path.display(), "no .modinfo section"))?;
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut module = Module {
+            name: module_name(&file_name),
+            modified: modified(path),
+            ..Default::default()
+        };
+        module.set_modinfo(modinfo);
+        if let Some(signer) = module_signer(&data) {
+            module.set_signer(signer);
+        }
+        // in-tree modules are versioned with the kernel
+        if module.version.is_empty() {
+            module.version = release.to_owned();
+        }
+
+        Ok(module)
+    }
+
+    fn collect_modules(&self, release: &str, collected: &mut Collected) -> Vec<Module> {
+        let mut modules = vec![];
+        let modules_path = format!("{}/{}", MODULES_PATH, release);
+        let on_host = self.ctx.path(&modules_path);
+
+        for entry in walkdir::WalkDir::new(&on_host) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    collected.warn(Error::parse(on_host.display(), e));
+                    continue;
+                }
+            };
+            let file_name = entry.file_name().to_string_lossy();
+            if !entry.file_type().is_file()
+                || !MODULE_EXTENSIONS.iter().any(|ext| file_name.ends_with(ext))
+            {
+                continue;
+            }
+
+            match self.collect_module(release, entry.path()) {
+                Ok(mut module) => {
+                    let relative = entry.path().strip_prefix(&on_host).unwrap_or(entry.path());
+                    module.path = Path::new(&modules_path)
+                        .join(relative)
+                        .to_string_lossy()
+                        .into_owned();
+                    modules.push(module);
+                }
+                Err(e) => collected.warn(e),
+            }
+        }
+
+        modules
+    }
+
+    // "name size refcount dependencies state offset"
+    fn mark_loaded(&self, release: &str, modules: &mut Vec<Module>) -> Result<(), Error> {
+        let data =
+            fs::read_to_string(PROC_MODULES_PATH).map_err(|e| Error::io(PROC_MODULES_PATH, e))?;
+
+        let by_name: HashMap<String, usize> = modules
+            .iter()
+            .enumerate()
+            .map(|(idx, module)| (module.name.clone(), idx))
+            .collect();
+
+        for name in data
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+        {
+            let module = match by_name.get(name) {
+                Some(idx) => &mut modules[*idx],
+                None => {
+                    modules.push(self.sysfs_module(release, name));
+                    modules.last_mut().unwrap()
+                }
+            };
+            module
+                .properties
+                .insert("loaded".to_owned(), "true".to_owned());
+        }
+
+        Ok(())
+    }
+
+    // loaded modules that are not on disk anymore, for instance after an upgrade
+    fn sysfs_module(&self, release: &str, name: &str) -> Module {
+        let path = format!("{}/{}", SYS_MODULE_PATH, name);
+        let attribute = |attribute: &str| {
+            fs::read_to_string(Path::new(&path).join(attribute))
+                .ok()
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        let mut module = Module {
+            name: name.to_owned(),
+            version: attribute("version").unwrap_or_else(|| release.to_owned()),
+            modified: modified(Path::new(&path)),
+            ..Default::default()
+        };
+        if let Some(srcversion) = attribute("srcversion") {
+            module
+                .properties
+                .insert("srcversion".to_owned(), srcversion);
+        }
+        module.path = path;
+        module
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let mut collected = Collected::new("kernel");
+
+        for release in releases(&self.ctx) {
+            collected.push(self.collect_kernel(&release));
+
+            let mut modules = self.collect_modules(&release, &mut collected);
+
+            if self.ctx.is_live() {
+                if let Err(e) = self.mark_loaded(&release, &mut modules) {
+                    collected.warn(e);
+                }
+            }
+
+            for module in modules {
+                collected.push(module);
+            }
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a DER value, with the long form of the length when needed
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = vec![tag];
+        if content.len() < 0x80 {
+            data.push(content.len() as u8);
+        } else {
+            data.extend_from_slice(&[0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        data.extend_from_slice(content);
+        data
+    }
+
+    fn pkcs7(organization: &str, common_name: Option<&str>) -> Vec<u8> {
+        let attribute = |oid: &[u8], value: &str| {
+            let attribute = [tlv(0x06, oid), tlv(0x0c, value.as_bytes())].concat();
+            tlv(0x31, &tlv(0x30, &attribute))
+        };
+        let mut issuer = attribute(OID_O, organization);
+        if let Some(common_name) = common_name {
+            issuer.extend(attribute(OID_CN, common_name));
+        }
+        let issuer = tlv(0x30, &issuer);
+        let issuer_and_serial = tlv(0x30, &[issuer, tlv(0x02, &[1])].concat());
+        let digest = tlv(
+            0x30,
+            &tlv(
+                0x06,
+                &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+            ),
+        );
+        let signer_info = tlv(
+            0x30,
+            &[
+                tlv(0x02, &[1]),
+                issuer_and_serial,
+                digest.clone(),
+                tlv(0x04, &[0xaa; 256]),
+            ]
+            .concat(),
+        );
+        let signed_data = tlv(
+            0x30,
+            &[
+                tlv(0x02, &[1]),
+                tlv(0x31, &digest),
+                tlv(
+                    0x30,
+                    &tlv(
+                        0x06,
+                        &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01],
+                    ),
+                ),
+                tlv(0x31, &signer_info),
+            ]
+            .concat(),
+        );
+        let oid = tlv(
+            0x06,
+            &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02],
+        );
+        tlv(0x30, &[oid, tlv(0xa0, &signed_data)].concat())
+    }
+
+    // the module, the signature, struct module_signature and the magic string
+    fn signed(signature: &[u8], id_type: u8, signer_len: u8, key_id_len: u8) -> Vec<u8> {
+        let mut data = b"\x7fELF module".to_vec();
+        data.extend_from_slice(signature);
+        let sig_len = signature.len() - signer_len as usize - key_id_len as usize;
+        data.extend_from_slice(&[0, 0, id_type, signer_len, key_id_len, 0, 0, 0]);
+        data.extend_from_slice(&(sig_len as u32).to_be_bytes());
+        data.extend_from_slice(SIGNATURE_MAGIC);
+        data
+    }
+
+    #[test]
+    fn parses_proc_version() {
+        let mut kernel = Kernel::default();
+        kernel.set_proc_version(
+            "Linux version 6.1.0-13-amd64 (debian-kernel@lists.debian.org) (gcc-12 (Debian \
+             12.2.0-14) 12.2.0, GNU ld (GNU Binutils for Debian) 2.40) #1 SMP PREEMPT_DYNAMIC \
+             Debian 6.1.55-1 (2023-09-29)",
+        );
+        assert_eq!(kernel.publishers, vec!["debian-kernel@lists.debian.org"]);
+        assert_eq!(
+            kernel.properties.get("compiler").unwrap(),
+            "gcc-12 (Debian 12.2.0-14) 12.2.0, GNU ld (GNU Binutils for Debian) 2.40"
+        );
+        assert_eq!(
+            kernel.properties.get("build").unwrap(),
+            "#1 SMP PREEMPT_DYNAMIC Debian 6.1.55-1 (2023-09-29)"
+        );
+
+        // the compiler of the running kernel is preferred to the configured one
+        kernel.set_config("CONFIG_CC_VERSION_TEXT=\"gcc-12 (Debian 12.2.0-14) 12.2.0\"\n");
+        assert!(kernel.properties["compiler"].ends_with("2.40"));
+        let mut kernel = Kernel::default();
+        kernel.set_config("CONFIG_CC_VERSION_TEXT=\"gcc-12 (Debian 12.2.0-14) 12.2.0\"\n");
+        assert_eq!(
+            kernel.properties["compiler"],
+            "gcc-12 (Debian 12.2.0-14) 12.2.0"
+        );
+    }
+
+    #[test]
+    fn parses_modinfo() {
+        let mut module = Module::default();
+        module.set_modinfo(
+            b"license=GPL\0author=Jane Doe\0author=John Doe\0description=A driver\0name=foo\0\
+              version=1.2\0srcversion=0C4A2B\0depends=\0vermagic=6.1.0-13-amd64 SMP mod_unload\0\0",
+        );
+        assert_eq!(module.name, "foo");
+        assert_eq!(module.version, "1.2");
+        assert_eq!(module.license.as_deref(), Some("GPL"));
+        assert_eq!(module.publishers, vec!["Jane Doe", "John Doe"]);
+        let properties: Vec<(&str, &str)> = module
+            .properties
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            properties,
+            vec![
+                ("srcversion", "0C4A2B"),
+                ("vermagic", "6.1.0-13-amd64 SMP mod_unload")
+            ]
+        );
+        assert_eq!(module_name("snd-hda-intel.ko.zst"), "snd_hda_intel");
+    }
+
+    #[test]
+    fn parses_der() {
+        assert_eq!(
+            der(&[0x04, 0x02, 0xaa, 0xbb, 0xcc]),
+            Some((0x04, &[0xaa, 0xbb][..], &[0xcc][..]))
+        );
+        let long = tlv(0x04, &[0xaa; 300]);
+        let (tag, content, rest) = der(&long).unwrap();
+        assert_eq!((tag, content.len(), rest.len()), (0x04, 300, 0));
+
+        assert_eq!(der(&[]), None);
+        assert_eq!(der(&[0x04]), None);
+        assert_eq!(der(&[0x04, 0x03, 0xaa]), None);
+        // indefinite and oversized lengths
+        assert_eq!(der(&[0x04, 0x80, 0xaa]), None);
+        assert_eq!(der(&[0x04, 0x85, 0xff, 0xff, 0xff, 0xff, 0xff]), None);
+        assert_eq!(der(&[0x04, 0x84, 0xff, 0xff, 0xff, 0xff]), None);
+    }
+
+    #[test]
+    fn reads_module_signers() {
+        let signature = pkcs7("Debian", Some("Debian Secure Boot CA"));
+        let data = signed(&signature, PKEY_ID_PKCS7, 0, 0);
+        assert_eq!(
+            module_signer(&data).as_deref(),
+            Some("Debian Secure Boot CA")
+        );
+
+        // the organization when there is no common name
+        let signature = pkcs7("Debian", None);
+        let data = signed(&signature, PKEY_ID_PKCS7, 0, 0);
+        assert_eq!(module_signer(&data).as_deref(), Some("Debian"));
+
+        // legacy x509 signatures have the signer and the key id before the signature
+        let signature = [
+            &b"Build time autogenerated kernel key"[..],
+            &[0x11; 20],
+            &[0xaa; 64],
+        ]
+        .concat();
+        let data = signed(&signature, 1, 35, 20);
+        assert_eq!(
+            module_signer(&data).as_deref(),
+            Some("Build time autogenerated kernel key")
+        );
+
+        assert_eq!(module_signer(b"\x7fELF module"), None);
+        let data = signed(&[0xaa; 8], PKEY_ID_PKCS7, 0, 0);
+        assert_eq!(module_signer(&data[data.len() - 20..]), None);
+    }
+
+    #[test]
+    fn limits_module_sizes() {
+        const LIMIT: usize = 64 * 1024;
+
+        let dir = tempfile::tempdir().unwrap();
+        let compress = |name: &str, size: usize| {
+            let path = dir.path().join(name);
+            let file = fs::File::create(&path).unwrap();
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            encoder.write_all(&vec![0; size]).unwrap();
+            encoder.finish().unwrap();
+            path
+        };
+
+        let path = compress("small.ko.gz", LIMIT);
+        assert_eq!(read_module(&path, LIMIT).unwrap().len(), LIMIT);
+        let path = compress("bomb.ko.gz", LIMIT + 1);
+        let error = read_module(&path, LIMIT).unwrap_err();
+        assert!(error.to_string().contains("bigger than"), "{}", error);
+
+        let path = dir.path().join("bomb.ko.xz");
+        let mut xz = vec![];
+        lzma_rs::xz_compress(&mut &vec![0; LIMIT + 1][..], &mut xz).unwrap();
+        fs::write(&path, xz).unwrap();
+        let error = read_module(&path, LIMIT).unwrap_err();
+        assert!(error.to_string().contains("bigger than"), "{}", error);
+
+        // plain modules have the same limit
+        let path = dir.path().join("small.ko");
+        fs::write(&path, vec![0; LIMIT]).unwrap();
+        assert_eq!(read_module(&path, LIMIT).unwrap().len(), LIMIT);
+        let path = dir.path().join("big.ko");
+        fs::write(&path, vec![0; LIMIT + 1]).unwrap();
+        let error = read_module(&path, LIMIT).unwrap_err();
+        assert!(error.to_string().contains("bigger than"), "{}", error);
+
+        // real modules are far below the default limit
+        let path = compress("amdgpu.ko.gz", 40 * 1024 * 1024);
+        assert!(read_module(&path, MAX_MODULE_SIZE).is_ok());
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::collector::{self, Collected, CollectorKind};
+use crate::component::ComponentTrait;
+use crate::purl::Purl;
+use crate::Error;
+
+mod apk;
+mod dpkg;
+mod kernel;
+pub(crate) mod os;
+mod pacman;
+mod rpm;
+
+// The install path of a package is the deepest directory shared by all the files it owns,
+// directories are skipped or every package would share the root.
+pub(crate) fn install_path(files: &[String]) -> String {
+    let mut sorted: Vec<&String> = files.iter().collect();
+    sorted.sort();
+
+    let mut prefix: Option<PathBuf> = None;
+    for (idx, file) in sorted.iter().enumerate() {
+        if let Some(next) = sorted.get(idx + 1) {
+            if next.starts_with(&format!("{}/", file)) {
+                continue;
+            }
+        }
+
+        let file = Path::new(file);
+        prefix = Some(match prefix {
+            None => file.parent().unwrap_or(file).to_path_buf(),
+            Some(prefix) => prefix
+                .components()
+                .zip(file.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+
+    match prefix {
+        Some(prefix) if !prefix.as_os_str().is_empty() => prefix.to_string_lossy().into_owned(),
+        _ => "/".to_owned(),
+    }
+}
+
+/// A package of a database that depends on other packages.
+pub(crate) trait Dependent: ComponentTrait {
+    /// Names the package can be required with: its own, and the virtual packages, capabilities
+    /// or files it provides.
+    fn provides(&self) -> Vec<&str>;
+
+    /// Names of the required packages, each dependency can be satisfied by any of its alternatives.
+    fn requires(&self) -> &[Vec<String>];
+
+    fn set_dependencies(&mut self, dependencies: Vec<Purl>);
+}
+
+// The first alternative provided by an installed package satisfies a dependency, packages of the
+// same architecture are preferred on multiarch systems. Dependencies that are not installed are skipped.
+pub(crate) fn resolve_dependencies<P: Dependent>(packages: &mut [P]) {
+    let purls: Vec<Option<Purl>> = packages.iter().map(|p| p.purl()).collect();
+    let arch = |idx: usize| purls[idx].as_ref().and_then(|p| p.qualifiers.get("arch"));
+
+    let mut providers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, package) in packages.iter().enumerate() {
+        for name in package.provides() {
+            providers.entry(name).or_default().push(idx);
+        }
+    }
+
+    let mut resolved = vec![];
+    for (idx, package) in packages.iter().enumerate() {
+        let mut dependencies: Vec<Purl> = vec![];
+        for alternatives in package.requires() {
+            // a package can provide what it depends on, for another architecture
+            let provider = alternatives
+                .iter()
+                .filter_map(|name| providers.get(name.as_str()))
+                .find_map(|candidates| {
+                    let mut others = candidates.iter().filter(|c| **c != idx);
+                    others
+                        .clone()
+                        .find(|c| arch(**c) == arch(idx))
+                        .or_else(|| others.next())
+                });
+            let purl = provider.and_then(|provider| purls[*provider].as_ref());
+            if let Some(purl) = purl.filter(|purl| !dependencies.contains(purl)) {
+                dependencies.push(purl.clone());
+            }
+        }
+        resolved.push(dependencies);
+    }
+
+    for (package, dependencies) in packages.iter_mut().zip(resolved) {
+        package.set_dependencies(dependencies);
+    }
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    release: Option<os::Release>,
+    backends: Vec<Box<dyn collector::Collector>>,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            release: None,
+            backends: vec![],
+        }
+    }
+
+    // databases of other package managers, such as the rpm database that can be installed on
+    // debian, are only read if explicitly selected
+    fn uses(&self, kind: CollectorKind) -> bool {
+        if !self.ctx.is_enabled(kind) {
+            return false;
+        }
+        if self.ctx.is_selected(kind) {
+            return true;
+        }
+
+        match &self.release {
+            Some(release) => match release.package_managers() {
+                Some(managers) if !managers.contains(&kind) => {
+                    log::info!(
+                        "skipping the {} database, not used by {}",
+                        format!("{:?}", kind).to_lowercase(),
+                        release.name()
+                    );
+                    false
+                }
+                _ => true,
+            },
+            None => true,
+        }
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        self.release = os::detect(&self.ctx);
+        let distro = self
+            .release
+            .as_ref()
+            .map(|release| release.distro())
+            .unwrap_or_default();
+
+        if dpkg::is_available(&self.ctx) && self.uses(CollectorKind::Dpkg) {
+            log::debug!("found dpkg database");
+            self.backends
+                .push(Box::new(dpkg::Collector::new(&self.ctx, distro.clone())));
+        }
+
+        if apk::is_available(&self.ctx) && self.uses(CollectorKind::Apk) {
+            log::debug!("found apk database");
+            self.backends
+                .push(Box::new(apk::Collector::new(&self.ctx, distro.clone())));
+        }
+
+        if pacman::is_available(&self.ctx) && self.uses(CollectorKind::Pacman) {
+            log::debug!("found pacman database");
+            self.backends
+                .push(Box::new(pacman::Collector::new(&self.ctx, distro.clone())));
+        }
+
+        if let Some(database) = rpm::find_database(&self.ctx) {
+            if self.uses(CollectorKind::Rpm) {
+                log::debug!("found rpm database: {:?}", &database);
+                self.backends
+                    .push(Box::new(rpm::Collector::new(database, distro)));
+            }
+        }
+
+        if self.ctx.is_enabled(CollectorKind::Kernel) && kernel::is_available(&self.ctx) {
+            log::debug!("found linux kernel");
+            self.backends
+                .push(Box::new(kernel::Collector::new(&self.ctx)));
+        }
+
+        // a distroless image only has the os-release file
+        if self.backends.is_empty() && self.release.is_none() {
+            return Err(Error::unsupported(
+                "no supported package database or kernel found",
+            ));
+        }
+
+        for backend in &mut self.backends {
+            backend.setup()?;
+        }
+
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        log::info!("collecting packages, please wait ...");
+
+        let mut collected = Collected::new("linux");
+        if let Some(release) = &self.release {
+            collected.push(release.clone());
+        }
+
+        // a broken database is only fatal if there's nothing else to report
+        collector::collect_all(&mut collected, &self.backends)?;
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::Kind;
+    use chrono::{DateTime, Utc};
+
+    #[derive(Default)]
+    struct Package {
+        name: &'static str,
+        arch: &'static str,
+        provides: Vec<&'static str>,
+        requires: Vec<Vec<String>>,
+        publishers: Vec<String>,
+        dependencies: Vec<Purl>,
+    }
+
+    fn package(name: &'static str, arch: &'static str, requires: &[&[&str]]) -> Package {
+        Package {
+            name,
+            arch,
+            requires: requires
+                .iter()
+                .map(|alternatives| alternatives.iter().map(|n| n.to_string()).collect())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    impl ComponentTrait for Package {
+        fn kind(&self) -> Kind {
+            Kind::Application
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn id(&self) -> &str {
+            self.name
+        }
+
+        fn version(&self) -> &str {
+            "1"
+        }
+
+        fn path(&self) -> &str {
+            ""
+        }
+
+        fn modified(&self) -> DateTime<Utc> {
+            DateTime::default()
+        }
+
+        fn publishers(&self) -> &Vec<String> {
+            &self.publishers
+        }
+
+        fn purl(&self) -> Option<Purl> {
+            Some(Purl::new("deb", self.name).with_qualifier("arch", self.arch))
+        }
+    }
+
+    impl Dependent for Package {
+        fn provides(&self) -> Vec<&str> {
+            let mut names = vec![self.name];
+            names.extend(&self.provides);
+            names
+        }
+
+        fn requires(&self) -> &[Vec<String>] {
+            &self.requires
+        }
+
+        fn set_dependencies(&mut self, dependencies: Vec<Purl>) {
+            self.dependencies = dependencies;
+        }
+    }
+
+    fn dependencies(package: &Package) -> Vec<String> {
+        package
+            .dependencies
+            .iter()
+            .map(|purl| purl.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn resolves_dependencies() {
+        let mut packages = vec![
+            package("app", "amd64", &[&["missing", "mta"], &["lib"], &["lib"]]),
+            package("lib", "i386", &[]),
+            package("lib", "amd64", &[]),
+            Package {
+                provides: vec!["mta"],
+                ..package("postfix", "amd64", &[&["postfix"], &["mta", "lib"]])
+            },
+        ];
+        resolve_dependencies(&mut packages);
+
+        assert_eq!(
+            dependencies(&packages[0]),
+            vec!["pkg:deb/postfix?arch=amd64", "pkg:deb/lib?arch=amd64"]
+        );
+        // the package itself is skipped, the next alternative is used
+        assert_eq!(dependencies(&packages[3]), vec!["pkg:deb/lib?arch=amd64"]);
+    }
+
+    #[test]
+    fn resolves_provides_of_other_architectures() {
+        let mut packages = vec![
+            Package {
+                provides: vec!["libfoo-any"],
+                ..package("libfoo", "amd64", &[&["libfoo-any"]])
+            },
+            Package {
+                provides: vec!["libfoo-any"],
+                ..package("libfoo", "i386", &[&["libfoo-any"]])
+            },
+            package("solo", "amd64", &[&["solo"]]),
+        ];
+        resolve_dependencies(&mut packages);
+
+        assert_eq!(dependencies(&packages[0]), vec!["pkg:deb/libfoo?arch=i386"]);
+        assert_eq!(
+            dependencies(&packages[1]),
+            vec!["pkg:deb/libfoo?arch=amd64"]
+        );
+        assert!(dependencies(&packages[2]).is_empty());
+    }
+}
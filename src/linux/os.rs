@@ -0,0 +1,508 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use chrono::{DateTime, Utc};
+
+use crate::collector::{self, CollectorKind};
+use crate::component::{ComponentTrait, Kind};
+use crate::cpe;
+use crate::purl::Purl;
+
+// https://www.freedesktop.org/software/systemd/man/os-release.html
+const OS_RELEASE_PATHS: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+const LSB_RELEASE_PATH: &str = "/etc/lsb-release";
+const DEBIAN_VERSION_PATH: &str = "/etc/debian_version";
+const REDHAT_RELEASE_PATH: &str = "/etc/redhat-release";
+const ALPINE_RELEASE_PATH: &str = "/etc/alpine-release";
+
+// os-release ids that don't match the package URL namespace
+const NAMESPACES: &[(&str, &str)] = &[
+    ("rhel", "redhat"),
+    ("opensuse-leap", "opensuse"),
+    ("opensuse-tumbleweed", "opensuse"),
+    ("opensuse-microos", "opensuse"),
+    ("sles", "suse"),
+    ("sled", "suse"),
+    ("sle-micro", "suse"),
+    ("ol", "oracle"),
+    ("amzn", "amazon"),
+];
+
+// os-release ids (or ID_LIKE entries) and their package manager
+const PACKAGE_MANAGERS: &[(&str, CollectorKind)] = &[
+    ("debian", CollectorKind::Dpkg),
+    ("ubuntu", CollectorKind::Dpkg),
+    ("alpine", CollectorKind::Apk),
+    ("wolfi", CollectorKind::Apk),
+    ("chainguard", CollectorKind::Apk),
+    ("arch", CollectorKind::Pacman),
+    ("fedora", CollectorKind::Rpm),
+    ("rhel", CollectorKind::Rpm),
+    ("centos", CollectorKind::Rpm),
+    ("suse", CollectorKind::Rpm),
+    ("opensuse", CollectorKind::Rpm),
+    ("mageia", CollectorKind::Rpm),
+    ("amzn", CollectorKind::Rpm),
+];
+
+/// Namespace and distro qualifier of the package URLs of a distribution.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Distro {
+    namespace: Option<String>,
+    qualifier: Option<String>,
+}
+
+impl Distro {
+    pub fn apply(&self, mut purl: Purl) -> Purl {
+        if let Some(namespace) = &self.namespace {
+            purl = purl.with_namespace(namespace);
+        }
+        if let Some(qualifier) = &self.qualifier {
+            purl = purl.with_qualifier("distro", qualifier);
+        }
+        purl
+    }
+}
+
+/// The distribution, as described by os-release or by the older release files.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Release {
+    id: String,
+    id_like: Vec<String>,
+    name: String,
+    version_id: String,
+    codename: String,
+    build_id: String,
+    variant_id: String,
+    cpe_name: Option<String>,
+    publishers: Vec<String>,
+    path: String,
+    modified: DateTime<Utc>,
+}
+
+// backslash escapes of double quoted values, \" \\ \$ and \`
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('"' | '\\' | '$' | '`'))) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// KEY=value, values can be quoted and use shell escapes
+fn parse_variables(data: &str) -> HashMap<String, String> {
+    let mut variables = HashMap::new();
+
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = if let Some(quoted) = value.strip_prefix('"') {
+                unescape(quoted.strip_suffix('"').unwrap_or(quoted))
+            } else if let Some(quoted) = value.strip_prefix('\'') {
+                quoted.strip_suffix('\'').unwrap_or(quoted).to_owned()
+            } else {
+                value.to_owned()
+            };
+            variables.insert(key.trim().to_owned(), value);
+        }
+    }
+
+    variables
+}
+
+impl Release {
+    fn from_os_release(data: &str) -> Option<Self> {
+        let vars = parse_variables(data);
+        let var = |name: &str| vars.get(name).cloned().unwrap_or_default();
+
+        let id = var("ID").to_lowercase();
+        if id.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name: vars.get("NAME").cloned().unwrap_or_else(|| id.clone()),
+            id_like: var("ID_LIKE")
+                .split_whitespace()
+                .map(|id| id.to_lowercase())
+                .collect(),
+            id,
+            version_id: var("VERSION_ID"),
+            codename: var("VERSION_CODENAME"),
+            build_id: var("BUILD_ID"),
+            variant_id: var("VARIANT_ID"),
+            cpe_name: vars.get("CPE_NAME").cloned().filter(|cpe| !cpe.is_empty()),
+            publishers: vars.get("VENDOR_NAME").cloned().into_iter().collect(),
+            ..Default::default()
+        })
+    }
+
+    fn from_lsb_release(data: &str) -> Option<Self> {
+        let vars = parse_variables(data);
+        let name = vars.get("DISTRIB_ID")?.to_owned();
+
+        Some(Self {
+            id: name.to_lowercase(),
+            name,
+            version_id: vars.get("DISTRIB_RELEASE").cloned().unwrap_or_default(),
+            codename: vars.get("DISTRIB_CODENAME").cloned().unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
+    // "12.2" for stable releases, "bookworm/sid" for testing and unstable
+    fn from_debian_version(data: &str) -> Option<Self> {
+        let version = data.trim();
+        if version.is_empty() {
+            return None;
+        }
+
+        let mut release = Self {
+            id: "debian".to_owned(),
+            name: "Debian GNU/Linux".to_owned(),
+            ..Default::default()
+        };
+        if version.starts_with(|c: char| c.is_ascii_digit()) {
+            release.version_id = version.to_owned();
+        } else {
+            release.codename = version.split('/').next().unwrap_or_default().to_owned();
+        }
+        Some(release)
+    }
+
+    // "Red Hat Enterprise Linux release 8.6 (Ootpa)", "CentOS Linux release 7.9.2009 (Core)"
+    fn from_redhat_release(data: &str) -> Option<Self> {
+        let line = data.lines().next()?.trim();
+        let (name, rest) = line.split_once(" release ")?;
+        let id = match name {
+            name if name.starts_with("Red Hat") => "rhel".to_owned(),
+            name if name.starts_with("AlmaLinux") => "almalinux".to_owned(),
+            name => name
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_lowercase(),
+        };
+
+        Some(Self {
+            id,
+            id_like: vec!["rhel".to_owned()],
+            name: name.to_owned(),
+            version_id: rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+            codename: rest
+                .split_once('(')
+                .map(|(_, codename)| codename.trim_end_matches(')').to_owned())
+                .unwrap_or_default(),
+            ..Default::default()
+        })
+    }
+
+    fn from_alpine_release(data: &str) -> Option<Self> {
+        let version = data.trim();
+        if version.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            id: "alpine".to_owned(),
+            name: "Alpine Linux".to_owned(),
+            version_id: version.to_owned(),
+            ..Default::default()
+        })
+    }
+
+    /// Package URL namespace of the distribution's packages.
+    pub fn namespace(&self) -> String {
+        NAMESPACES
+            .iter()
+            .find(|(id, _)| *id == self.id)
+            .map(|(_, namespace)| namespace.to_string())
+            .unwrap_or_else(|| self.id.clone())
+    }
+
+    pub fn distro(&self) -> Distro {
+        Distro {
+            namespace: Some(self.namespace()),
+            // same as the purl examples and the OSV releases, "debian-12" or "alpine-3.18.4"
+            qualifier: Some(&self.version_id)
+                .filter(|version| !version.is_empty())
+                .map(|version| format!("{}-{}", self.namespace(), version)),
+        }
+    }
+
+    /// The package managers used by the distribution or the ones it derives from, None if unknown.
+    pub fn package_managers(&self) -> Option<Vec<CollectorKind>> {
+        let ids: Vec<&str> = std::iter::once(self.id.as_str())
+            .chain(self.id_like.iter().map(|id| id.as_str()))
+            .collect();
+
+        let mut kinds: Vec<CollectorKind> = PACKAGE_MANAGERS
+            .iter()
+            .filter(|(id, _)| ids.contains(id))
+            .map(|(_, kind)| *kind)
+            .collect();
+        // the ids of a package manager are next to each other
+        kinds.dedup();
+
+        if kinds.is_empty() {
+            None
+        } else {
+            Some(kinds)
+        }
+    }
+}
+
+impl ComponentTrait for Release {
+    fn kind(&self) -> Kind {
+        Kind::OS
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    // rolling releases only have a build id
+    fn version(&self) -> &str {
+        [&self.version_id, &self.build_id, &self.codename]
+            .into_iter()
+            .find(|version| !version.is_empty())
+            .map(|version| version.as_str())
+            .unwrap_or_default()
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            Purl::new("generic", &self.id)
+                .with_namespace(&self.namespace())
+                .with_version(self.version()),
+        )
+    }
+
+    fn cpe(&self) -> Option<String> {
+        self.cpe_name.as_deref().and_then(cpe::from_uri)
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        let mut properties = BTreeMap::new();
+        for (name, value) in [
+            ("codename", &self.codename),
+            ("build_id", &self.build_id),
+            ("variant_id", &self.variant_id),
+        ] {
+            if !value.is_empty() {
+                properties.insert(name.to_owned(), value.clone());
+            }
+        }
+        properties
+    }
+}
+
+/// Detects the distribution of the inspected system.
+pub(crate) fn detect(ctx: &collector::Context) -> Option<Release> {
+    type Parser = fn(&str) -> Option<Release>;
+    let sources: Vec<(&str, Parser)> = OS_RELEASE_PATHS
+        .iter()
+        .map(|path| (*path, Release::from_os_release as Parser))
+        .chain([
+            (LSB_RELEASE_PATH, Release::from_lsb_release as Parser),
+            (DEBIAN_VERSION_PATH, Release::from_debian_version as Parser),
+            (REDHAT_RELEASE_PATH, Release::from_redhat_release as Parser),
+            (ALPINE_RELEASE_PATH, Release::from_alpine_release as Parser),
+        ])
+        .collect();
+
+    for (path, parse) in sources {
+        let on_host = ctx.path(path);
+        let data = match fs::read_to_string(&on_host) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+
+        if let Some(mut release) = parse(&data) {
+            log::debug!("found {} {} in {}", &release.id, release.version(), path);
+            release.path = path.to_owned();
+            release.modified = fs::metadata(&on_host)
+                .and_then(|meta| meta.modified())
+                .map(DateTime::from)
+                .unwrap_or_default();
+            return Some(release);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_variables() {
+        let vars = parse_variables(
+            r#"# comment
+NAME="Debian GNU/Linux"
+ID=debian
+PRETTY_NAME='Debian "bookworm"'
+VERSION="12 (\"bookworm\")"
+HOME_URL="https://example.com/\$HOME\\path\`x\`"
+  ANSI_COLOR = "1;31"
+EMPTY=""
+UNTERMINATED="value
+NOT A VARIABLE
+"#,
+        );
+        let var = |name: &str| vars.get(name).map(|value| value.as_str());
+        assert_eq!(var("NAME"), Some("Debian GNU/Linux"));
+        assert_eq!(var("ID"), Some("debian"));
+        assert_eq!(var("PRETTY_NAME"), Some(r#"Debian "bookworm""#));
+        assert_eq!(var("VERSION"), Some(r#"12 ("bookworm")"#));
+        assert_eq!(var("HOME_URL"), Some(r"https://example.com/$HOME\path`x`"));
+        assert_eq!(var("ANSI_COLOR"), Some("1;31"));
+        assert_eq!(var("EMPTY"), Some(""));
+        assert_eq!(var("UNTERMINATED"), Some("value"));
+        assert_eq!(vars.len(), 8);
+    }
+
+    #[test]
+    fn parses_os_release() {
+        let release = Release::from_os_release(
+            "NAME=\"Red Hat Enterprise Linux\"\nID=\"rhel\"\nID_LIKE=\"fedora\"\n\
+             VERSION_ID=\"9.3\"\nCPE_NAME=\"cpe:/o:redhat:enterprise_linux:9::baseos\"\n",
+        )
+        .unwrap();
+        assert_eq!(release.name(), "Red Hat Enterprise Linux");
+        assert_eq!(release.id_like, vec!["fedora"]);
+        assert_eq!(release.version(), "9.3");
+        assert_eq!(
+            release.purl().unwrap().to_string(),
+            "pkg:generic/redhat/rhel@9.3"
+        );
+        assert_eq!(release.package_managers(), Some(vec![CollectorKind::Rpm]));
+        assert!(release.cpe().is_some());
+
+        // rolling releases
+        let release = Release::from_os_release("ID=arch\nBUILD_ID=rolling\n").unwrap();
+        assert_eq!(release.name(), "arch");
+        assert_eq!(release.version(), "rolling");
+        assert_eq!(
+            release.package_managers(),
+            Some(vec![CollectorKind::Pacman])
+        );
+
+        assert!(Release::from_os_release("NAME=Unknown\n").is_none());
+    }
+
+    #[test]
+    fn parses_older_release_files() {
+        let release = Release::from_lsb_release(
+            "DISTRIB_ID=Ubuntu\nDISTRIB_RELEASE=22.04\nDISTRIB_CODENAME=jammy\n\
+             DISTRIB_DESCRIPTION=\"Ubuntu 22.04.3 LTS\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                release.id(),
+                release.name(),
+                release.version(),
+                release.codename.as_str()
+            ),
+            ("ubuntu", "Ubuntu", "22.04", "jammy")
+        );
+        assert!(Release::from_lsb_release("DISTRIB_RELEASE=22.04\n").is_none());
+
+        let release = Release::from_debian_version("12.2\n").unwrap();
+        assert_eq!((release.id(), release.version()), ("debian", "12.2"));
+        let release = Release::from_debian_version("trixie/sid\n").unwrap();
+        assert_eq!(release.version_id, "");
+        assert_eq!(release.version(), "trixie");
+        assert!(Release::from_debian_version("\n").is_none());
+
+        let release =
+            Release::from_redhat_release("Red Hat Enterprise Linux release 8.6 (Ootpa)\n").unwrap();
+        assert_eq!(
+            (
+                release.id(),
+                release.name(),
+                release.version(),
+                release.codename.as_str()
+            ),
+            ("rhel", "Red Hat Enterprise Linux", "8.6", "Ootpa")
+        );
+        let release = Release::from_redhat_release("CentOS Linux release 7.9.2009 (Core)").unwrap();
+        assert_eq!((release.id(), release.version()), ("centos", "7.9.2009"));
+        let release =
+            Release::from_redhat_release("AlmaLinux release 9.3 (Shamrock Pampas Cat)").unwrap();
+        assert_eq!(release.id(), "almalinux");
+        assert_eq!(release.package_managers(), Some(vec![CollectorKind::Rpm]));
+        assert!(Release::from_redhat_release("Fedora 39\n").is_none());
+
+        let release = Release::from_alpine_release("3.18.4\n").unwrap();
+        assert_eq!((release.id(), release.version()), ("alpine", "3.18.4"));
+        assert!(Release::from_alpine_release("").is_none());
+    }
+
+    #[test]
+    fn applies_distro() {
+        let purl = || Purl::new("rpm", "openssl").with_version("3.0.7-25.el9");
+
+        let release = Release::from_os_release("ID=rhel\nVERSION_ID=9.3\n").unwrap();
+        assert_eq!(
+            release.distro().apply(purl()).to_string(),
+            "pkg:rpm/redhat/openssl@3.0.7-25.el9?distro=redhat-9.3"
+        );
+
+        // the qualifier is left out without a version
+        let release = Release::from_debian_version("trixie/sid").unwrap();
+        assert_eq!(
+            release.distro().apply(purl()).to_string(),
+            "pkg:rpm/debian/openssl@3.0.7-25.el9"
+        );
+
+        // the namespace of the purl is replaced and the other qualifiers are kept
+        let release = Release::from_alpine_release("3.18.4").unwrap();
+        let apk = Purl::new("apk", "musl")
+            .with_namespace("wolfi")
+            .with_version("1.2.4-r2")
+            .with_qualifier("arch", "x86_64");
+        assert_eq!(
+            release.distro().apply(apk).to_string(),
+            "pkg:apk/alpine/musl@1.2.4-r2?arch=x86_64&distro=alpine-3.18.4"
+        );
+
+        assert_eq!(
+            Distro::default().apply(purl()).to_string(),
+            "pkg:rpm/openssl@3.0.7-25.el9"
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::linux::{self, os::Distro, Dependent};
+use crate::purl::Purl;
+use crate::Error;
+
+const PACMAN_LOCAL_PATH: &str = "/var/lib/pacman/local";
+
+// %REASON% is only present for packages that were not explicitly installed
+const REASON_DEPENDENCY: &str = "1";
+
+pub(crate) fn is_available(ctx: &collector::Context) -> bool {
+    ctx.path(PACMAN_LOCAL_PATH).is_dir()
+}
+
+// https://man.archlinux.org/man/alpm-db-desc.5
+fn parse_sections(data: &str) -> HashMap<String, Vec<String>> {
+    let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in data.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            current = None;
+        } else if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+            let name = line[1..line.len() - 1].to_owned();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let Some(name) = &current {
+            sections
+                .entry(name.to_owned())
+                .or_default()
+                .push(line.to_owned());
+        }
+    }
+
+    sections
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Package {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub license: String,
+    pub reason: String,
+    pub modified: DateTime<Utc>,
+    pub files: Vec<String>,
+
+    path: String,
+    publishers: Vec<String>,
+    #[serde(skip)]
+    distro: Distro,
+    #[serde(skip)]
+    provides: Vec<String>,
+    #[serde(skip)]
+    requires: Vec<Vec<String>>,
+    #[serde(skip)]
+    dependencies: Vec<Purl>,
+}
+
+impl Package {
+    fn from_desc(desc: &str) -> Self {
+        let sections = parse_sections(desc);
+        let first = |name: &str| {
+            sections
+                .get(name)
+                .and_then(|values| values.first())
+                .cloned()
+                .unwrap_or_default()
+        };
+        // "glibc", "libcap.so=2-64", "bash>=5", the versions are dropped
+        let names = |name: &str| -> Vec<String> {
+            sections
+                .get(name)
+                .into_iter()
+                .flatten()
+                .filter_map(|value| value.split(['<', '>', '=']).next())
+                .map(|value| value.to_owned())
+                .collect()
+        };
+
+        Self {
+            name: first("NAME"),
+            version: first("VERSION"),
+            arch: first("ARCH"),
+            license: sections
+                .get("LICENSE")
+                .map(|values| values.join(" AND "))
+                .unwrap_or_default(),
+            reason: first("REASON"),
+            modified: first("INSTALLDATE")
+                .parse()
+                .ok()
+                .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+                .unwrap_or_default(),
+            files: vec![],
+            path: "/".to_owned(),
+            publishers: sections.get("PACKAGER").cloned().unwrap_or_default(),
+            distro: Distro::default(),
+            provides: names("PROVIDES"),
+            requires: names("DEPENDS")
+                .into_iter()
+                .map(|name| vec![name])
+                .collect(),
+            dependencies: vec![],
+        }
+    }
+
+    fn load_files(&mut self, files: &str) {
+        self.files = parse_sections(files)
+            .remove("FILES")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|file| format!("/{}", file.trim_end_matches('/')))
+            .collect();
+        self.path = linux::install_path(&self.files);
+    }
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        if self.reason == REASON_DEPENDENCY {
+            Kind::Dependency
+        } else {
+            Kind::Application
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            self.distro.apply(
+                Purl::new("alpm", &self.name)
+                    .with_namespace("arch")
+                    .with_version(&self.version)
+                    .with_qualifier("arch", &self.arch),
+            ),
+        )
+    }
+
+    fn license(&self) -> Option<&str> {
+        if self.license.is_empty() {
+            None
+        } else {
+            Some(&self.license)
+        }
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect()
+    }
+}
+
+impl Dependent for Package {
+    fn provides(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.provides.iter().map(|name| name.as_str()));
+        names
+    }
+
+    fn requires(&self) -> &[Vec<String>] {
+        &self.requires
+    }
+
+    fn set_dependencies(&mut self, dependencies: Vec<Purl>) {
+        self.dependencies = dependencies;
+    }
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+    local_path: PathBuf,
+    distro: Distro,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context, distro: Distro) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            local_path: ctx.path(PACMAN_LOCAL_PATH),
+            distro,
+        }
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let mut collected = Collected::new("pacman");
+        let mut packages = vec![];
+
+        let entries = fs::read_dir(&self.local_path).map_err(|e| Error::io(&self.local_path, e))?;
+        // the order of read_dir depends on the file system
+        let mut names: Vec<OsString> = entries.flatten().map(|entry| entry.file_name()).collect();
+        names.sort();
+
+        for name in names {
+            // the files are resolved in the inspected system as they can be symlinks
+            let dir = Path::new(PACMAN_LOCAL_PATH).join(name);
+            let desc_path = self.ctx.path(dir.join("desc"));
+            if !desc_path.exists() {
+                // ALPM_DB_VERSION and other non package entries
+                continue;
+            }
+
+            let desc = match fs::read_to_string(&desc_path) {
+                Ok(desc) => desc,
+                Err(e) => {
+                    collected.warn(Error::io(&desc_path, e));
+                    continue;
+                }
+            };
+
+            let mut package = Package::from_desc(&desc);
+            if package.name.is_empty() {
+                log::debug!("skipping {}: no package name", desc_path.display());
+                continue;
+            }
+
+            if let Ok(files) = fs::read_to_string(self.ctx.path(dir.join("files"))) {
+                package.load_files(&files);
+            }
+            package.distro = self.distro.clone();
+            packages.push(package);
+        }
+
+        linux::resolve_dependencies(&mut packages);
+        for package in packages {
+            collected.own(&package.files, package.purl());
+            collected.push(package);
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::Collector as _;
+
+    const DESC: &str = "\
+%NAME%
+libcap
+
+%VERSION%
+2.69-1
+
+%DESC%
+POSIX 1003.1e capabilities
+
+%ARCH%
+x86_64
+
+%INSTALLDATE%
+1700000000
+
+%PACKAGER%
+Christian Hesse <eworm@archlinux.org>
+
+%LICENSE%
+GPL2
+BSD-3-Clause
+
+%REASON%
+1
+
+%PROVIDES%
+libcap.so=2-64
+
+%DEPENDS%
+glibc
+pam>=1.5
+";
+
+    const FILES: &str = "\
+%FILES%
+usr/
+usr/lib/
+usr/lib/libcap.so.2.69
+usr/share/licenses/libcap/
+
+%BACKUP%
+etc/security/capability.conf\td41d8cd98f00b204e9800998ecf8427e
+";
+
+    #[test]
+    fn parses_sections() {
+        let sections = parse_sections(DESC);
+        assert_eq!(sections["NAME"], vec!["libcap"]);
+        assert_eq!(sections["LICENSE"], vec!["GPL2", "BSD-3-Clause"]);
+        assert_eq!(sections["DEPENDS"], vec!["glibc", "pam>=1.5"]);
+        assert_eq!(sections.len(), 10);
+
+        // an empty section, and lines outside of any section
+        let sections = parse_sections("%EMPTY%\n\nignored\n%%\n");
+        assert_eq!(sections.len(), 1);
+        assert!(sections["EMPTY"].is_empty());
+    }
+
+    #[test]
+    fn reads_desc() {
+        let mut package = Package::from_desc(DESC);
+        assert_eq!(package.name, "libcap");
+        assert_eq!(package.version, "2.69-1");
+        assert_eq!(package.license(), Some("GPL2 AND BSD-3-Clause"));
+        assert_eq!(package.kind(), Kind::Dependency);
+        assert_eq!(package.modified.timestamp(), 1700000000);
+        assert_eq!(
+            package.publishers,
+            vec!["Christian Hesse <eworm@archlinux.org>"]
+        );
+        assert_eq!(package.provides(), vec!["libcap", "libcap.so"]);
+        assert_eq!(package.requires, vec![vec!["glibc"], vec!["pam"]]);
+        assert_eq!(
+            package.purl().unwrap().to_string(),
+            "pkg:alpm/arch/libcap@2.69-1?arch=x86_64"
+        );
+
+        // directories end with a slash
+        package.load_files(FILES);
+        assert_eq!(
+            package.files,
+            vec![
+                "/usr",
+                "/usr/lib",
+                "/usr/lib/libcap.so.2.69",
+                "/usr/share/licenses/libcap"
+            ]
+        );
+
+        let explicit = Package::from_desc(&DESC.replace("%REASON%\n1\n", ""));
+        assert_eq!(explicit.kind(), Kind::Application);
+    }
+
+    #[test]
+    fn collects_local_database() {
+        let root = tempfile::tempdir().unwrap();
+        let local = root.path().join(PACMAN_LOCAL_PATH.trim_start_matches('/'));
+        for (dir, desc) in [
+            (
+                "pam-1.5.3-3",
+                "%NAME%\npam\n\n%VERSION%\n1.5.3-3\n\n%ARCH%\nx86_64\n",
+            ),
+            ("libcap-2.69-1", DESC),
+            (
+                "glibc-2.38-7",
+                "%NAME%\nglibc\n\n%VERSION%\n2.38-7\n\n%ARCH%\nx86_64\n",
+            ),
+        ] {
+            fs::create_dir_all(local.join(dir)).unwrap();
+            fs::write(local.join(dir).join("desc"), desc).unwrap();
+        }
+        fs::write(local.join("libcap-2.69-1/files"), FILES).unwrap();
+        fs::write(local.join("ALPM_DB_VERSION"), "9\n").unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let collected = Collector::new(&ctx, Distro::default()).collect().unwrap();
+
+        // sorted by folder, whatever the order of the file system
+        let names: Vec<&str> = collected.components.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["glibc", "libcap", "pam"]);
+
+        let dependencies: Vec<String> = collected.components[1]
+            .relationships()
+            .iter()
+            .map(|rel| rel.purl.to_string())
+            .collect();
+        assert_eq!(
+            dependencies,
+            vec![
+                "pkg:alpm/arch/glibc@2.38-7?arch=x86_64",
+                "pkg:alpm/arch/pam@1.5.3-3?arch=x86_64"
+            ]
+        );
+        assert_eq!(
+            collected.owned.get("/usr/lib/libcap.so.2.69"),
+            Some(&collected.components[1].purl())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_symlinked_files_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("desc"), "%NAME%\nhost\n").unwrap();
+        fs::write(outside.path().join("files"), "%FILES%\nhostsecret\n").unwrap();
+
+        let local = root.path().join(PACMAN_LOCAL_PATH.trim_start_matches('/'));
+        fs::create_dir_all(local.join("libcap-2.69-1")).unwrap();
+        fs::write(local.join("libcap-2.69-1/desc"), DESC).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("files"),
+            local.join("libcap-2.69-1/files"),
+        )
+        .unwrap();
+        fs::create_dir_all(local.join("host-1-1")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("desc"), local.join("host-1-1/desc"))
+            .unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let collected = Collector::new(&ctx, Distro::default()).collect().unwrap();
+
+        let names: Vec<&str> = collected.components.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["libcap"]);
+        assert!(!collected.owned.contains_key("/hostsecret"));
+    }
+}
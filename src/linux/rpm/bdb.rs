@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Error;
+
+// https://github.com/berkeleydb/libdb/blob/master/src/dbinc/db_page.h
+const HASH_MAGIC: u32 = 0x061561;
+const HASH_METADATA_PAGE: u8 = 8;
+const HASH_UNSORTED_PAGE: u8 = 2;
+const HASH_PAGE: u8 = 13;
+const OVERFLOW_PAGE: u8 = 7;
+
+const HASH_OFFPAGE_ITEM: u8 = 3;
+
+const PAGE_HEADER_SIZE: usize = 26;
+
+fn malformed(message: String) -> Error {
+    Error::parse("BerkeleyDB database", message)
+}
+
+struct Reader {
+    data: Vec<u8>,
+    page_size: usize,
+    last_page: u32,
+    swapped: bool,
+}
+
+impl Reader {
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let b = self.data.get(offset..offset + 2)?;
+        let b = [b[0], b[1]];
+        Some(if self.swapped {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let b = self.data.get(offset..offset + 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Some(if self.swapped {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+
+    fn page(&self, page_no: u32) -> Option<&[u8]> {
+        let start = page_no as usize * self.page_size;
+        self.data.get(start..start + self.page_size)
+    }
+
+    fn page_type(&self, page_no: u32) -> Option<u8> {
+        self.page(page_no).map(|page| page[25])
+    }
+
+    fn read_overflow(&self, mut page_no: u32, total_len: usize) -> Result<Vec<u8>, Error> {
+        // the length comes from the file, it can't be bigger than the file itself
+        let mut value = Vec::with_capacity(total_len.min(self.data.len()));
+        // a chain longer than the number of pages loops through a corrupted next page
+        let max_pages = self.data.len() / self.page_size;
+        let mut pages = 0;
+
+        while page_no != 0 && value.len() < total_len {
+            pages += 1;
+            if pages > max_pages {
+                return Err(malformed(format!(
+                    "overflow chain loops at page {}",
+                    page_no
+                )));
+            }
+
+            let start = page_no as usize * self.page_size;
+            let page = self
+                .page(page_no)
+                .ok_or_else(|| malformed(format!("overflow page {} out of bounds", page_no)))?;
+            if page[25] != OVERFLOW_PAGE {
+                return Err(malformed(format!(
+                    "page {} has type {}, expected overflow",
+                    page_no, page[25]
+                )));
+            }
+
+            let next_page = self.u32_at(start + 16).unwrap_or_default();
+            // for overflow pages the free area offset holds the number of used bytes
+            let used = self.u16_at(start + 22).unwrap_or_default() as usize;
+
+            value.extend_from_slice(
+                page.get(PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + used)
+                    .ok_or_else(|| malformed(format!("overflow page {} is corrupted", page_no)))?,
+            );
+            page_no = next_page;
+        }
+
+        value.truncate(total_len);
+
+        Ok(value)
+    }
+}
+
+/// Reads all the values of a BerkeleyDB hash database such as the legacy RPM Packages file.
+pub(crate) fn read_values(path: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+
+    let mut reader = Reader {
+        data,
+        page_size: 0,
+        last_page: 0,
+        swapped: false,
+    };
+
+    match reader.u32_at(12) {
+        Some(HASH_MAGIC) => {}
+        Some(magic) if magic.swap_bytes() == HASH_MAGIC => reader.swapped = true,
+        _ => {
+            return Err(Error::parse(
+                path.display(),
+                "not a BerkeleyDB hash database",
+            ))
+        }
+    }
+
+    match reader.data.get(25) {
+        Some(&HASH_METADATA_PAGE) => {}
+        page_type => {
+            return Err(Error::parse(
+                path.display(),
+                format!("unexpected metadata page type {:?}", page_type),
+            ))
+        }
+    }
+
+    reader.page_size = reader.u32_at(20).unwrap_or_default() as usize;
+    reader.last_page = reader.u32_at(32).unwrap_or_default();
+    if reader.page_size < 512 {
+        return Err(Error::parse(
+            path.display(),
+            format!("invalid page size {}", reader.page_size),
+        ));
+    }
+
+    // the last page comes from the file, the pages past its end are never read
+    let last_page = reader
+        .last_page
+        .min((reader.data.len() / reader.page_size) as u32);
+    let mut values = vec![];
+    for page_no in 1..=last_page {
+        match reader.page_type(page_no) {
+            Some(HASH_PAGE) | Some(HASH_UNSORTED_PAGE) => {}
+            _ => continue,
+        }
+
+        let start = page_no as usize * reader.page_size;
+        let entries = reader.u16_at(start + 20).unwrap_or_default() as usize;
+
+        // entries are key/value pairs, we only want the values
+        for idx in (1..entries).step_by(2) {
+            let item_offset = match reader.u16_at(start + PAGE_HEADER_SIZE + idx * 2) {
+                Some(offset) => start + offset as usize,
+                None => break,
+            };
+
+            if reader.data.get(item_offset) != Some(&HASH_OFFPAGE_ITEM) {
+                // headers are always bigger than a page, anything else is not a package
+                continue;
+            }
+
+            let page_no = reader.u32_at(item_offset + 4).unwrap_or_default();
+            let total_len = reader.u32_at(item_offset + 8).unwrap_or_default() as usize;
+
+            values.push(reader.read_overflow(page_no, total_len)?);
+        }
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const PAGE_SIZE: usize = 512;
+
+    fn put_u16(page: &mut [u8], offset: usize, value: u16) {
+        page[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(page: &mut [u8], offset: usize, value: u32) {
+        page[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    // a metadata page, a hash page with one off-page value and the given overflow pages
+    fn database(total_len: u32, overflow: &[(u32, &[u8])]) -> Vec<u8> {
+        let last_page = 1 + overflow.len() as u32;
+        let mut data = vec![0; PAGE_SIZE * (last_page as usize + 1)];
+
+        let meta = &mut data[..PAGE_SIZE];
+        put_u32(meta, 12, HASH_MAGIC);
+        put_u32(meta, 20, PAGE_SIZE as u32);
+        meta[25] = HASH_METADATA_PAGE;
+        put_u32(meta, 32, last_page);
+
+        let hash = &mut data[PAGE_SIZE..2 * PAGE_SIZE];
+        hash[25] = HASH_PAGE;
+        put_u16(hash, 20, 2);
+        put_u16(hash, PAGE_HEADER_SIZE, 400);
+        put_u16(hash, PAGE_HEADER_SIZE + 2, 450);
+        hash[450] = HASH_OFFPAGE_ITEM;
+        put_u32(hash, 454, 2);
+        put_u32(hash, 458, total_len);
+
+        for (idx, (next_page, bytes)) in overflow.iter().enumerate() {
+            let start = (idx + 2) * PAGE_SIZE;
+            let page = &mut data[start..start + PAGE_SIZE];
+            page[25] = OVERFLOW_PAGE;
+            put_u32(page, 16, *next_page);
+            put_u16(page, 22, bytes.len() as u16);
+            page[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + bytes.len()].copy_from_slice(bytes);
+        }
+
+        data
+    }
+
+    fn read(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(data).unwrap();
+        read_values(file.path())
+    }
+
+    #[test]
+    fn reads_overflow_chain() {
+        let data = database(10, &[(3, b"hello "), (0, b"world")]);
+        assert_eq!(read(&data).unwrap(), vec![b"hello worl".to_vec()]);
+    }
+
+    #[test]
+    fn stops_at_the_value_length() {
+        // the second page points back to the first one
+        let data = database(8, &[(3, b"abcd"), (2, b"efgh")]);
+        assert_eq!(read(&data).unwrap(), vec![b"abcdefgh".to_vec()]);
+    }
+
+    #[test]
+    fn rejects_cyclic_chains() {
+        let data = database(u32::MAX, &[(3, b"abcd"), (2, b"efgh")]);
+        assert!(read(&data).is_err());
+
+        // empty pages never reach the length of the value
+        let data = database(16, &[(2, b"")]);
+        assert!(read(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_overflow_pages() {
+        let mut data = database(4, &[(0, b"abcd")]);
+        data[2 * PAGE_SIZE + 25] = HASH_PAGE;
+        assert!(read(&data).is_err());
+
+        let data = database(4, &[(9, b"ab")]);
+        assert!(read(&data).is_err());
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_file() {
+        let mut data = database(5, &[(0, b"hello")]);
+        put_u32(&mut data, 32, u32::MAX);
+        assert_eq!(read(&data).unwrap(), vec![b"hello".to_vec()]);
+    }
+}
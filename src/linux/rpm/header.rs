@@ -0,0 +1,241 @@
+use crate::Error;
+
+// https://github.com/rpm-software-management/rpm/blob/master/include/rpm/rpmtag.h
+pub(crate) const TAG_NAME: u32 = 1000;
+pub(crate) const TAG_VERSION: u32 = 1001;
+pub(crate) const TAG_RELEASE: u32 = 1002;
+pub(crate) const TAG_EPOCH: u32 = 1003;
+pub(crate) const TAG_INSTALLTIME: u32 = 1008;
+pub(crate) const TAG_VENDOR: u32 = 1011;
+pub(crate) const TAG_LICENSE: u32 = 1014;
+pub(crate) const TAG_ARCH: u32 = 1022;
+pub(crate) const TAG_PROVIDENAME: u32 = 1047;
+pub(crate) const TAG_REQUIRENAME: u32 = 1049;
+pub(crate) const TAG_DIRINDEXES: u32 = 1116;
+pub(crate) const TAG_BASENAMES: u32 = 1117;
+pub(crate) const TAG_DIRNAMES: u32 = 1118;
+
+const TYPE_INT32: u32 = 4;
+const TYPE_STRING: u32 = 6;
+const TYPE_STRING_ARRAY: u32 = 8;
+const TYPE_I18NSTRING: u32 = 9;
+
+const ENTRY_INFO_SIZE: usize = 16;
+
+struct EntryInfo {
+    tag: u32,
+    kind: u32,
+    offset: usize,
+    count: usize,
+}
+
+/// An RPM header as stored in the package database, that is without the lead and the header magic.
+pub(crate) struct Header<'a> {
+    entries: Vec<EntryInfo>,
+    data: &'a [u8],
+}
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("rpm header", message)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+impl<'a> Header<'a> {
+    pub fn parse(blob: &'a [u8]) -> Result<Self, Error> {
+        let index_len =
+            read_u32(blob, 0).ok_or_else(|| malformed("header blob too short"))? as usize;
+        let data_len =
+            read_u32(blob, 4).ok_or_else(|| malformed("header blob too short"))? as usize;
+
+        let data = index_len
+            .checked_mul(ENTRY_INFO_SIZE)
+            .and_then(|index_size| index_size.checked_add(8))
+            .and_then(|data_start| Some(data_start..data_start.checked_add(data_len)?))
+            .and_then(|range| blob.get(range))
+            .ok_or_else(|| {
+                malformed(format!(
+                    "header blob of {} bytes too short for {} entries and {} bytes of data",
+                    blob.len(),
+                    index_len,
+                    data_len
+                ))
+            })?;
+
+        let mut entries = vec![];
+        for idx in 0..index_len {
+            let base = 8 + idx * ENTRY_INFO_SIZE;
+            entries.push(EntryInfo {
+                tag: read_u32(blob, base).unwrap_or_default(),
+                kind: read_u32(blob, base + 4).unwrap_or_default(),
+                offset: read_u32(blob, base + 8).unwrap_or_default() as usize,
+                count: read_u32(blob, base + 12).unwrap_or_default() as usize,
+            });
+        }
+
+        Ok(Self { entries, data })
+    }
+
+    fn entry(&self, tag: u32) -> Option<&EntryInfo> {
+        self.entries.iter().find(|e| e.tag == tag)
+    }
+
+    fn strings_at(&self, offset: usize, count: usize) -> Vec<String> {
+        let mut strings = vec![];
+        let mut offset = offset;
+        for _ in 0..count {
+            let data = match self.data.get(offset..) {
+                Some(data) => data,
+                None => break,
+            };
+            let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+            strings.push(String::from_utf8_lossy(&data[..end]).into_owned());
+            offset += end + 1;
+        }
+        strings
+    }
+
+    pub fn string(&self, tag: u32) -> Option<String> {
+        let entry = self.entry(tag)?;
+        match entry.kind {
+            // for i18n strings the first one is the default locale
+            TYPE_STRING | TYPE_I18NSTRING | TYPE_STRING_ARRAY => {
+                self.strings_at(entry.offset, 1).pop()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn strings(&self, tag: u32) -> Vec<String> {
+        match self.entry(tag) {
+            Some(entry) if entry.kind == TYPE_STRING_ARRAY || entry.kind == TYPE_I18NSTRING => {
+                self.strings_at(entry.offset, entry.count)
+            }
+            Some(entry) if entry.kind == TYPE_STRING => self.strings_at(entry.offset, 1),
+            _ => vec![],
+        }
+    }
+
+    pub fn int32s(&self, tag: u32) -> Vec<u32> {
+        match self.entry(tag) {
+            Some(entry) if entry.kind == TYPE_INT32 => {
+                // the count comes from the blob, it can't be bigger than the remaining data
+                let available = self.data.len().saturating_sub(entry.offset) / 4;
+                (0..entry.count.min(available))
+                    .filter_map(|idx| read_u32(self.data, entry.offset + idx * 4))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn int32(&self, tag: u32) -> Option<u32> {
+        self.int32s(tag).first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG_UNKNOWN: u32 = 5000;
+
+    fn blob(entries: &[(u32, u32, u32, u32)], data: &[u8]) -> Vec<u8> {
+        let mut blob = vec![];
+        blob.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        blob.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        for (tag, kind, offset, count) in entries {
+            for value in [tag, kind, offset, count] {
+                blob.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        blob.extend_from_slice(data);
+        blob
+    }
+
+    #[test]
+    fn reads_tags() {
+        let mut data = b"bash\0/usr/bin/\0/etc/\0GPLv3+\0".to_vec();
+        data.extend_from_slice(&1700000000u32.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+        let blob = blob(
+            &[
+                (TAG_NAME, TYPE_STRING, 0, 1),
+                (TAG_DIRNAMES, TYPE_STRING_ARRAY, 5, 2),
+                (TAG_LICENSE, TYPE_I18NSTRING, 21, 1),
+                (TAG_INSTALLTIME, TYPE_INT32, 28, 1),
+                (TAG_DIRINDEXES, TYPE_INT32, 32, 2),
+            ],
+            &data,
+        );
+
+        let header = Header::parse(&blob).unwrap();
+        assert_eq!(header.string(TAG_NAME).as_deref(), Some("bash"));
+        assert_eq!(header.strings(TAG_NAME), vec!["bash"]);
+        assert_eq!(header.strings(TAG_DIRNAMES), vec!["/usr/bin/", "/etc/"]);
+        assert_eq!(header.string(TAG_DIRNAMES).as_deref(), Some("/usr/bin/"));
+        assert_eq!(header.string(TAG_LICENSE).as_deref(), Some("GPLv3+"));
+        assert_eq!(header.int32(TAG_INSTALLTIME), Some(1700000000));
+        assert_eq!(header.int32s(TAG_DIRINDEXES), vec![1, 0]);
+
+        // missing tags and mismatched types
+        assert_eq!(header.string(TAG_UNKNOWN), None);
+        assert_eq!(header.string(TAG_INSTALLTIME), None);
+        assert!(header.int32s(TAG_NAME).is_empty());
+        assert!(header.strings(TAG_DIRINDEXES).is_empty());
+    }
+
+    #[test]
+    fn entries_out_of_the_data() {
+        let blob = blob(
+            &[
+                (TAG_NAME, TYPE_STRING, 100, 1),
+                (TAG_DIRNAMES, TYPE_STRING_ARRAY, 0, 5),
+                (TAG_EPOCH, TYPE_INT32, 2, 2),
+            ],
+            b"a\0b\0",
+        );
+
+        let header = Header::parse(&blob).unwrap();
+        assert_eq!(header.string(TAG_NAME), None);
+        // the last string is unterminated
+        assert_eq!(header.strings(TAG_DIRNAMES), vec!["a", "b", ""]);
+        assert!(header.int32s(TAG_EPOCH).is_empty());
+    }
+
+    #[test]
+    fn bounds_counts_by_the_data() {
+        let blob = blob(
+            &[
+                (TAG_DIRINDEXES, TYPE_INT32, 0, u32::MAX),
+                (TAG_EPOCH, TYPE_INT32, u32::MAX, u32::MAX),
+            ],
+            &[0, 0, 0, 1, 0, 0, 0, 2, 0xff],
+        );
+
+        let header = Header::parse(&blob).unwrap();
+        assert_eq!(header.int32s(TAG_DIRINDEXES), vec![1, 2]);
+        assert!(header.int32s(TAG_EPOCH).is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_blobs() {
+        assert!(Header::parse(&[]).is_err());
+        assert!(Header::parse(&[0, 0, 0, 1]).is_err());
+
+        let mut truncated = blob(&[(TAG_NAME, TYPE_STRING, 0, 1)], b"bash\0");
+        // one more entry than there is room for
+        truncated[3] = 2;
+        assert!(Header::parse(&truncated).is_err());
+
+        let mut truncated = blob(&[(TAG_NAME, TYPE_STRING, 0, 1)], b"bash\0");
+        truncated.pop();
+        assert!(Header::parse(&truncated).is_err());
+
+        let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(Header::parse(&huge).is_err());
+    }
+}
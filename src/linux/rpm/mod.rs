@@ -0,0 +1,315 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::collector::{self, Collected};
+use crate::component::{ComponentTrait, Kind, Relationship, RelationshipKind};
+use crate::linux::{self, os::Distro, Dependent};
+use crate::purl::Purl;
+use crate::Error;
+
+mod bdb;
+mod header;
+mod ndb;
+mod sqlite;
+
+use header::Header;
+
+// dependencies on features of rpm itself, such as rpmlib(PayloadIsZstd)
+const RPMLIB_PREFIX: &str = "rpmlib(";
+// file dependencies are almost always on interpreters, the other files are not indexed
+const BIN_DIR: &str = "/bin/";
+
+// newer distributions moved the database to /usr/lib/sysimage/rpm and left a symlink behind
+const RPM_DATABASE_PATHS: &[&str] = &["/var/lib/rpm", "/usr/lib/sysimage/rpm"];
+
+#[derive(Debug)]
+pub(crate) enum Database {
+    Sqlite(PathBuf),
+    Ndb(PathBuf),
+    BerkeleyDB(PathBuf),
+}
+
+impl Database {
+    fn read_blobs(&self) -> Result<Vec<Vec<u8>>, Error> {
+        match self {
+            Self::Sqlite(path) => sqlite::read_values(path),
+            Self::Ndb(path) => ndb::read_values(path),
+            Self::BerkeleyDB(path) => bdb::read_values(path),
+        }
+    }
+}
+
+pub(crate) fn find_database(ctx: &collector::Context) -> Option<Database> {
+    for base in RPM_DATABASE_PATHS {
+        // the database files are resolved in the inspected system as they can be symlinks
+        let base = Path::new(base);
+
+        let path = ctx.path(base.join("rpmdb.sqlite"));
+        if path.exists() {
+            return Some(Database::Sqlite(path));
+        }
+
+        let path = ctx.path(base.join("Packages.db"));
+        if path.exists() {
+            return Some(Database::Ndb(path));
+        }
+
+        let path = ctx.path(base.join("Packages"));
+        if path.exists() {
+            return Some(Database::BerkeleyDB(path));
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Package {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    pub epoch: Option<u32>,
+    pub license: String,
+    pub modified: DateTime<Utc>,
+    pub files: Vec<String>,
+
+    path: String,
+    publishers: Vec<String>,
+    #[serde(skip)]
+    distro: Distro,
+    #[serde(skip)]
+    provides: Vec<String>,
+    #[serde(skip)]
+    requires: Vec<Vec<String>>,
+    #[serde(skip)]
+    dependencies: Vec<Purl>,
+}
+
+impl Package {
+    fn from_header(header: &Header) -> Self {
+        let mut version = header.string(header::TAG_VERSION).unwrap_or_default();
+        if let Some(release) = header.string(header::TAG_RELEASE) {
+            version = format!("{}-{}", version, release);
+        }
+        let epoch = header.int32(header::TAG_EPOCH);
+        if let Some(epoch) = epoch {
+            version = format!("{}:{}", epoch, version);
+        }
+
+        let dirnames = header.strings(header::TAG_DIRNAMES);
+        let files: Vec<String> = header
+            .strings(header::TAG_BASENAMES)
+            .iter()
+            .zip(header.int32s(header::TAG_DIRINDEXES))
+            .filter_map(|(basename, dir_index)| {
+                dirnames
+                    .get(dir_index as usize)
+                    .map(|dirname| format!("{}{}", dirname, basename))
+            })
+            .collect();
+
+        Self {
+            name: header.string(header::TAG_NAME).unwrap_or_default(),
+            version,
+            arch: header.string(header::TAG_ARCH).unwrap_or_default(),
+            epoch,
+            license: header.string(header::TAG_LICENSE).unwrap_or_default(),
+            modified: header
+                .int32(header::TAG_INSTALLTIME)
+                .and_then(|ts| Utc.timestamp_opt(ts as i64, 0).single())
+                .unwrap_or_default(),
+            path: linux::install_path(&files),
+            files,
+            publishers: header.string(header::TAG_VENDOR).into_iter().collect(),
+            distro: Distro::default(),
+            provides: header.strings(header::TAG_PROVIDENAME),
+            requires: header
+                .strings(header::TAG_REQUIRENAME)
+                .into_iter()
+                .filter(|name| !name.starts_with(RPMLIB_PREFIX))
+                .map(|name| vec![name])
+                .collect(),
+            dependencies: vec![],
+        }
+    }
+}
+
+impl ComponentTrait for Package {
+    fn kind(&self) -> Kind {
+        Kind::Application
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(&self) -> DateTime<Utc> {
+        self.modified
+    }
+
+    fn publishers(&self) -> &Vec<String> {
+        &self.publishers
+    }
+
+    fn purl(&self) -> Option<Purl> {
+        // the epoch is a qualifier rather than part of the version
+        let version = match self.epoch {
+            Some(_) => self
+                .version
+                .split_once(':')
+                .map(|(_, version)| version)
+                .unwrap_or(&self.version),
+            None => &self.version,
+        };
+
+        Some(
+            self.distro.apply(
+                Purl::new("rpm", &self.name)
+                    .with_version(version)
+                    .with_qualifier("arch", &self.arch)
+                    .with_qualifier(
+                        "epoch",
+                        &self.epoch.map(|e| e.to_string()).unwrap_or_default(),
+                    ),
+            ),
+        )
+    }
+
+    fn license(&self) -> Option<&str> {
+        if self.license.is_empty() {
+            None
+        } else {
+            Some(&self.license)
+        }
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.dependencies
+            .iter()
+            .map(|purl| Relationship::new(RelationshipKind::DependsOn, purl.clone()))
+            .collect()
+    }
+}
+
+impl Dependent for Package {
+    fn provides(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        names.extend(self.provides.iter().map(|name| name.as_str()));
+        names.extend(
+            self.files
+                .iter()
+                .filter(|file| file.contains(BIN_DIR))
+                .map(|file| file.as_str()),
+        );
+        names
+    }
+
+    fn requires(&self) -> &[Vec<String>] {
+        &self.requires
+    }
+
+    fn set_dependencies(&mut self, dependencies: Vec<Purl>) {
+        self.dependencies = dependencies;
+    }
+}
+
+pub(crate) struct Collector {
+    database: Database,
+    distro: Distro,
+}
+
+impl Collector {
+    pub fn new(database: Database, distro: Distro) -> Self {
+        Self { database, distro }
+    }
+}
+
+impl collector::Collector for Collector {
+    fn setup(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn collect_from_json(&self, _: &str) -> Result<Collected, Error> {
+        Err(Error::unsupported("not implemented"))
+    }
+
+    fn collect(&self) -> Result<Collected, Error> {
+        let mut collected = Collected::new("rpm");
+        let mut packages = vec![];
+
+        for blob in self.database.read_blobs()? {
+            let header = match Header::parse(&blob) {
+                Ok(header) => header,
+                Err(e) => {
+                    collected.warn(e);
+                    continue;
+                }
+            };
+            let mut package = Package::from_header(&header);
+
+            // imported signing keys are stored as fake packages
+            if package.name.is_empty() || package.name == "gpg-pubkey" {
+                continue;
+            }
+            package.distro = self.distro.clone();
+            packages.push(package);
+        }
+
+        linux::resolve_dependencies(&mut packages);
+        for package in packages {
+            collected.own(&package.files, package.purl());
+            collected.push(package);
+        }
+
+        Ok(collected)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn keeps_symlinked_databases_in_root() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("rpmdb.sqlite"), "").unwrap();
+
+        let rpm = root.path().join("var/lib/rpm");
+        fs::create_dir_all(&rpm).unwrap();
+        symlink(
+            outside.path().join("rpmdb.sqlite"),
+            rpm.join("rpmdb.sqlite"),
+        )
+        .unwrap();
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        assert!(find_database(&ctx).is_none());
+
+        // links inside of the root are still followed
+        let sysimage = root.path().join("usr/lib/sysimage/rpm");
+        fs::create_dir_all(&sysimage).unwrap();
+        fs::write(sysimage.join("Packages.db"), "").unwrap();
+        fs::remove_file(rpm.join("rpmdb.sqlite")).unwrap();
+        symlink("/usr/lib/sysimage/rpm/Packages.db", rpm.join("Packages.db")).unwrap();
+        match find_database(&ctx) {
+            Some(Database::Ndb(path)) => assert_eq!(path, sysimage.join("Packages.db")),
+            other => panic!("unexpected database {:?}", other),
+        }
+    }
+}
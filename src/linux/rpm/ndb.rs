@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Error;
+
+// https://github.com/rpm-software-management/rpm/blob/master/lib/backend/ndb/rpmpkg.c
+const HEADER_MAGIC: u32 = u32::from_le_bytes(*b"RpmP");
+const SLOT_MAGIC: u32 = u32::from_le_bytes(*b"Slot");
+const BLOB_MAGIC: u32 = u32::from_le_bytes(*b"BlbS");
+
+const PAGE_SIZE: usize = 4096;
+const SLOT_SIZE: usize = 16;
+const BLOB_HEADER_SIZE: usize = 16;
+const BLOCK_SIZE: usize = 16;
+// the database header takes the space of the first two slots
+const HEADER_SLOTS: usize = 2;
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Reads all the package blobs of an RPM ndb database (Packages.db).
+pub(crate) fn read_values(path: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+
+    if u32_at(&data, 0) != Some(HEADER_MAGIC) {
+        return Err(Error::parse(path.display(), "not an ndb database"));
+    }
+
+    let slot_pages = u32_at(&data, 12).unwrap_or_default() as usize;
+    let slots_end = (slot_pages * PAGE_SIZE).min(data.len());
+
+    let mut values = vec![];
+    for slot in (HEADER_SLOTS * SLOT_SIZE..slots_end).step_by(SLOT_SIZE) {
+        let magic = u32_at(&data, slot).unwrap_or_default();
+        if magic != SLOT_MAGIC {
+            return Err(Error::parse(
+                path.display(),
+                format!("bad slot magic {:x} at offset {}", magic, slot),
+            ));
+        }
+
+        let pkg_index = u32_at(&data, slot + 4).unwrap_or_default();
+        if pkg_index == 0 {
+            // free slot
+            continue;
+        }
+
+        let blob = u32_at(&data, slot + 8).unwrap_or_default() as usize * BLOCK_SIZE;
+        if u32_at(&data, blob) != Some(BLOB_MAGIC) {
+            return Err(Error::parse(
+                path.display(),
+                format!("bad blob magic for package {}", pkg_index),
+            ));
+        }
+        if u32_at(&data, blob + 4) != Some(pkg_index) {
+            return Err(Error::parse(
+                path.display(),
+                format!("blob index mismatch for package {}", pkg_index),
+            ));
+        }
+
+        let blob_len = u32_at(&data, blob + 12).unwrap_or_default() as usize;
+        let start = blob + BLOB_HEADER_SIZE;
+        let value = data.get(start..start + blob_len).ok_or_else(|| {
+            Error::parse(
+                path.display(),
+                format!("blob for package {} out of bounds", pkg_index),
+            )
+        })?;
+
+        values.push(value.to_vec());
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    // one page of slots, the first one pointing to a blob right after it
+    fn database(value: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; PAGE_SIZE + BLOB_HEADER_SIZE + value.len()];
+        put_u32(&mut data, 0, HEADER_MAGIC);
+        put_u32(&mut data, 12, 1);
+        for slot in (HEADER_SLOTS * SLOT_SIZE..PAGE_SIZE).step_by(SLOT_SIZE) {
+            put_u32(&mut data, slot, SLOT_MAGIC);
+        }
+
+        let slot = HEADER_SLOTS * SLOT_SIZE;
+        put_u32(&mut data, slot + 4, 7);
+        put_u32(&mut data, slot + 8, (PAGE_SIZE / BLOCK_SIZE) as u32);
+        put_u32(&mut data, PAGE_SIZE, BLOB_MAGIC);
+        put_u32(&mut data, PAGE_SIZE + 4, 7);
+        put_u32(&mut data, PAGE_SIZE + 12, value.len() as u32);
+        data[PAGE_SIZE + BLOB_HEADER_SIZE..].copy_from_slice(value);
+
+        data
+    }
+
+    fn read(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(data).unwrap();
+        read_values(file.path())
+    }
+
+    #[test]
+    fn reads_blobs() {
+        assert_eq!(
+            read(&database(b"header")).unwrap(),
+            vec![b"header".to_vec()]
+        );
+    }
+
+    #[test]
+    fn rejects_corrupted_databases() {
+        assert!(read(b"RpmX").is_err());
+
+        let mut data = database(b"header");
+        put_u32(&mut data, PAGE_SIZE + 4, 8);
+        assert!(read(&data).is_err());
+
+        let mut data = database(b"header");
+        put_u32(&mut data, PAGE_SIZE + 12, 100);
+        assert!(read(&data).is_err());
+
+        let mut data = database(b"header");
+        put_u32(&mut data, 64, 0);
+        assert!(read(&data).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::Error;
+
+/// Reads all the package blobs of an RPM sqlite database (rpmdb.sqlite).
+pub(crate) fn read_values(path: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| Error::parse(path.display(), e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT blob FROM Packages")
+        .map_err(|e| Error::parse(path.display(), e))?;
+
+    let rows = stmt
+        .query_map([], |row| row.get::<_, Vec<u8>>(0))
+        .map_err(|e| Error::parse(path.display(), e))?;
+
+    rows.map(|row| row.map_err(|e| Error::parse(path.display(), e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_package_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpmdb.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE Packages (hnum INTEGER PRIMARY KEY AUTOINCREMENT, blob BLOB NOT NULL);
+             INSERT INTO Packages (blob) VALUES (x'0001'), (x'02');",
+        )
+        .unwrap();
+        drop(conn);
+
+        assert_eq!(read_values(&path).unwrap(), vec![vec![0, 1], vec![2]]);
+    }
+
+    #[test]
+    fn rejects_other_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rpmdb.sqlite");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE Other (blob BLOB);")
+            .unwrap();
+
+        assert!(read_values(&path).is_err());
+        assert!(read_values(&dir.path().join("missing.sqlite")).is_err());
+    }
+}
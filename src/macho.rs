@@ -0,0 +1,360 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::binary::{self, SectionHeader};
+use crate::Error;
+
+// https://github.com/apple-oss-distributions/xnu/blob/main/EXTERNAL_HEADERS/mach-o/loader.h
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const MH_CIGAM: u32 = 0xcefaedfe;
+const MH_CIGAM_64: u32 = 0xcffaedfe;
+// universal binaries, big endian
+const FAT_MAGIC: u32 = 0xcafebabe;
+// java class files share the fat magic, they are followed by the class version and not by a small count
+const MAX_FAT_ARCHS: u32 = 30;
+const MH_EXECUTE: u32 = 0x2;
+const LC_SEGMENT: u32 = 0x1;
+const LC_SEGMENT_64: u32 = 0x19;
+// sections without data in the file, such as __bss
+const S_ZEROFILL: u32 = 0x1;
+const S_GB_ZEROFILL: u32 = 0xc;
+const S_THREAD_LOCAL_ZEROFILL: u32 = 0x12;
+const SECTION_TYPE: u32 = 0xff;
+// the magic and the number of architectures, followed by the first one
+const FAT_HEADER_SIZE: u64 = 28;
+// of the 64 bits header, followed by the load commands
+const HEADER_SIZE: u64 = 32;
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("Mach-O image", message)
+}
+
+fn magic(data: &[u8]) -> Option<u32> {
+    data.get(..4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Returns true if the data starts with the signature of a Mach-O or universal binary.
+pub(crate) fn is_macho(data: &[u8]) -> bool {
+    match magic(data) {
+        Some(MH_MAGIC | MH_MAGIC_64 | MH_CIGAM | MH_CIGAM_64) => true,
+        Some(FAT_MAGIC) => magic(&data[4..]).is_some_and(|count| count < MAX_FAT_ARCHS),
+        _ => false,
+    }
+}
+
+/// Returns true if the header is the one of an executable, the architectures of universal binaries
+/// are further in the file and are assumed to be executables.
+pub(crate) fn is_executable(header: &[u8]) -> bool {
+    match magic(header) {
+        Some(FAT_MAGIC) => is_macho(header),
+        Some(_) => Image::parse(header)
+            .and_then(|image| image.u32(12))
+            .is_ok_and(|file_type| file_type == MH_EXECUTE),
+        None => false,
+    }
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl<'a> Image<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let (is_64, big_endian) = match magic(data) {
+            Some(MH_MAGIC) => (false, true),
+            Some(MH_MAGIC_64) => (true, true),
+            Some(MH_CIGAM) => (false, false),
+            Some(MH_CIGAM_64) => (true, false),
+            _ => return Err(malformed("not a Mach-O file: missing signature")),
+        };
+
+        Ok(Self {
+            data,
+            is_64,
+            big_endian,
+        })
+    }
+
+    fn uint(&self, offset: usize, size: usize) -> Result<u64, Error> {
+        let bytes = offset
+            .checked_add(size)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| malformed(format!("truncated at offset {:#x}", offset)))?;
+
+        let mut value = 0u64;
+        for idx in 0..size {
+            let byte = if self.big_endian {
+                bytes[idx]
+            } else {
+                bytes[size - 1 - idx]
+            };
+            value = (value << 8) | byte as u64;
+        }
+        Ok(value)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        self.uint(offset, 4).map(|v| v as u32)
+    }
+
+    // addresses and sizes are 32 or 64 bits wide depending on the magic
+    fn word(&self, offset: usize) -> Result<u64, Error> {
+        self.uint(offset, if self.is_64 { 8 } else { 4 })
+    }
+
+    fn name(&self, offset: usize) -> Result<String, Error> {
+        let end = offset
+            .checked_add(16)
+            .ok_or_else(|| malformed(format!("name at {:#x} is out of bounds", offset)))?;
+        let name = self
+            .data
+            .get(offset..end)
+            .ok_or_else(|| malformed(format!("truncated name at {:#x}", offset)))?;
+        let name = name.split(|b| *b == 0).next().unwrap_or_default();
+        Ok(String::from_utf8_lossy(name).into_owned())
+    }
+
+    // sections are listed after the header of their segment, their offsets are relative to the
+    // start of the image
+    fn section_headers(&self, base: u64) -> Result<Vec<SectionHeader>, Error> {
+        let (header_size, segment_size, section_size, word) = if self.is_64 {
+            (32, 72, 80, 8)
+        } else {
+            (28, 56, 68, 4)
+        };
+        let ncmds = self.u32(16)?;
+
+        let mut sections = vec![];
+        let mut command = header_size;
+        for _ in 0..ncmds {
+            let cmd = self.u32(command)?;
+            let cmdsize = self.u32(command + 4)? as usize;
+            if cmdsize == 0 {
+                return Err(malformed(format!("empty load command at {:#x}", command)));
+            }
+
+            if cmd == LC_SEGMENT || cmd == LC_SEGMENT_64 {
+                let nsects = self.u32(command + 24 + 4 * word + 8)?;
+                for idx in 0..nsects as usize {
+                    let header = command + segment_size + idx * section_size;
+                    let flags = self.u32(header + 32 + 2 * word + 16)?;
+                    let size = if matches!(
+                        flags & SECTION_TYPE,
+                        S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL
+                    ) {
+                        0
+                    } else {
+                        self.word(header + 32 + word)?
+                    };
+
+                    sections.push(SectionHeader {
+                        name: self.name(header)?,
+                        address: self.word(header + 32)?,
+                        offset: base + self.u32(header + 32 + 2 * word)? as u64,
+                        size,
+                    });
+                }
+            }
+
+            command += cmdsize;
+        }
+
+        Ok(sections)
+    }
+}
+
+fn read<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+    binary::read_at(reader, offset, size).map_err(malformed)
+}
+
+// the offset of the first architecture of a universal binary, they are built from the same sources
+fn thin<R: Read + Seek>(reader: &mut R) -> Result<u64, Error> {
+    let header = read(reader, 0, FAT_HEADER_SIZE)?;
+    if magic(&header) != Some(FAT_MAGIC) {
+        return Ok(0);
+    }
+
+    // cputype, cpusubtype, offset, size, align
+    let arch = header
+        .get(8..28)
+        .ok_or_else(|| malformed("truncated universal header"))?;
+    let offset = u32::from_be_bytes([arch[8], arch[9], arch[10], arch[11]]) as u64;
+    let size = u32::from_be_bytes([arch[12], arch[13], arch[14], arch[15]]) as u64;
+
+    let len = reader.seek(SeekFrom::End(0)).map_err(malformed)?;
+    if offset + size > len {
+        return Err(malformed("architecture out of bounds"));
+    }
+    Ok(offset)
+}
+
+/// Returns where the sections of a Mach-O image, or of the first architecture of a universal
+/// binary, are. Only the headers and the load commands are read.
+pub(crate) fn section_headers<R: Read + Seek>(reader: &mut R) -> Result<Vec<SectionHeader>, Error> {
+    let base = thin(reader)?;
+    let header = read(reader, base, HEADER_SIZE)?;
+    let sizeofcmds = Image::parse(&header)?.u32(20)?;
+    let commands = read(reader, base, HEADER_SIZE + sizeofcmds as u64)?;
+    Image::parse(&commands)?.section_headers(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const SEGMENT_SIZE: usize = 72;
+    const SECTION_SIZE: usize = 80;
+    // where the sections of the test image start
+    const DATA_OFFSET: usize = 0x200;
+
+    fn put(data: &mut [u8], offset: usize, value: u64, size: usize) {
+        data[offset..offset + size].copy_from_slice(&value.to_le_bytes()[..size]);
+    }
+
+    fn put_name(data: &mut [u8], offset: usize, name: &str) {
+        data[offset..offset + name.len()].copy_from_slice(name.as_bytes());
+    }
+
+    // a 64 bits little endian executable with a __TEXT segment holding these sections (name,
+    // data, flags) one after the other
+    fn image(sections: &[(&str, &[u8], u32)]) -> Vec<u8> {
+        let cmdsize = SEGMENT_SIZE + sections.len() * SECTION_SIZE;
+        let mut data = vec![0; DATA_OFFSET];
+        put(&mut data, 0, MH_CIGAM_64.swap_bytes() as u64, 4);
+        put(&mut data, 12, MH_EXECUTE as u64, 4);
+        put(&mut data, 16, 1, 4);
+        put(&mut data, 20, cmdsize as u64, 4);
+
+        let command = HEADER_SIZE as usize;
+        put(&mut data, command, LC_SEGMENT_64 as u64, 4);
+        put(&mut data, command + 4, cmdsize as u64, 4);
+        put_name(&mut data, command + 8, "__TEXT");
+        put(&mut data, command + 64, sections.len() as u64, 4);
+
+        for (idx, (name, content, flags)) in sections.iter().enumerate() {
+            let header = command + SEGMENT_SIZE + idx * SECTION_SIZE;
+            let offset = data.len();
+            put_name(&mut data, header, name);
+            put_name(&mut data, header + 16, "__TEXT");
+            put(&mut data, header + 32, 0x1_0000_0000 + offset as u64, 8);
+            put(&mut data, header + 40, content.len() as u64, 8);
+            put(&mut data, header + 48, offset as u64, 4);
+            put(&mut data, header + 64, *flags as u64, 4);
+            data.extend_from_slice(content);
+        }
+        data
+    }
+
+    fn sample() -> Vec<u8> {
+        image(&[
+            ("__text", b"\xc3", 0),
+            ("__info_plist", b"<plist></plist>", 0),
+            ("__bss", b"", S_ZEROFILL),
+        ])
+    }
+
+    // a universal binary with a single architecture at the given offset
+    fn fat(thin: &[u8], offset: usize) -> Vec<u8> {
+        let mut data = vec![0; offset];
+        data[..4].copy_from_slice(&FAT_MAGIC.to_be_bytes());
+        data[4..8].copy_from_slice(&1u32.to_be_bytes());
+        data[16..20].copy_from_slice(&(offset as u32).to_be_bytes());
+        data[20..24].copy_from_slice(&(thin.len() as u32).to_be_bytes());
+        data.extend_from_slice(thin);
+        data
+    }
+
+    fn headers(data: &[u8]) -> Result<Vec<SectionHeader>, Error> {
+        section_headers(&mut Cursor::new(data))
+    }
+
+    fn section<'a>(data: &'a [u8], headers: &[SectionHeader], name: &str) -> &'a [u8] {
+        let header = headers.iter().find(|h| h.name == name).unwrap();
+        &data[header.offset as usize..(header.offset + header.size) as usize]
+    }
+
+    #[test]
+    fn reads_thin_images() {
+        let data = sample();
+        assert!(is_macho(&data));
+        assert!(is_executable(&data));
+
+        let headers = headers(&data).unwrap();
+        let names: Vec<&str> = headers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["__text", "__info_plist", "__bss"]);
+        assert_eq!(headers[0].address, 0x1_0000_0000 + DATA_OFFSET as u64);
+        assert_eq!(section(&data, &headers, "__info_plist"), b"<plist></plist>");
+        // zero filled sections have no data in the file
+        assert_eq!(headers[2].size, 0);
+
+        // libraries share the format
+        let mut library = data.clone();
+        put(&mut library, 12, 0x6, 4);
+        assert!(is_macho(&library));
+        assert!(!is_executable(&library));
+    }
+
+    #[test]
+    fn reads_fat_images() {
+        let thin = sample();
+        let data = fat(&thin, 0x1000);
+        assert!(is_macho(&data));
+        assert!(is_executable(&data));
+
+        // the offsets are relative to the start of the file
+        let headers = headers(&data).unwrap();
+        assert_eq!(headers[1].offset, 0x1000 + DATA_OFFSET as u64 + 1);
+        assert_eq!(section(&data, &headers, "__info_plist"), b"<plist></plist>");
+
+        // java class files share the magic
+        let mut class = vec![0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41];
+        class.resize(64, 0);
+        assert!(!is_macho(&class));
+    }
+
+    #[test]
+    fn rejects_truncated_load_commands() {
+        let data = sample();
+        assert!(headers(&data[..HEADER_SIZE as usize + 16]).is_err());
+        assert!(headers(&data[..HEADER_SIZE as usize + SEGMENT_SIZE + 20]).is_err());
+        assert!(headers(&data[..8]).is_err());
+
+        // more commands than their size
+        let mut extra = data.clone();
+        put(&mut extra, 16, 2, 4);
+        assert!(headers(&extra).is_err());
+
+        let mut empty = data.clone();
+        put(&mut empty, HEADER_SIZE as usize + 4, 0, 4);
+        assert!(headers(&empty).is_err());
+
+        // the architecture is past the end of the file
+        let data = fat(&sample(), 0x1000);
+        assert!(headers(&data[..data.len() - 1]).is_err());
+        assert!(headers(&data[..16]).is_err());
+    }
+
+    #[test]
+    fn rejects_huge_offsets() {
+        let data = sample();
+        let image = Image::parse(&data).unwrap();
+        assert!(image.name(usize::MAX - 8).is_err());
+        assert!(image.u32(usize::MAX - 2).is_err());
+
+        let mut data = fat(&sample(), 0x1000);
+        data[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(headers(&data).is_err());
+
+        // the headers are read, the data of a section past the end of the file is not
+        let mut data = sample();
+        let header = HEADER_SIZE as usize + SEGMENT_SIZE + SECTION_SIZE;
+        put(&mut data, header + 48, u32::MAX as u64, 4);
+        let headers = headers(&data).unwrap();
+        assert_eq!(headers[1].offset, u32::MAX as u64);
+    }
+}
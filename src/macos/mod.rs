@@ -4,8 +4,9 @@ use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
-use crate::collector;
+use crate::collector::{self, Collected};
 use crate::component::{ComponentTrait, Kind};
+use crate::purl::Purl;
 use crate::utils::serde::string_as_string_vector;
 use crate::Error;
 
@@ -49,6 +50,14 @@ impl ComponentTrait for OS {
     fn publishers(&self) -> &Vec<String> {
         &APPLE_DEFAULT_PUBLISHERS
     }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            Purl::new("generic", self.name())
+                .with_namespace("apple")
+                .with_version(&self.os_version),
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -94,6 +103,10 @@ impl ComponentTrait for Application {
     fn publishers(&self) -> &Vec<String> {
         &self.signed_by
     }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(Purl::new("generic", &self.name).with_version(&self.version))
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -159,6 +172,10 @@ impl ComponentTrait for Extension {
     fn publishers(&self) -> &Vec<String> {
         &self.signed_by
     }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(Purl::new("generic", &self.bundleid).with_version(&self.version))
+    }
 }
 
 #[derive(Deserialize)]
@@ -179,45 +196,45 @@ impl collector::Collector for Collector {
         Ok(())
     }
 
-    fn collect_from_json(&self, json: &str) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
-        let mut comps: Vec<Box<dyn ComponentTrait>> = vec![];
+    fn collect_from_json(&self, json: &str) -> Result<Collected, Error> {
+        let mut collected = Collected::new("macos");
 
-        let profile: Profile = serde_json::from_str(json)
-            .map_err(|e| format!("could not parse system_profiler output: {:?}", e))?;
+        let profile: Profile =
+            serde_json::from_str(json).map_err(|e| Error::parse("system_profiler output", e))?;
 
         for mut os in profile.os {
             os.os_version = os.os_version.replace("macOS ", "");
-            comps.push(Box::new(os));
+            collected.push(os);
         }
 
         for ext in profile.drivers {
-            comps.push(Box::new(ext));
+            collected.push(ext);
         }
 
         for app in profile.apps {
-            comps.push(Box::new(app));
+            collected.push(app);
         }
 
-        Ok(comps)
+        Ok(collected)
     }
 
-    fn collect(&self) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
+    fn collect(&self) -> Result<Collected, Error> {
         log::info!("collecting applications and drivers, please wait ...");
 
         let profiler = Command::new("system_profiler")
             .arg("SPSoftwareDataType")
             .arg("SPExtensionsDataType")
             .arg("SPApplicationsDataType")
-            .args(&["-detailLevel", "full"])
+            .args(["-detailLevel", "full"])
             .arg("-json")
             .output()
-            .map_err(|e| format!("could not execute system_profiler: {:?}", e))?;
+            .map_err(|e| Error::command("system_profiler", None, e.to_string().as_bytes()))?;
 
         if !profiler.status.success() {
-            return Err(format!(
-                "system_profiler exit status {:?}: {:?}",
-                profiler.status,
-                String::from_utf8_lossy(&profiler.stderr)
+            return Err(Error::command(
+                "system_profiler",
+                profiler.status.code(),
+                &profiler.stderr,
             ));
         }
 
@@ -1,62 +1,285 @@
 use std::fs::File;
+use std::path::Path;
 
 use clap::Parser;
 
-pub(crate) type Error = String;
-
-mod collector;
-mod component;
-mod format;
-mod utils;
-
-#[cfg(target_os = "macos")]
-mod macos;
-#[cfg(target_os = "windows")]
-mod windows;
+use unisbom::{diff, format, CollectorKind, Error, HashAlgorithm, Input, Scanner};
 
 #[derive(clap::ValueEnum, Default, Debug, Clone)]
 enum OutputFormat {
     #[default]
     Text,
     Json,
+    CyclonedxJson,
+    SpdxJson,
+    SpdxTv,
 }
 
-#[derive(Parser, Default, Debug, Clone)]
-#[clap(about = "Build a software bill of materials of the current system.")]
-struct Arguments {
+#[derive(clap::ValueEnum, Default, Debug, Clone)]
+enum DiffFormat {
+    #[default]
+    Text,
+    Json,
+    Patch,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum InputType {
+    SystemProfiler,
+    DriverqueryCsv,
+    UninstallRegExport,
+}
+
+impl From<InputType> for Input {
+    fn from(input_type: InputType) -> Self {
+        match input_type {
+            InputType::SystemProfiler => Input::SystemProfiler,
+            InputType::DriverqueryCsv => Input::DriverqueryCsv,
+            InputType::UninstallRegExport => Input::UninstallRegExport,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum CollectorName {
+    Dpkg,
+    Apk,
+    Pacman,
+    Rpm,
+    Kernel,
+    Python,
+    Npm,
+    Binaries,
+    Macos,
+    Windows,
+}
+
+impl From<CollectorName> for CollectorKind {
+    fn from(name: CollectorName) -> Self {
+        match name {
+            CollectorName::Dpkg => CollectorKind::Dpkg,
+            CollectorName::Apk => CollectorKind::Apk,
+            CollectorName::Pacman => CollectorKind::Pacman,
+            CollectorName::Rpm => CollectorKind::Rpm,
+            CollectorName::Kernel => CollectorKind::Kernel,
+            CollectorName::Python => CollectorKind::Python,
+            CollectorName::Npm => CollectorKind::Npm,
+            CollectorName::Binaries => CollectorKind::Binaries,
+            CollectorName::Macos => CollectorKind::MacOS,
+            CollectorName::Windows => CollectorKind::Windows,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum HashName {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl From<HashName> for HashAlgorithm {
+    fn from(name: HashName) -> Self {
+        match name {
+            HashName::Sha1 => HashAlgorithm::Sha1,
+            HashName::Sha256 => HashAlgorithm::Sha256,
+            HashName::Sha512 => HashAlgorithm::Sha512,
+        }
+    }
+}
+
+#[derive(clap::Args, Default, Debug, Clone)]
+struct ScanArguments {
     /// Specify output format, text will print a summary of each component, while JSON will dump the full information.
+    /// Use cyclonedx-json to produce a CycloneDX 1.5 BOM, spdx-json or spdx-tv for an SPDX 2.3 document.
     #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
     format: OutputFormat,
     /// Write output to this file instead of the standard output.
     #[clap(long)]
     output: Option<String>,
+    /// Inspect the filesystem mounted at this path (container rootfs, chroot, disk image) instead of the running system.
+    #[clap(long, conflicts_with = "input")]
+    root: Option<String>,
+    /// Only use these collectors (comma separated), by default all the ones supported by the system are used.
+    #[clap(long, value_enum, use_value_delimiter = true, conflicts_with = "input")]
+    collectors: Vec<CollectorName>,
+    /// Search these folders (comma separated) for language packages, such as python site-packages and node_modules, and for
+    /// executables instead of the default install locations. With --root they are relative to it.
+    #[clap(long, use_value_delimiter = true, conflicts_with = "input")]
+    paths: Vec<String>,
+    /// Only report the executables whose path matches one of these glob patterns (comma separated), such as "/opt/**/bin/*".
+    #[clap(long, use_value_delimiter = true, conflicts_with = "input")]
+    include: Vec<String>,
+    /// Skip the files and folders whose path matches one of these glob patterns (comma separated) while searching for
+    /// language packages and executables.
+    #[clap(long, use_value_delimiter = true, conflicts_with = "input")]
+    exclude: Vec<String>,
+    /// Search the folders for language packages and executables up to this depth.
+    #[clap(long, conflicts_with = "input")]
+    max_depth: Option<usize>,
+    /// Hash the file of each component with these algorithms (comma separated), .app and .kext bundles are hashed
+    /// as a whole while the components installed in another directory, such as system packages, are skipped and
+    /// counted in a diagnostic. This reads every file, so it's disabled by default.
+    #[clap(long, value_enum, use_value_delimiter = true, conflicts_with = "input")]
+    hashes: Vec<HashName>,
+    /// Build the components from a file captured on another machine instead of inspecting a system, requires --input-type.
+    #[clap(long, requires = "input-type")]
+    input: Option<String>,
+    /// Format of the --input file: "system_profiler -json SPSoftwareDataType SPExtensionsDataType SPApplicationsDataType",
+    /// "driverquery /v /fo csv" or a .reg export of the Uninstall registry keys.
+    #[clap(long, value_enum, requires = "input")]
+    input_type: Option<InputType>,
+    /// Check the components for known vulnerabilities, requires --osv-db.
+    #[clap(long, requires = "osv-db")]
+    vulns: bool,
+    /// Path of an OSV database export, either a folder of JSON records or a zip archive.
+    #[clap(long, requires = "vulns")]
+    osv_db: Option<String>,
 }
 
-fn main() -> Result<(), Error> {
-    let args = Arguments::parse();
+#[derive(clap::Args, Debug, Clone)]
+struct DiffArguments {
+    /// Previous scan, either unisbom JSON, CycloneDX JSON, SPDX JSON or SPDX tag-value.
+    old: String,
+    /// Current scan, in any of the formats accepted for the previous one.
+    new: String,
+    /// Specify output format, text will print one line per change, json the changes with the full components,
+    /// patch an RFC 6902 JSON Patch turning the old components (in unisbom JSON format) into the new ones.
+    #[clap(long, value_enum, default_value_t = DiffFormat::Text)]
+    format: DiffFormat,
+    /// Write output to this file instead of the standard output.
+    #[clap(long)]
+    output: Option<String>,
+}
 
-    if std::env::var_os("RUST_LOG").is_none() {
-        std::env::set_var("RUST_LOG", "info");
-    }
-    pretty_env_logger::init();
+#[derive(clap::Subcommand, Debug, Clone)]
+enum Command {
+    /// Build the software bill of materials, this is the default when no command is given.
+    Scan(ScanArguments),
+    /// Compare two scans and report added, removed, upgraded and downgraded components and publisher changes.
+    /// Exits with status 1 if anything changed, 2 on errors.
+    Diff(DiffArguments),
+}
 
-    let components = collector::get()?.collect()?;
+#[derive(Parser, Debug, Clone)]
+#[clap(
+    about = "Build a software bill of materials of the current system.",
+    args_conflicts_with_subcommands = true
+)]
+struct Arguments {
+    #[clap(subcommand)]
+    command: Option<Command>,
+    #[clap(flatten)]
+    scan: ScanArguments,
+}
 
-    let output: Box<dyn std::io::Write> = match args.output {
+fn open_output(path: Option<String>) -> Result<Box<dyn std::io::Write>, Error> {
+    Ok(match path {
         None => Box::new(std::io::stdout()),
         Some(path) => {
-            log::info!("writing results to {} as {:?}", &path, args.format);
-            Box::new(
-                File::create(&path)
-                    .map_err(|e| format!("can't open {} for writing: {:?}", &path, e))?,
-            )
+            log::info!("writing results to {}", &path);
+            Box::new(File::create(&path).map_err(Error::Write)?)
         }
-    };
+    })
+}
+
+fn scan(args: ScanArguments) -> Result<(), Error> {
+    let mut scanner = Scanner::new();
+    if let Some(root) = &args.root {
+        scanner = scanner.root(root);
+    }
+    if !args.collectors.is_empty() {
+        let collectors: Vec<CollectorKind> =
+            args.collectors.iter().map(|name| (*name).into()).collect();
+        scanner = scanner.collectors(&collectors);
+    }
+    if !args.paths.is_empty() {
+        scanner = scanner.paths(&args.paths);
+    }
+    if !args.include.is_empty() {
+        scanner = scanner.include(&args.include);
+    }
+    if !args.exclude.is_empty() {
+        scanner = scanner.exclude(&args.exclude);
+    }
+    if let Some(max_depth) = args.max_depth {
+        scanner = scanner.max_depth(max_depth);
+    }
+    if !args.hashes.is_empty() {
+        let algorithms: Vec<HashAlgorithm> =
+            args.hashes.iter().map(|name| (*name).into()).collect();
+        scanner = scanner.hashes(&algorithms);
+    }
+    if let (Some(input), Some(input_type)) = (&args.input, args.input_type) {
+        scanner = scanner.input(input_type.into(), input);
+    }
+    if args.vulns {
+        if let Some(osv_db) = &args.osv_db {
+            scanner = scanner.osv_database(osv_db);
+        }
+    }
+
+    let sbom = scanner.scan()?;
+
+    log::debug!("output format is {:?}", args.format);
+    let output = open_output(args.output)?;
 
     match args.format {
-        OutputFormat::Text => format::to_text(components, output)?,
-        OutputFormat::Json => format::to_json(components, output)?,
+        OutputFormat::Text => format::to_text(&sbom, output)?,
+        OutputFormat::Json => format::to_json(&sbom, output)?,
+        OutputFormat::CyclonedxJson => format::cyclonedx::to_json(&sbom, output)?,
+        OutputFormat::SpdxJson => format::spdx::to_json(&sbom, output)?,
+        OutputFormat::SpdxTv => format::spdx::to_tag_value(&sbom, output)?,
     }
 
     Ok(())
 }
+
+// returns true if the scans are different
+fn diff(args: DiffArguments) -> Result<bool, Error> {
+    let old = format::load(Path::new(&args.old))?.components;
+    let new = format::load(Path::new(&args.new))?.components;
+
+    log::info!(
+        "comparing {} components of {} with {} components of {} ...",
+        old.len(),
+        &args.old,
+        new.len(),
+        &args.new
+    );
+
+    let changes = diff::compare(&old, &new);
+    let output = open_output(args.output)?;
+
+    match args.format {
+        DiffFormat::Text => diff::to_text(&changes, output)?,
+        DiffFormat::Json => diff::to_json(&changes, output)?,
+        DiffFormat::Patch => diff::to_patch(&changes, output)?,
+    }
+
+    Ok(!changes.is_empty())
+}
+
+fn main() {
+    let args = Arguments::parse();
+
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "info");
+    }
+    pretty_env_logger::init();
+
+    let result = match args.command.unwrap_or(Command::Scan(args.scan)) {
+        Command::Scan(args) => scan(args).map(|_| false),
+        Command::Diff(args) => diff(args),
+    };
+
+    // same convention of diff(1): 1 if the scans are different, 2 on errors
+    match result {
+        Ok(false) => {}
+        Ok(true) => std::process::exit(1),
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    }
+}
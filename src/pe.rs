@@ -0,0 +1,737 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::binary::{self, SectionHeader};
+use crate::Error;
+
+// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format
+const DOS_MAGIC: &[u8] = b"MZ";
+const PE_MAGIC: &[u8] = b"PE\0\0";
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const COFF_HEADER_SIZE: usize = 20;
+const IMAGE_FILE_DLL: u16 = 0x2000;
+const SECTION_HEADER_SIZE: usize = 40;
+// the DOS stub and the headers that follow it usually fit, the section table can be further
+const HEADERS_SIZE: u64 = 4096;
+const RESOURCE_DIRECTORY: usize = 2;
+const RT_VERSION: u32 = 16;
+// https://learn.microsoft.com/en-us/windows/win32/api/verrsrc/ns-verrsrc-vs_fixedfileinfo
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef04bd;
+const FIXED_FILE_INFO_SIZE: usize = 52;
+// VS_VERSIONINFO > StringFileInfo > string table > string, deeper blocks are not parsed
+const MAX_BLOCK_DEPTH: usize = 8;
+
+/// The versions of the VS_FIXEDFILEINFO structure of a version resource.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct FixedFileInfo {
+    pub file_version: [u16; 4],
+    pub product_version: [u16; 4],
+}
+
+/// Parsed VS_VERSIONINFO resource.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct VersionInfo {
+    pub fixed: Option<FixedFileInfo>,
+    // entries of the first StringFileInfo table
+    pub strings: HashMap<String, String>,
+}
+
+fn format_version(parts: &[u16; 4]) -> String {
+    format!("{}.{}.{}.{}", parts[0], parts[1], parts[2], parts[3])
+}
+
+impl VersionInfo {
+    fn string(&self, key: &str) -> Option<&str> {
+        self.strings
+            .get(key)
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn company_name(&self) -> Option<&str> {
+        self.string("CompanyName")
+    }
+
+    pub fn product_name(&self) -> Option<&str> {
+        self.string("ProductName")
+    }
+
+    pub fn file_description(&self) -> Option<&str> {
+        self.string("FileDescription")
+    }
+
+    pub fn original_filename(&self) -> Option<&str> {
+        self.string("OriginalFilename")
+    }
+
+    /// Product version from the fixed info, or from the string table if missing.
+    pub fn product_version(&self) -> Option<String> {
+        match &self.fixed {
+            Some(fixed) => Some(format_version(&fixed.product_version)),
+            None => self.string("ProductVersion").map(|v| v.to_owned()),
+        }
+    }
+
+    /// File version from the fixed info, or from the string table if missing.
+    pub fn file_version(&self) -> Option<String> {
+        match &self.fixed {
+            Some(fixed) => Some(format_version(&fixed.file_version)),
+            None => self.string("FileVersion").map(|v| v.to_owned()),
+        }
+    }
+}
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("PE image", message)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Returns true if the data starts with the DOS header of a PE image.
+pub(crate) fn is_pe(data: &[u8]) -> bool {
+    data.starts_with(DOS_MAGIC)
+        && u32_at(data, 0x3c)
+            .and_then(|offset| data.get(offset as usize..offset as usize + 4))
+            .is_some_and(|magic| magic == PE_MAGIC)
+}
+
+/// Returns true if the header is the one of a PE image that is not a DLL.
+pub(crate) fn is_executable(header: &[u8]) -> bool {
+    is_pe(header)
+        && u32_at(header, 0x3c)
+            .and_then(|offset| u16_at(header, offset as usize + 4 + 18))
+            .is_some_and(|characteristics| characteristics & IMAGE_FILE_DLL == 0)
+}
+
+#[derive(Clone)]
+struct Section {
+    name: String,
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    image_base: u64,
+    sections: Vec<Section>,
+    resources_rva: u32,
+}
+
+impl<'a> Image<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if !data.starts_with(DOS_MAGIC) {
+            return Err(malformed("not a PE file: missing MZ signature"));
+        }
+
+        let pe_offset =
+            u32_at(data, 0x3c).ok_or_else(|| malformed("truncated DOS header"))? as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(PE_MAGIC) {
+            return Err(malformed("not a PE file: missing PE signature"));
+        }
+
+        let coff = pe_offset + 4;
+        let num_sections =
+            u16_at(data, coff + 2).ok_or_else(|| malformed("truncated COFF header"))? as usize;
+        let optional_size =
+            u16_at(data, coff + 16).ok_or_else(|| malformed("truncated COFF header"))? as usize;
+        let optional = coff + COFF_HEADER_SIZE;
+
+        let (num_dirs_offset, dirs_offset, image_base) =
+            match u16_at(data, optional).ok_or_else(|| malformed("truncated optional header"))? {
+                PE32_MAGIC => (92, 96, u32_at(data, optional + 28).map(|base| base as u64)),
+                PE32_PLUS_MAGIC => (
+                    108,
+                    112,
+                    u32_at(data, optional + 24)
+                        .zip(u32_at(data, optional + 28))
+                        .map(|(low, high)| ((high as u64) << 32) | low as u64),
+                ),
+                magic => {
+                    return Err(malformed(format!(
+                        "unsupported optional header magic {:#x}",
+                        magic
+                    )))
+                }
+            };
+
+        let num_dirs = u32_at(data, optional + num_dirs_offset)
+            .ok_or_else(|| malformed("truncated optional header"))? as usize;
+        let resources_rva = if num_dirs > RESOURCE_DIRECTORY {
+            u32_at(data, optional + dirs_offset + RESOURCE_DIRECTORY * 8)
+                .ok_or_else(|| malformed("truncated data directories"))?
+        } else {
+            0
+        };
+
+        let mut sections = vec![];
+        let table = optional + optional_size;
+        for idx in 0..num_sections {
+            let header = table + idx * SECTION_HEADER_SIZE;
+            let name = data
+                .get(header..header + 8)
+                .ok_or_else(|| malformed("truncated section table"))?;
+            sections.push(Section {
+                name: String::from_utf8_lossy(name)
+                    .trim_end_matches('\0')
+                    .to_owned(),
+                virtual_size: u32_at(data, header + 8)
+                    .ok_or_else(|| malformed("truncated section table"))?,
+                virtual_address: u32_at(data, header + 12)
+                    .ok_or_else(|| malformed("truncated section table"))?,
+                raw_size: u32_at(data, header + 16)
+                    .ok_or_else(|| malformed("truncated section table"))?,
+                raw_offset: u32_at(data, header + 20)
+                    .ok_or_else(|| malformed("truncated section table"))?,
+            });
+        }
+
+        Ok(Self {
+            data,
+            image_base: image_base.ok_or_else(|| malformed("truncated optional header"))?,
+            sections,
+            resources_rva,
+        })
+    }
+
+    fn section_of(&self, rva: u32) -> Option<&Section> {
+        let rva = rva as u64;
+        self.sections.iter().find(|s| {
+            let start = s.virtual_address as u64;
+            rva >= start && rva < start + s.virtual_size.max(s.raw_size) as u64
+        })
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        self.section_of(rva)
+            .map(|s| (rva as u64 - s.virtual_address as u64 + s.raw_offset as u64) as usize)
+    }
+
+    fn resource_section(&self) -> Result<&Section, Error> {
+        if self.resources_rva == 0 {
+            return Err(malformed("no resource directory"));
+        }
+        self.section_of(self.resources_rva)
+            .ok_or_else(|| malformed("resource directory outside of any section"))
+    }
+
+    // returns the offset of the entry with the given id, or of the first one if id is None
+    fn find_entry(&self, directory: usize, id: Option<u32>) -> Option<u32> {
+        let named = u16_at(self.data, directory + 12)? as usize;
+        let ids = u16_at(self.data, directory + 14)? as usize;
+
+        (0..named + ids)
+            .map(|idx| directory + 16 + idx * 8)
+            .find(|entry| match id {
+                Some(id) => u32_at(self.data, *entry) == Some(id),
+                None => true,
+            })
+            .and_then(|entry| u32_at(self.data, entry + 4))
+    }
+
+    fn version_resource(&self) -> Result<&'a [u8], Error> {
+        let section = self.resource_section()?;
+        let base = (self.resources_rva as u64 - section.virtual_address as u64
+            + section.raw_offset as u64) as usize;
+
+        // type -> name -> language -> data entry
+        let mut offset = base;
+        for (level, id) in [Some(RT_VERSION), None, None].iter().enumerate() {
+            let entry = self
+                .find_entry(offset, *id)
+                .ok_or_else(|| malformed(format!("no version resource (level {})", level)))?;
+            let is_directory = entry & 0x8000_0000 != 0;
+            if is_directory == (level == 2) {
+                return Err(malformed(format!(
+                    "malformed resource directory at level {}",
+                    level
+                )));
+            }
+            offset = base + (entry & 0x7fff_ffff) as usize;
+        }
+
+        let data_rva =
+            u32_at(self.data, offset).ok_or_else(|| malformed("truncated resource data entry"))?;
+        let size = u32_at(self.data, offset + 4)
+            .ok_or_else(|| malformed("truncated resource data entry"))? as usize;
+        let start = self
+            .rva_to_offset(data_rva)
+            .ok_or_else(|| malformed("version resource outside of any section"))?;
+
+        self.data
+            .get(start..start + size)
+            .ok_or_else(|| malformed("truncated version resource"))
+    }
+}
+
+// version resources are a tree of blocks with the same layout:
+// wLength, wValueLength, wType, szKey, padding, Value, padding, Children
+struct Block<'a> {
+    key: String,
+    // text values have their length expressed in words instead of bytes
+    is_text: bool,
+    value: &'a [u8],
+    children: Vec<Block<'a>>,
+}
+
+// the children are parsed within the block, up to MAX_BLOCK_DEPTH levels
+fn parse_block(data: &[u8], offset: usize, depth: usize) -> Option<(Block<'_>, usize)> {
+    let length = u16_at(data, offset)? as usize;
+    let value_length = u16_at(data, offset + 2)? as usize;
+    let is_text = u16_at(data, offset + 4)? == 1;
+    let end = offset + length;
+    if length < 6 || end > data.len() {
+        return None;
+    }
+
+    let mut key = vec![];
+    let mut pos = offset + 6;
+    while pos + 2 <= end {
+        let c = u16_at(data, pos)?;
+        pos += 2;
+        if c == 0 {
+            break;
+        }
+        key.push(c);
+    }
+
+    let value_start = align4(pos).min(end);
+    let value_size = if is_text {
+        value_length * 2
+    } else {
+        value_length
+    };
+    let value_end = (value_start + value_size).min(end);
+    let value = &data[value_start..value_end];
+
+    let mut children = vec![];
+    let mut child = align4(value_end);
+    while child < end && depth < MAX_BLOCK_DEPTH {
+        match parse_block(&data[..end], child, depth + 1) {
+            Some((block, next)) => {
+                children.push(block);
+                child = next;
+            }
+            None => break,
+        }
+    }
+
+    Some((
+        Block {
+            key: String::from_utf16_lossy(&key),
+            is_text,
+            value,
+            children,
+        },
+        align4(end),
+    ))
+}
+
+fn utf16_string(data: &[u8]) -> String {
+    let words: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    String::from_utf16_lossy(&words)
+}
+
+fn parse_fixed_file_info(data: &[u8]) -> Option<FixedFileInfo> {
+    if data.len() < FIXED_FILE_INFO_SIZE || u32_at(data, 0)? != FIXED_FILE_INFO_SIGNATURE {
+        return None;
+    }
+
+    let split = |offset: usize| -> Option<[u16; 2]> {
+        let value = u32_at(data, offset)?;
+        Some([(value >> 16) as u16, (value & 0xffff) as u16])
+    };
+    let version = |offset: usize| -> Option<[u16; 4]> {
+        let (ms, ls) = (split(offset)?, split(offset + 4)?);
+        Some([ms[0], ms[1], ls[0], ls[1]])
+    };
+
+    Some(FixedFileInfo {
+        file_version: version(8)?,
+        product_version: version(16)?,
+    })
+}
+
+fn read<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+    binary::read_at(reader, offset, size).map_err(malformed)
+}
+
+// the end of the section table, None if the headers are truncated
+fn headers_end(data: &[u8]) -> Option<u64> {
+    let coff = u32_at(data, 0x3c)? as u64 + 4;
+    let num_sections = u16_at(data, coff as usize + 2)? as u64;
+    let optional_size = u16_at(data, coff as usize + 16)? as u64;
+    Some(coff + COFF_HEADER_SIZE as u64 + optional_size + num_sections * SECTION_HEADER_SIZE as u64)
+}
+
+// the headers up to the end of the section table, without the sections
+fn read_headers<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let headers = read(reader, 0, HEADERS_SIZE)?;
+    match headers_end(&headers) {
+        Some(end) if end > headers.len() as u64 => read(reader, 0, end),
+        _ => Ok(headers),
+    }
+}
+
+// only the headers and the section holding the resources are read
+fn read_version_info_from<R: Read + Seek>(reader: &mut R) -> Result<VersionInfo, Error> {
+    let headers = read_headers(reader)?;
+    let image = Image::parse(&headers)?;
+    let section = image.resource_section()?;
+
+    // the resources are looked up in the section alone, the offsets are relative to its start
+    let data = read(reader, section.raw_offset as u64, section.raw_size as u64)?;
+    let resources = Image {
+        data: &data,
+        image_base: image.image_base,
+        sections: vec![Section {
+            raw_offset: 0,
+            ..section.clone()
+        }],
+        resources_rva: image.resources_rva,
+    };
+
+    parse_version_resource(resources.version_resource()?)
+}
+
+fn parse_version_resource(resource: &[u8]) -> Result<VersionInfo, Error> {
+    let (root, _) =
+        parse_block(resource, 0, 0).ok_or_else(|| malformed("malformed version resource"))?;
+    if root.key != "VS_VERSION_INFO" {
+        return Err(malformed(format!(
+            "unexpected version resource key '{}'",
+            root.key
+        )));
+    }
+
+    let mut info = VersionInfo {
+        fixed: parse_fixed_file_info(root.value),
+        strings: HashMap::new(),
+    };
+
+    if let Some(table) = root
+        .children
+        .iter()
+        .find(|block| block.key == "StringFileInfo")
+        .and_then(|block| block.children.first())
+    {
+        for string in &table.children {
+            let value = if string.is_text {
+                utf16_string(string.value)
+            } else {
+                String::from_utf8_lossy(string.value).into_owned()
+            };
+            info.strings.insert(string.key.clone(), value);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Returns where the sections of a PE image are, their address includes the image base. Only
+/// the headers are read.
+pub(crate) fn section_headers<R: Read + Seek>(reader: &mut R) -> Result<Vec<SectionHeader>, Error> {
+    let headers = read_headers(reader)?;
+    let image = Image::parse(&headers)?;
+
+    Ok(image
+        .sections
+        .iter()
+        .map(|section| SectionHeader {
+            name: section.name.clone(),
+            address: image.image_base + section.virtual_address as u64,
+            offset: section.raw_offset as u64,
+            // the raw data is padded to the file alignment, the virtual size is 0 in object files
+            size: match section.virtual_size {
+                0 => section.raw_size,
+                virtual_size => section.raw_size.min(virtual_size),
+            } as u64,
+        })
+        .collect())
+}
+
+/// Reads the version information of a PE file (executable, library, driver).
+pub(crate) fn read_version_info(path: &Path) -> Result<VersionInfo, Error> {
+    let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+    read_version_info_from(&mut file).map_err(|e| Error::parse(path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const PE_OFFSET: usize = 0x40;
+    const OPTIONAL_HEADER_SIZE: usize = 240;
+    const RAW_OFFSET: usize = 0x200;
+    const RESOURCES_RVA: u32 = 0x1000;
+    // after the three levels of directories and the data entry
+    const VERSION_OFFSET: usize = 0x58;
+
+    fn parse_version_info(data: &[u8]) -> Result<VersionInfo, Error> {
+        read_version_info_from(&mut Cursor::new(data))
+    }
+
+    fn put_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .chain([0])
+            .flat_map(|c| c.to_le_bytes())
+            .collect()
+    }
+
+    fn pad(data: &mut Vec<u8>) {
+        data.resize(align4(data.len()), 0);
+    }
+
+    fn block(key: &str, value: &[u8], is_text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0; 6];
+        data.extend(utf16(key));
+        pad(&mut data);
+        data.extend_from_slice(value);
+        for child in children {
+            pad(&mut data);
+            data.extend_from_slice(child);
+        }
+
+        let value_length = if is_text {
+            value.len() / 2
+        } else {
+            value.len()
+        };
+        let length = data.len();
+        put_u16(&mut data, 0, length as u16);
+        put_u16(&mut data, 2, value_length as u16);
+        put_u16(&mut data, 4, is_text as u16);
+        data
+    }
+
+    fn fixed_file_info(file_version: [u16; 4], product_version: [u16; 4]) -> Vec<u8> {
+        let mut data = vec![0; FIXED_FILE_INFO_SIZE];
+        put_u32(&mut data, 0, FIXED_FILE_INFO_SIGNATURE);
+        for (offset, version) in [(8, file_version), (16, product_version)] {
+            put_u32(
+                &mut data,
+                offset,
+                (version[0] as u32) << 16 | version[1] as u32,
+            );
+            put_u32(
+                &mut data,
+                offset + 4,
+                (version[2] as u32) << 16 | version[3] as u32,
+            );
+        }
+        data
+    }
+
+    fn version_info(fixed: &[u8], strings: &[(&str, &str)]) -> Vec<u8> {
+        let strings: Vec<Vec<u8>> = strings
+            .iter()
+            .map(|(key, value)| block(key, &utf16(value), true, &[]))
+            .collect();
+        let table = block("040904b0", &[], true, &strings);
+        let string_file_info = block("StringFileInfo", &[], true, &[table]);
+        block("VS_VERSION_INFO", fixed, false, &[string_file_info])
+    }
+
+    // a PE32+ image with a single .rsrc section holding the version resource
+    fn image(version: &[u8]) -> Vec<u8> {
+        let mut rsrc = vec![0; VERSION_OFFSET];
+        // type, name and language directories with a single entry each
+        for (level, (id, entry)) in [(RT_VERSION, 0x8000_0018), (1, 0x8000_0030), (0x409, 0x48)]
+            .into_iter()
+            .enumerate()
+        {
+            let directory = level * 0x18;
+            put_u16(&mut rsrc, directory + 14, 1);
+            put_u32(&mut rsrc, directory + 16, id);
+            put_u32(&mut rsrc, directory + 20, entry);
+        }
+        put_u32(&mut rsrc, 0x48, RESOURCES_RVA + VERSION_OFFSET as u32);
+        put_u32(&mut rsrc, 0x4c, version.len() as u32);
+        rsrc.extend_from_slice(version);
+
+        let mut data = vec![0; RAW_OFFSET];
+        data[..2].copy_from_slice(DOS_MAGIC);
+        put_u32(&mut data, 0x3c, PE_OFFSET as u32);
+        data[PE_OFFSET..PE_OFFSET + 4].copy_from_slice(PE_MAGIC);
+
+        let coff = PE_OFFSET + 4;
+        put_u16(&mut data, coff, 0x8664);
+        put_u16(&mut data, coff + 2, 1);
+        put_u16(&mut data, coff + 16, OPTIONAL_HEADER_SIZE as u16);
+
+        let optional = coff + COFF_HEADER_SIZE;
+        put_u16(&mut data, optional, PE32_PLUS_MAGIC);
+        put_u32(&mut data, optional + 24, 0x4000_0000);
+        put_u32(&mut data, optional + 108, 16);
+        put_u32(
+            &mut data,
+            optional + 112 + RESOURCE_DIRECTORY * 8,
+            RESOURCES_RVA,
+        );
+
+        let section = optional + OPTIONAL_HEADER_SIZE;
+        data[section..section + 5].copy_from_slice(b".rsrc");
+        put_u32(&mut data, section + 8, rsrc.len() as u32);
+        put_u32(&mut data, section + 12, RESOURCES_RVA);
+        put_u32(&mut data, section + 16, rsrc.len() as u32);
+        put_u32(&mut data, section + 20, RAW_OFFSET as u32);
+
+        data.extend(rsrc);
+        data
+    }
+
+    fn driver() -> Vec<u8> {
+        image(&version_info(
+            &fixed_file_info([10, 0, 19041, 1001], [10, 0, 19041, 1]),
+            &[
+                ("CompanyName", "Contoso Ltd. "),
+                ("ProductName", "Contoso Filter"),
+                ("FileDescription", "Contoso filter driver"),
+                ("OriginalFilename", "contoso.sys"),
+                ("ProductVersion", "10.0 (ignored)"),
+            ],
+        ))
+    }
+
+    #[test]
+    fn parses_version_info() {
+        let data = driver();
+        assert!(is_pe(&data));
+        assert!(is_executable(&data));
+
+        let info = parse_version_info(&data).unwrap();
+        assert_eq!(info.product_version().as_deref(), Some("10.0.19041.1"));
+        assert_eq!(info.file_version().as_deref(), Some("10.0.19041.1001"));
+        assert_eq!(info.company_name(), Some("Contoso Ltd."));
+        assert_eq!(info.product_name(), Some("Contoso Filter"));
+        assert_eq!(info.file_description(), Some("Contoso filter driver"));
+        assert_eq!(info.original_filename(), Some("contoso.sys"));
+    }
+
+    #[test]
+    fn falls_back_to_version_strings() {
+        let data = image(&version_info(
+            &[],
+            &[
+                ("CompanyName", ""),
+                ("ProductVersion", "2.1"),
+                ("FileVersion", "2.1.3"),
+            ],
+        ));
+
+        let info = parse_version_info(&data).unwrap();
+        assert_eq!(info.product_version().as_deref(), Some("2.1"));
+        assert_eq!(info.file_version().as_deref(), Some("2.1.3"));
+        assert_eq!(info.company_name(), None);
+    }
+
+    #[test]
+    fn rejects_truncated_resources() {
+        let data = driver();
+
+        // the version resource runs past the end of the file
+        let truncated = &data[..data.len() - 8];
+        assert!(parse_version_info(truncated).is_err());
+
+        // the data entry points outside of the section
+        let mut outside = data.clone();
+        put_u32(&mut outside, RAW_OFFSET + 0x48, 0x8000);
+        assert!(parse_version_info(&outside).is_err());
+
+        // the language level is a directory instead of a data entry
+        let mut directory = data.clone();
+        put_u32(&mut directory, RAW_OFFSET + 0x30 + 20, 0x8000_0048);
+        assert!(parse_version_info(&directory).is_err());
+
+        // no version resource in the type directory
+        let mut missing = data.clone();
+        put_u32(&mut missing, RAW_OFFSET + 16, 3);
+        assert!(parse_version_info(&missing).is_err());
+
+        // a block longer than the resource
+        let mut block = data.clone();
+        put_u16(&mut block, RAW_OFFSET + VERSION_OFFSET, 0xffff);
+        assert!(parse_version_info(&block).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let data = driver();
+        assert!(parse_version_info(&data[..0x50]).is_err());
+        assert!(parse_version_info(&data[..PE_OFFSET + 4 + COFF_HEADER_SIZE + 100]).is_err());
+        assert!(parse_version_info(b"MZ").is_err());
+
+        // no resource directory
+        let mut no_resources = data.clone();
+        put_u32(&mut no_resources, PE_OFFSET + 4 + COFF_HEADER_SIZE + 108, 2);
+        assert!(parse_version_info(&no_resources).is_err());
+    }
+
+    fn depth(block: &Block) -> usize {
+        1 + block.children.iter().map(depth).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn limits_nested_blocks() {
+        // each block holds the next one, far deeper than any real resource
+        let mut nested = block("", &[], false, &[]);
+        for _ in 0..4000 {
+            nested = block("", &[], false, &[nested]);
+        }
+        let (root, _) = parse_block(&nested, 0, 0).unwrap();
+        assert_eq!(depth(&root), MAX_BLOCK_DEPTH + 1);
+
+        let info = parse_version_info(&image(&block(
+            "VS_VERSION_INFO",
+            &fixed_file_info([1, 2, 3, 4], [1, 2, 3, 4]),
+            false,
+            &[nested],
+        )))
+        .unwrap();
+        assert_eq!(info.file_version().as_deref(), Some("1.2.3.4"));
+    }
+
+    #[test]
+    fn keeps_children_in_their_parent() {
+        let child = block("child", &[], false, &[]);
+        let mut parent = block("parent", &[], false, &[child.clone(), child]);
+        // the parent ends before its last child, which still fits in the data
+        let parent_len = parent.len() - 4;
+        put_u16(&mut parent, 0, parent_len as u16);
+
+        let (root, next) = parse_block(&parent, 0, 0).unwrap();
+        assert_eq!(next, parent_len);
+        let keys: Vec<&str> = root.children.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["child"]);
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Error;
+
+// https://github.com/package-url/purl-spec/blob/master/PURL-SPECIFICATION.rst
+const SCHEME: &str = "pkg";
+
+/// A package URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Purl {
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+    pub qualifiers: BTreeMap<String, String>,
+    pub subpath: Option<String>,
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'.' || b == b'-' || b == b'_' || b == b'~'
+}
+
+fn encode(s: &str, allowed: &[u8]) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if is_unreserved(b) || allowed.contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
+
+fn invalid(message: String) -> Error {
+    Error::parse("package url", message)
+}
+
+fn decode(s: &str) -> Result<String, Error> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = s
+                .get(idx + 1..idx + 3)
+                .ok_or_else(|| invalid(format!("truncated percent encoding in '{}'", s)))?;
+            decoded.push(
+                u8::from_str_radix(hex, 16)
+                    .map_err(|_| invalid(format!("invalid percent encoding in '{}'", s)))?,
+            );
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid(format!("invalid utf-8 in '{}'", s)))
+}
+
+fn is_valid_type(kind: &str) -> bool {
+    !kind.is_empty()
+        && !kind.starts_with(|c: char| c.is_ascii_digit())
+        && kind
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-')
+}
+
+fn is_valid_qualifier_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+}
+
+impl Purl {
+    pub fn new(kind: &str, name: &str) -> Self {
+        let mut purl = Self {
+            kind: kind.to_lowercase(),
+            namespace: None,
+            name: name.to_owned(),
+            version: None,
+            qualifiers: BTreeMap::new(),
+            subpath: None,
+        };
+        purl.normalize();
+        purl
+    }
+
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        if !namespace.is_empty() {
+            self.namespace = Some(namespace.to_owned());
+            self.normalize();
+        }
+        self
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        if !version.is_empty() {
+            self.version = Some(version.to_owned());
+        }
+        self
+    }
+
+    pub fn with_qualifier(mut self, key: &str, value: &str) -> Self {
+        if !value.is_empty() {
+            self.qualifiers.insert(key.to_lowercase(), value.to_owned());
+        }
+        self
+    }
+
+    // type specific rules, only for the types we generate
+    fn normalize(&mut self) {
+        match self.kind.as_str() {
+            "alpm" | "apk" | "deb" | "npm" => {
+                self.name = self.name.to_lowercase();
+                self.namespace = self.namespace.as_ref().map(|ns| ns.to_lowercase());
+            }
+            "pypi" => self.name = self.name.to_lowercase().replace('_', "-"),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Purl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}/", SCHEME, self.kind)?;
+
+        if let Some(namespace) = &self.namespace {
+            let segments: Vec<String> = namespace
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(|s| encode(s, b":"))
+                .collect();
+            if !segments.is_empty() {
+                write!(f, "{}/", segments.join("/"))?;
+            }
+        }
+
+        write!(f, "{}", encode(&self.name, b":"))?;
+
+        if let Some(version) = &self.version {
+            write!(f, "@{}", encode(version, b":"))?;
+        }
+
+        if !self.qualifiers.is_empty() {
+            let qualifiers: Vec<String> = self
+                .qualifiers
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, encode(value, b":/")))
+                .collect();
+            write!(f, "?{}", qualifiers.join("&"))?;
+        }
+
+        if let Some(subpath) = &self.subpath {
+            let segments: Vec<String> = subpath
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(|s| encode(s, b":"))
+                .collect();
+            write!(f, "#{}", segments.join("/"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Purl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (remainder, subpath) = match s.rsplit_once('#') {
+            Some((remainder, subpath)) => {
+                let segments = subpath
+                    .split('/')
+                    .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+                    .map(decode)
+                    .collect::<Result<Vec<String>, Error>>()?;
+                (
+                    remainder,
+                    Some(segments.join("/")).filter(|s| !s.is_empty()),
+                )
+            }
+            None => (s, None),
+        };
+
+        let (remainder, qualifiers) = match remainder.rsplit_once('?') {
+            Some((remainder, raw)) => {
+                let mut qualifiers = BTreeMap::new();
+                for pair in raw.split('&').filter(|p| !p.is_empty()) {
+                    let (key, value) = pair.split_once('=').ok_or_else(|| {
+                        invalid(format!("invalid qualifier '{}' in '{}'", pair, s))
+                    })?;
+                    let key = key.to_lowercase();
+                    if !is_valid_qualifier_key(&key) {
+                        return Err(invalid(format!(
+                            "invalid qualifier key '{}' in '{}'",
+                            key, s
+                        )));
+                    }
+                    let value = decode(value)?;
+                    if !value.is_empty() {
+                        qualifiers.insert(key, value);
+                    }
+                }
+                (remainder, qualifiers)
+            }
+            None => (remainder, BTreeMap::new()),
+        };
+
+        let (scheme, remainder) = remainder
+            .split_once(':')
+            .ok_or_else(|| invalid(format!("missing scheme in '{}'", s)))?;
+        if scheme != SCHEME {
+            return Err(invalid(format!("invalid scheme '{}' in '{}'", scheme, s)));
+        }
+
+        let remainder = remainder.trim_start_matches('/');
+        let (kind, remainder) = remainder
+            .split_once('/')
+            .ok_or_else(|| invalid(format!("missing type in '{}'", s)))?;
+        let kind = kind.to_lowercase();
+        if !is_valid_type(&kind) {
+            return Err(invalid(format!("invalid type '{}' in '{}'", kind, s)));
+        }
+
+        let remainder = remainder.trim_end_matches('/');
+        // the version is after the name, an @ before it is an unencoded npm scope
+        let name_start = remainder.rfind('/').map_or(0, |idx| idx + 1);
+        let (remainder, version) = match remainder[name_start..].rfind('@') {
+            Some(idx) => (
+                &remainder[..name_start + idx],
+                Some(decode(&remainder[name_start + idx + 1..])?),
+            ),
+            None => (remainder, None),
+        };
+
+        let (namespace, name) = match remainder.rsplit_once('/') {
+            Some((namespace, name)) => {
+                let segments = namespace
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(decode)
+                    .collect::<Result<Vec<String>, Error>>()?;
+                (Some(segments.join("/")).filter(|s| !s.is_empty()), name)
+            }
+            None => (None, remainder),
+        };
+
+        let name = decode(name)?;
+        if name.is_empty() {
+            return Err(invalid(format!("missing name in '{}'", s)));
+        }
+
+        let mut purl = Self {
+            kind,
+            namespace,
+            name,
+            version,
+            qualifiers,
+            subpath,
+        };
+        purl.normalize();
+
+        Ok(purl)
+    }
+}
+
+impl Serialize for Purl {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Purl {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(purl: Purl, expected: &str) {
+        assert_eq!(purl.to_string(), expected);
+        assert_eq!(expected.parse::<Purl>().unwrap(), purl);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(
+            Purl::new("deb", "libssl3")
+                .with_namespace("debian")
+                .with_version("3.0.11-1~deb12u2")
+                .with_qualifier("arch", "amd64")
+                .with_qualifier("distro", "debian-12"),
+            "pkg:deb/debian/libssl3@3.0.11-1~deb12u2?arch=amd64&distro=debian-12",
+        );
+        round_trip(
+            Purl::new("npm", "core")
+                .with_namespace("@angular")
+                .with_version("16.2.0"),
+            "pkg:npm/%40angular/core@16.2.0",
+        );
+        round_trip(
+            Purl::new("golang", "protobuf")
+                .with_namespace("google.golang.org")
+                .with_version("v1.31.0"),
+            "pkg:golang/google.golang.org/protobuf@v1.31.0",
+        );
+        round_trip(
+            Purl::new("generic", "kernel").with_version("6.1.0+deb12"),
+            "pkg:generic/kernel@6.1.0%2Bdeb12",
+        );
+        round_trip(
+            Purl::new("generic", "app")
+                .with_qualifier("download_url", "https://example.com/a b.tar.gz")
+                .with_qualifier("checksum", "sha256:00ff"),
+            "pkg:generic/app?checksum=sha256:00ff&download_url=https://example.com/a%20b.tar.gz",
+        );
+
+        let mut purl = Purl::new("github", "unisbom").with_namespace("example");
+        purl.subpath = Some("src/lang".to_owned());
+        round_trip(purl, "pkg:github/example/unisbom#src/lang");
+    }
+
+    #[test]
+    fn parses_unencoded_input() {
+        let purl: Purl = "pkg:npm/@angular/core".parse().unwrap();
+        assert_eq!(purl.namespace.as_deref(), Some("@angular"));
+        assert_eq!(purl.name, "core");
+        assert_eq!(purl.version, None);
+
+        let purl: Purl = "pkg:npm/@angular/core@16.2.0".parse().unwrap();
+        assert_eq!(purl.namespace.as_deref(), Some("@angular"));
+        assert_eq!(purl.version.as_deref(), Some("16.2.0"));
+
+        let purl: Purl = "pkg:generic/kernel@6.1.0+deb12#/./a/../b/".parse().unwrap();
+        assert_eq!(purl.version.as_deref(), Some("6.1.0+deb12"));
+        assert_eq!(purl.subpath.as_deref(), Some("a/b"));
+
+        let purl: Purl = "pkg:PyPI/Foo_Bar@1.0?Arch=".parse().unwrap();
+        assert_eq!(purl.to_string(), "pkg:pypi/foo-bar@1.0");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for s in [
+            "npm/left-pad",
+            "http:npm/left-pad",
+            "pkg:npm",
+            "pkg:1npm/left-pad",
+            "pkg:npm/",
+            "pkg:npm/@1.0",
+            "pkg:npm/left-pad?arch",
+            "pkg:npm/left-pad?1arch=x",
+            "pkg:npm/left-pad%2",
+            "pkg:npm/left-pad%zz",
+            "pkg:npm/left-pad%ff",
+        ] {
+            assert!(s.parse::<Purl>().is_err(), "{}", s);
+        }
+    }
+}
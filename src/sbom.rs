@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::component::Component;
+
+/// A non-fatal problem of a collector, the data it refers to is missing from the results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub collector: String,
+    pub message: String,
+}
+
+/// The result of a scan.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sbom {
+    /// Name of the scanned system.
+    #[serde(default)]
+    pub host: String,
+    pub components: Vec<Component>,
+    /// Problems that prevented collecting some of the components.
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+}
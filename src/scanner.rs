@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use crate::collector::{self, CollectorKind, Context, Input};
+use crate::component::Component;
+use crate::graph;
+use crate::hashes::{self, HashAlgorithm};
+use crate::sbom::Sbom;
+use crate::utils;
+use crate::vulns;
+use crate::Error;
+
+/// Builds the software bill of materials of a system.
+///
+/// ```no_run
+/// use unisbom::{CollectorKind, Scanner};
+///
+/// let scanner = Scanner::new()
+///     .root("/mnt/rootfs")
+///     .collectors(&[CollectorKind::Dpkg, CollectorKind::Rpm]);
+///
+/// let sbom = scanner.scan()?;
+/// unisbom::format::cyclonedx::to_json(&sbom, std::io::stdout())?;
+/// # Ok::<(), unisbom::Error>(())
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Scanner {
+    root: Option<PathBuf>,
+    collectors: Option<Vec<CollectorKind>>,
+    paths: Option<Vec<String>>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_depth: Option<usize>,
+    hashes: Vec<HashAlgorithm>,
+    input: Option<(Input, PathBuf)>,
+    osv_db: Option<PathBuf>,
+}
+
+impl Scanner {
+    /// Creates a scanner of the running system using all the supported collectors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inspects the filesystem mounted at this path (container rootfs, chroot, disk image) instead of the running system.
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Only uses the given collectors.
+    pub fn collectors(mut self, collectors: &[CollectorKind]) -> Self {
+        self.collectors = Some(collectors.to_vec());
+        self
+    }
+
+    /// Searches these folders of the inspected system for language packages (such as python
+    /// site-packages) and executables instead of the default install locations.
+    pub fn paths<P: AsRef<Path>>(mut self, paths: &[P]) -> Self {
+        self.paths = Some(
+            paths
+                .iter()
+                .map(|path| path.as_ref().to_string_lossy().into_owned())
+                .collect(),
+        );
+        self
+    }
+
+    /// Only reports the executables whose path matches one of these glob patterns, such as
+    /// `/opt/**/bin/*`.
+    pub fn include<S: AsRef<str>>(mut self, patterns: &[S]) -> Self {
+        self.include = patterns.iter().map(|p| p.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Skips the files and folders whose path matches one of these glob patterns while searching
+    /// for language packages and executables.
+    pub fn exclude<S: AsRef<str>>(mut self, patterns: &[S]) -> Self {
+        self.exclude = patterns.iter().map(|p| p.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Limits how deep the folders are searched for language packages and executables.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Hashes the file of each component, or the whole directory of `.app` and `.kext` bundles,
+    /// with these algorithms.
+    pub fn hashes(mut self, algorithms: &[HashAlgorithm]) -> Self {
+        self.hashes = vec![];
+        for algorithm in algorithms {
+            if !self.hashes.contains(algorithm) {
+                self.hashes.push(*algorithm);
+            }
+        }
+        self
+    }
+
+    /// Builds the components from a file captured on another machine instead of inspecting a system.
+    pub fn input<P: Into<PathBuf>>(mut self, input: Input, path: P) -> Self {
+        self.input = Some((input, path.into()));
+        self
+    }
+
+    /// Checks the components for known vulnerabilities using an OSV database export, either a folder
+    /// of JSON records or a zip archive.
+    pub fn osv_database<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.osv_db = Some(path.into());
+        self
+    }
+
+    fn context(&self) -> Result<Context, Error> {
+        let mut ctx = Context::new(self.root.clone())?;
+        if let Some(collectors) = &self.collectors {
+            ctx = ctx.with_collectors(collectors);
+        }
+        if let Some(paths) = &self.paths {
+            ctx = ctx.with_paths(paths);
+        }
+        if let Some(max_depth) = self.max_depth {
+            ctx = ctx.with_max_depth(max_depth);
+        }
+        ctx.with_filters(&self.include, &self.exclude)
+    }
+
+    /// Returns the name of the scanned system.
+    pub fn hostname(&self) -> String {
+        match &self.input {
+            // the captured system is only known by the name of the file
+            Some((_, path)) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            None => self.context().map(|ctx| ctx.hostname()).unwrap_or_default(),
+        }
+    }
+
+    /// Runs the collectors and returns the components found, the errors that only prevented some
+    /// of them from being found are reported as diagnostics.
+    pub fn scan(&self) -> Result<Sbom, Error> {
+        let found = match &self.input {
+            Some((input, path)) => collector::import(*input, &utils::read_text(path)?)?,
+            None => collector::get(&self.context()?)?.collect()?,
+        };
+
+        let mut components: Vec<Component> = found
+            .components
+            .iter()
+            .map(|comp| Component::from_trait(comp.as_ref()))
+            .collect();
+        graph::link(&mut components, &found.owned);
+
+        let mut diagnostics = found.diagnostics;
+        // captured data has no files to hash
+        if !self.hashes.is_empty() && self.input.is_none() {
+            diagnostics.extend(hashes::compute(
+                &self.context()?,
+                &self.hashes,
+                &mut components,
+            ));
+        }
+
+        if let Some(osv_db) = &self.osv_db {
+            vulns::Database::load(Path::new(osv_db))?.scan(&mut components);
+        }
+
+        Ok(Sbom {
+            host: self.hostname(),
+            components,
+            diagnostics,
+        })
+    }
+}
@@ -1,3 +1,25 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Error;
+
+/// Reads a text file, handling the byte order marks of UTF-8 and UTF-16 files (such as .reg exports).
+pub(crate) fn read_text(path: &Path) -> Result<String, Error> {
+    let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+
+    let utf16 = |data: &[u8], decode: fn([u8; 2]) -> u16| {
+        let words: Vec<u16> = data.chunks_exact(2).map(|c| decode([c[0], c[1]])).collect();
+        String::from_utf16_lossy(&words)
+    };
+
+    Ok(match data.as_slice() {
+        [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => String::from_utf8_lossy(&data).into_owned(),
+    })
+}
+
 #[allow(dead_code)]
 pub(crate) mod serde {
     use serde::{Deserialize, Deserializer};
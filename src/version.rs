@@ -0,0 +1,658 @@
+use std::cmp::Ordering;
+
+/// Version ordering rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scheme {
+    Semver,
+    Debian,
+    Rpm,
+    Pep440,
+    Generic,
+}
+
+impl Scheme {
+    /// Ordering used by the versions of a package URL type.
+    pub fn for_purl_type(kind: &str) -> Self {
+        match kind {
+            "deb" => Scheme::Debian,
+            // pacman's vercmp is derived from rpmvercmp
+            "rpm" | "alpm" => Scheme::Rpm,
+            "npm" | "golang" | "cargo" => Scheme::Semver,
+            "pypi" => Scheme::Pep440,
+            _ => Scheme::Generic,
+        }
+    }
+}
+
+pub(crate) fn compare(scheme: Scheme, a: &str, b: &str) -> Ordering {
+    match scheme {
+        Scheme::Semver => compare_semver(a, b),
+        Scheme::Debian => compare_debian(a, b),
+        Scheme::Rpm => compare_rpm(a, b),
+        Scheme::Pep440 => compare_pep440(a, b),
+        Scheme::Generic => compare_generic(a, b),
+    }
+}
+
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if !epoch.is_empty() && epoch.chars().all(|c| c.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or_default(), rest)
+        }
+        _ => (0, version),
+    }
+}
+
+fn compare_numeric(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+// https://manpages.debian.org/bookworm/dpkg-dev/deb-version.7.en.html
+fn debian_order(c: Option<u8>) -> i32 {
+    match c {
+        Some(b'~') => -1,
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+fn compare_debian_part(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        // non digit prefix
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let ac = debian_order(a.get(i).copied().filter(|c| !c.is_ascii_digit()));
+            let bc = debian_order(b.get(j).copied().filter(|c| !c.is_ascii_digit()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        let start_a = i;
+        while i < a.len() && a[i].is_ascii_digit() {
+            i += 1;
+        }
+        let start_b = j;
+        while j < b.len() && b[j].is_ascii_digit() {
+            j += 1;
+        }
+
+        let ord = compare_numeric(
+            std::str::from_utf8(&a[start_a..i]).unwrap_or_default(),
+            std::str::from_utf8(&b[start_b..j]).unwrap_or_default(),
+        );
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    Ordering::Equal
+}
+
+fn compare_debian(a: &str, b: &str) -> Ordering {
+    let (epoch_a, a) = split_epoch(a);
+    let (epoch_b, b) = split_epoch(b);
+
+    let (upstream_a, revision_a) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (upstream_b, revision_b) = b.rsplit_once('-').unwrap_or((b, ""));
+
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| compare_debian_part(upstream_a, upstream_b))
+        .then_with(|| compare_debian_part(revision_a, revision_b))
+}
+
+// https://github.com/rpm-software-management/rpm/blob/master/rpmio/rpmvercmp.c
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    let is_sep = |c: &u8| !c.is_ascii_alphanumeric() && *c != b'~' && *c != b'^';
+
+    loop {
+        while a.first().map(is_sep).unwrap_or(false) {
+            a = &a[1..];
+        }
+        while b.first().map(is_sep).unwrap_or(false) {
+            b = &b[1..];
+        }
+
+        // tilde sorts before everything, even the end of the version
+        if a.first() == Some(&b'~') || b.first() == Some(&b'~') {
+            if a.first() != Some(&b'~') {
+                return Ordering::Greater;
+            }
+            if b.first() != Some(&b'~') {
+                return Ordering::Less;
+            }
+            a = &a[1..];
+            b = &b[1..];
+            continue;
+        }
+
+        // caret sorts after the end of the version but before anything else
+        if a.first() == Some(&b'^') || b.first() == Some(&b'^') {
+            if a.is_empty() {
+                return Ordering::Less;
+            }
+            if b.is_empty() {
+                return Ordering::Greater;
+            }
+            if a.first() != Some(&b'^') {
+                return Ordering::Greater;
+            }
+            if b.first() != Some(&b'^') {
+                return Ordering::Less;
+            }
+            a = &a[1..];
+            b = &b[1..];
+            continue;
+        }
+
+        if a.is_empty() || b.is_empty() {
+            break;
+        }
+
+        let numeric = a[0].is_ascii_digit();
+        let segment = |s: &[u8]| {
+            s.iter()
+                .take_while(|c| {
+                    if numeric {
+                        c.is_ascii_digit()
+                    } else {
+                        c.is_ascii_alphabetic()
+                    }
+                })
+                .count()
+        };
+        let (len_a, len_b) = (segment(a), segment(b));
+
+        // segments of different types, numeric ones are newer
+        if len_b == 0 {
+            return if numeric {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+
+        let (seg_a, seg_b) = (
+            std::str::from_utf8(&a[..len_a]).unwrap_or_default(),
+            std::str::from_utf8(&b[..len_b]).unwrap_or_default(),
+        );
+        let ord = if numeric {
+            compare_numeric(seg_a, seg_b)
+        } else {
+            seg_a.cmp(seg_b)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+
+        a = &a[len_a..];
+        b = &b[len_b..];
+    }
+
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (false, _) => Ordering::Greater,
+        (_, false) => Ordering::Less,
+    }
+}
+
+fn compare_rpm(a: &str, b: &str) -> Ordering {
+    let (epoch_a, a) = split_epoch(a);
+    let (epoch_b, b) = split_epoch(b);
+
+    let (version_a, release_a) = a.rsplit_once('-').unwrap_or((a, ""));
+    let (version_b, release_b) = b.rsplit_once('-').unwrap_or((b, ""));
+
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| rpmvercmp(version_a, version_b))
+        .then_with(|| {
+            // a missing release matches any release
+            if release_a.is_empty() || release_b.is_empty() {
+                Ordering::Equal
+            } else {
+                rpmvercmp(release_a, release_b)
+            }
+        })
+}
+
+struct Semver<'a> {
+    core: Vec<u64>,
+    pre: Vec<&'a str>,
+}
+
+fn parse_semver(version: &str) -> Option<Semver<'_>> {
+    let version = version.trim().trim_start_matches('v');
+    // build metadata does not take part in the ordering
+    let version = version.split('+').next().unwrap_or_default();
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, pre.split('.').collect()),
+        None => (version, vec![]),
+    };
+
+    let mut parts = core
+        .split('.')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    parts.resize(3, 0);
+
+    Some(Semver { core: parts, pre })
+}
+
+// https://semver.org/#spec-item-11
+fn compare_semver(a: &str, b: &str) -> Ordering {
+    let (a, b) = match (parse_semver(a), parse_semver(b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return compare_generic(a, b),
+    };
+
+    a.core.cmp(&b.core).then_with(|| {
+        match (a.pre.is_empty(), b.pre.is_empty()) {
+            (true, true) => return Ordering::Equal,
+            // a pre-release has lower precedence than the release
+            (true, false) => return Ordering::Greater,
+            (false, true) => return Ordering::Less,
+            _ => {}
+        }
+
+        for (x, y) in a.pre.iter().zip(b.pre.iter()) {
+            let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+
+        a.pre.len().cmp(&b.pre.len())
+    })
+}
+
+struct Pep440<'a> {
+    epoch: u64,
+    release: Vec<u64>,
+    // a, b and rc are ranked 0, 1 and 2
+    pre: Option<(u8, u64)>,
+    post: Option<u64>,
+    dev: Option<u64>,
+    local: Vec<&'a str>,
+}
+
+fn is_pep440_separator(c: char) -> bool {
+    c == '.' || c == '-' || c == '_'
+}
+
+fn leading_number(s: &str) -> (Option<u64>, &str) {
+    let len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    (s[..len].parse().ok(), &s[len..])
+}
+
+// an optionally separated label followed by an optionally separated number, 0 when missing
+fn pep440_segment<'a>(s: &'a str, labels: &[(&str, u8)]) -> Option<(u8, u64, &'a str)> {
+    let rest = s.trim_start_matches(is_pep440_separator);
+    let (label, rank) = labels.iter().find(|(label, _)| rest.starts_with(label))?;
+    let rest = &rest[label.len()..];
+    let separated = rest.trim_start_matches(is_pep440_separator);
+    match leading_number(separated) {
+        (Some(number), rest) => Some((*rank, number, rest)),
+        (None, _) => Some((*rank, 0, rest)),
+    }
+}
+
+// https://packaging.python.org/en/latest/specifications/version-specifiers/#normalization
+fn parse_pep440(version: &str) -> Option<Pep440<'_>> {
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+    let (public, local) = match version.split_once('+') {
+        Some((public, local)) => (public, local.split(is_pep440_separator).collect()),
+        None => (version, vec![]),
+    };
+    let (epoch, public) = match public.split_once('!') {
+        Some((epoch, public)) => (epoch.parse().ok()?, public),
+        None => (0, public),
+    };
+    let lower = public.to_ascii_lowercase();
+
+    let mut release = vec![];
+    let mut rest = lower.as_str();
+    loop {
+        let (number, after) = leading_number(rest);
+        release.push(number?);
+        match after.strip_prefix('.') {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => {
+                rest = after;
+                break;
+            }
+        }
+    }
+
+    // longer spellings first
+    const PRE: &[(&str, u8)] = &[
+        ("alpha", 0),
+        ("a", 0),
+        ("beta", 1),
+        ("b", 1),
+        ("preview", 2),
+        ("pre", 2),
+        ("rc", 2),
+        ("c", 2),
+    ];
+    let pre = pep440_segment(rest, PRE).map(|(rank, number, after)| {
+        rest = after;
+        (rank, number)
+    });
+    let post = match rest.strip_prefix('-').map(leading_number) {
+        // 1.0-1 is 1.0.post1
+        Some((Some(number), after)) => {
+            rest = after;
+            Some(number)
+        }
+        _ => {
+            pep440_segment(rest, &[("post", 0), ("rev", 0), ("r", 0)]).map(|(_, number, after)| {
+                rest = after;
+                number
+            })
+        }
+    };
+    let dev = pep440_segment(rest, &[("dev", 0)]).map(|(_, number, after)| {
+        rest = after;
+        number
+    });
+    if !rest.is_empty() || local.iter().any(|segment: &&str| segment.is_empty()) {
+        return None;
+    }
+
+    while release.len() > 1 && release.last() == Some(&0) {
+        release.pop();
+    }
+    Some(Pep440 {
+        epoch,
+        release,
+        pre,
+        post,
+        dev,
+        local,
+    })
+}
+
+fn compare_pep440(a: &str, b: &str) -> Ordering {
+    let (a, b) = match (parse_pep440(a), parse_pep440(b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return compare_generic(a, b),
+    };
+
+    // 1.0.dev0 < 1.0a1.dev0 < 1.0a1 < 1.0 < 1.0+local < 1.0.post1.dev0 < 1.0.post1
+    let pre = |v: &Pep440| match v.pre {
+        Some((rank, number)) => (rank as i8, number),
+        None if v.post.is_none() && v.dev.is_some() => (-1, 0),
+        None => (3, 0),
+    };
+    let post = |v: &Pep440| v.post.map(|number| number as i128).unwrap_or(-1);
+    let dev = |v: &Pep440| v.dev.map(|number| number as i128).unwrap_or(i128::MAX);
+    let local = |x: &&str, y: &&str| match (x.parse::<u64>(), y.parse::<u64>()) {
+        (Ok(x), Ok(y)) => x.cmp(&y),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()),
+    };
+
+    a.epoch
+        .cmp(&b.epoch)
+        .then_with(|| a.release.cmp(&b.release))
+        .then_with(|| pre(&a).cmp(&pre(&b)))
+        .then_with(|| post(&a).cmp(&post(&b)))
+        .then_with(|| dev(&a).cmp(&dev(&b)))
+        .then_with(|| {
+            a.local
+                .iter()
+                .zip(&b.local)
+                .map(|(x, y)| local(x, y))
+                .find(|ord| *ord != Ordering::Equal)
+                .unwrap_or_else(|| a.local.len().cmp(&b.local.len()))
+        })
+}
+
+// splits a version into numeric and alphabetic tokens, everything else is a separator
+fn tokens(version: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let bytes = version.as_bytes();
+
+    for idx in 0..=bytes.len() {
+        let boundary = match (start, bytes.get(idx)) {
+            (None, _) => false,
+            (Some(s), Some(c)) => {
+                !c.is_ascii_alphanumeric()
+                    || c.is_ascii_digit() != (bytes[s] as char).is_ascii_digit()
+            }
+            (Some(_), None) => true,
+        };
+        if boundary {
+            tokens.push(&version[start.unwrap()..idx]);
+            start = None;
+        }
+        if start.is_none() && bytes.get(idx).map(|c| c.is_ascii_alphanumeric()) == Some(true) {
+            start = Some(idx);
+        }
+    }
+
+    tokens
+}
+
+fn compare_generic(a: &str, b: &str) -> Ordering {
+    let (epoch_a, a) = split_epoch(a);
+    let (epoch_b, b) = split_epoch(b);
+    let ord = epoch_a.cmp(&epoch_b);
+    if ord != Ordering::Equal {
+        return ord;
+    }
+
+    let (ta, tb) = (tokens(a), tokens(b));
+    for (x, y) in ta.iter().zip(tb.iter()) {
+        let x_num = x.as_bytes()[0].is_ascii_digit();
+        let y_num = y.as_bytes()[0].is_ascii_digit();
+        let ord = match (x_num, y_num) {
+            (true, true) => compare_numeric(x, y),
+            // 1.0 > 1.0rc1 > 1.0a
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => x.to_lowercase().cmp(&y.to_lowercase()),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    // an additional numeric token makes a version newer, an alphabetic one (pre-release) older
+    match ta.len().cmp(&tb.len()) {
+        Ordering::Greater if !ta[tb.len()].as_bytes()[0].is_ascii_digit() => Ordering::Less,
+        Ordering::Less if !tb[ta.len()].as_bytes()[0].is_ascii_digit() => Ordering::Greater,
+        ord => ord,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every version is older than the next one
+    fn assert_sorted(scheme: Scheme, versions: &[&str]) {
+        for pair in versions.windows(2) {
+            assert_eq!(
+                compare(scheme, pair[0], pair[1]),
+                Ordering::Less,
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+            assert_eq!(
+                compare(scheme, pair[1], pair[0]),
+                Ordering::Greater,
+                "{} > {}",
+                pair[1],
+                pair[0]
+            );
+        }
+    }
+
+    fn assert_equal(scheme: Scheme, pairs: &[(&str, &str)]) {
+        for (a, b) in pairs {
+            assert_eq!(compare(scheme, a, b), Ordering::Equal, "{} == {}", a, b);
+        }
+    }
+
+    #[test]
+    fn debian() {
+        assert_sorted(
+            Scheme::Debian,
+            &[
+                "1.0~~",
+                "1.0~~a",
+                "1.0~",
+                "1.0~rc1",
+                "1.0~rc2",
+                "1.0",
+                "1.0-1",
+                "1.0-1ubuntu1",
+                "1.0-2",
+                // letters sort before the other characters
+                "1.0a",
+                "1.0+dfsg",
+                "1.0.0",
+                "2.4-9",
+                "2.30-1",
+                "1:0.1",
+                "2:0.0.1",
+            ],
+        );
+        assert_equal(
+            Scheme::Debian,
+            &[("0:1.0-1", "1.0-1"), ("1.01", "1.1"), ("1.0", "1.0-")],
+        );
+    }
+
+    #[test]
+    fn rpm() {
+        assert_sorted(
+            Scheme::Rpm,
+            &[
+                "1.0~rc1",
+                "1.0~rc2",
+                "1.0",
+                "1.0^git1",
+                "1.0^git2",
+                "1.0a",
+                "1.0.1-1",
+                "1.0.1-2.el9",
+                "1.0.1-10.el9",
+                "2.9",
+                "2.10",
+                "1:0.5",
+            ],
+        );
+        assert_equal(
+            Scheme::Rpm,
+            &[("1.0", "1.0-5"), ("0:1.0-1", "1.0-1"), ("1.0_1", "1.0.1")],
+        );
+    }
+
+    #[test]
+    fn semver() {
+        assert_sorted(
+            Scheme::Semver,
+            &[
+                "1.0.0-alpha",
+                "1.0.0-alpha.1",
+                "1.0.0-alpha.beta",
+                "1.0.0-beta",
+                "1.0.0-beta.2",
+                "1.0.0-beta.11",
+                "1.0.0-rc.1",
+                "1.0.0",
+                "1.0.1",
+                "v1.2.0",
+                "1.10.0",
+            ],
+        );
+        assert_equal(
+            Scheme::Semver,
+            &[("v1.2", "1.2.0"), ("1.0.0+build.5", "1.0.0")],
+        );
+    }
+
+    #[test]
+    fn pep440() {
+        assert_sorted(
+            Scheme::Pep440,
+            &[
+                "1.0.dev0",
+                "1.0.dev1",
+                "1.0a1.dev1",
+                "1.0a1",
+                "1.0a2",
+                "1.0b1",
+                "1.0rc1",
+                "1.0rc1.post1",
+                "1.0",
+                "1.0+abc",
+                "1.0+abc.2",
+                "1.0+1",
+                "1.0.post1.dev0",
+                "1.0.post1",
+                "1.0.post2",
+                "1.0.1",
+                "1.1.dev1",
+                "1.10",
+                "1!0.1",
+            ],
+        );
+        assert_equal(
+            Scheme::Pep440,
+            &[
+                ("1.0", "1.0.0"),
+                ("v1.0", "1.0"),
+                ("0!1.0", "1.0"),
+                ("1.0alpha1", "1.0a1"),
+                ("1.0-beta.2", "1.0b2"),
+                ("1.0c1", "1.0rc1"),
+                ("1.0-preview1", "1.0rc1"),
+                ("1.0-1", "1.0.post1"),
+                ("1.0-r2", "1.0.post2"),
+                ("1.0.post", "1.0.post0"),
+                ("1.0.DEV", "1.0.dev0"),
+                ("1.0RC1-dev_3", "1.0rc1.dev3"),
+            ],
+        );
+        assert_eq!(Scheme::for_purl_type("pypi"), Scheme::Pep440);
+    }
+
+    #[test]
+    fn generic() {
+        assert_sorted(
+            Scheme::Generic,
+            &["1.0a", "1.0rc1", "1.0", "1.0.1", "1.9", "1.10", "2:0.1"],
+        );
+        assert_equal(Scheme::Generic, &[("1.0-RC1", "1.0rc1")]);
+    }
+}
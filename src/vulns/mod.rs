@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::component::{Component, ComponentTrait};
+use crate::purl::Purl;
+use crate::version::Scheme;
+use crate::Error;
+
+mod osv;
+
+/// A known vulnerability affecting a component.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<String>,
+}
+
+// OSV ecosystem and version ordering for a package URL
+fn ecosystem(purl: &Purl) -> Option<(&'static str, Scheme)> {
+    let namespace = purl.namespace.as_deref().unwrap_or_default();
+    match (purl.kind.as_str(), namespace) {
+        ("deb", "ubuntu") => Some(("Ubuntu", Scheme::Debian)),
+        ("deb", _) => Some(("Debian", Scheme::Debian)),
+        ("apk", "wolfi") => Some(("Wolfi", Scheme::Generic)),
+        ("apk", "chainguard") => Some(("Chainguard", Scheme::Generic)),
+        ("apk", _) => Some(("Alpine", Scheme::Generic)),
+        ("rpm", "redhat") => Some(("Red Hat", Scheme::Rpm)),
+        ("rpm", "rocky") => Some(("Rocky Linux", Scheme::Rpm)),
+        ("rpm", "almalinux") => Some(("AlmaLinux", Scheme::Rpm)),
+        ("rpm", "opensuse") => Some(("openSUSE", Scheme::Rpm)),
+        ("rpm", "suse") => Some(("SUSE", Scheme::Rpm)),
+        ("pypi", _) => Some(("PyPI", Scheme::Pep440)),
+        ("npm", _) => Some(("npm", Scheme::Semver)),
+        ("golang", _) => Some(("Go", Scheme::Semver)),
+        ("cargo", _) => Some(("crates.io", Scheme::Semver)),
+        ("gem", _) => Some(("RubyGems", Scheme::Generic)),
+        ("maven", _) => Some(("Maven", Scheme::Generic)),
+        ("nuget", _) => Some(("NuGet", Scheme::Generic)),
+        _ => None,
+    }
+}
+
+// "Debian:12" -> ("debian", Some("12"))
+fn split_ecosystem(ecosystem: &str) -> (String, Option<&str>) {
+    match ecosystem.split_once(':') {
+        Some((base, release)) => (base.to_lowercase(), Some(release)),
+        None => (ecosystem.to_lowercase(), None),
+    }
+}
+
+fn normalize_name(ecosystem: &str, name: &str) -> String {
+    match ecosystem {
+        // https://peps.python.org/pep-0503/#normalized-names
+        "pypi" => name.to_lowercase().replace(['_', '.'], "-"),
+        _ => name.to_lowercase(),
+    }
+}
+
+// OSV names include the namespace of the packages that have one: google.golang.org/protobuf,
+// @angular/core or org.apache.logging.log4j:log4j-core, distributions are a qualifier instead
+fn package_name(purl: &Purl) -> String {
+    match (purl.kind.as_str(), purl.namespace.as_deref()) {
+        ("golang" | "npm", Some(namespace)) => format!("{}/{}", namespace, purl.name),
+        ("maven", Some(namespace)) => format!("{}:{}", namespace, purl.name),
+        _ => purl.name.clone(),
+    }
+}
+
+// compares an OSV release ("12", "v3.18", "22.04:LTS") with the purl distro qualifier
+// ("debian-12", "3.18.4", "ubuntu-22.04")
+fn release_matches(distro: Option<&String>, release: Option<&str>) -> bool {
+    let (distro, release) = match (distro, release) {
+        (Some(distro), Some(release)) => (distro, release),
+        _ => return true,
+    };
+
+    let release = release.split(':').next().unwrap_or_default();
+    let release = release.trim_start_matches('v');
+    let distro = distro.rsplit('-').next().unwrap_or(distro);
+
+    distro == release || distro.starts_with(&format!("{}.", release))
+}
+
+/// Offline vulnerability database loaded from an OSV export.
+pub(crate) struct Database {
+    records: Vec<osv::Record>,
+    // (ecosystem, package name) -> (record, affected entry)
+    index: HashMap<(String, String), Vec<(usize, usize)>>,
+}
+
+impl Database {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut db = Self {
+            records: vec![],
+            index: HashMap::new(),
+        };
+
+        log::info!("loading vulnerability database from {} ...", path.display());
+
+        if path.is_dir() {
+            for entry in walkdir::WalkDir::new(path).follow_links(true) {
+                let entry = entry.map_err(|e| Error::io(path, e.into()))?;
+                if entry.file_type().is_file() && is_json(&entry.file_name().to_string_lossy()) {
+                    let data = fs::read(entry.path()).map_err(|e| Error::io(entry.path(), e))?;
+                    db.add(&entry.path().display().to_string(), &data);
+                }
+            }
+        } else if path.extension().map(|ext| ext == "zip").unwrap_or(false) {
+            db.load_zip(path)?;
+        } else {
+            let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+            db.add(&path.display().to_string(), &data);
+        }
+
+        log::info!(
+            "loaded {} vulnerability records for {} packages",
+            db.records.len(),
+            db.index.len()
+        );
+
+        Ok(db)
+    }
+
+    fn load_zip(&mut self, path: &Path) -> Result<(), Error> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut archive =
+            zip::ZipArchive::new(file).map_err(|e| Error::parse(path.display(), e))?;
+
+        for idx in 0..archive.len() {
+            let mut entry = archive
+                .by_index(idx)
+                .map_err(|e| Error::parse(path.display(), format!("entry {}: {}", idx, e)))?;
+            if !entry.is_file() || !is_json(entry.name()) {
+                continue;
+            }
+
+            let name = format!("{}:{}", path.display(), entry.name());
+            let mut data = vec![];
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| Error::io(&name, e))?;
+
+            self.add(&name, &data);
+        }
+
+        Ok(())
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) {
+        // a single malformed record should not prevent using the rest of the database
+        let record: osv::Record = match serde_json::from_slice(data) {
+            Ok(record) => record,
+            Err(e) => {
+                log::warn!("can't parse vulnerability record {}: {}", name, e);
+                return;
+            }
+        };
+
+        if record.withdrawn.is_some() {
+            log::debug!("skipping withdrawn record {}", &record.id);
+            return;
+        }
+
+        let record_idx = self.records.len();
+        for (affected_idx, affected) in record.affected.iter().enumerate() {
+            if let Some(package) = &affected.package {
+                let (ecosystem, _) = split_ecosystem(&package.ecosystem);
+                let name = normalize_name(&ecosystem, &package.name);
+                self.index
+                    .entry((ecosystem, name))
+                    .or_default()
+                    .push((record_idx, affected_idx));
+            }
+        }
+        self.records.push(record);
+    }
+
+    /// Returns the known vulnerabilities of a component.
+    pub fn check(&self, comp: &dyn ComponentTrait) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = vec![];
+
+        let purl = match comp.purl() {
+            Some(purl) => purl,
+            None => return findings,
+        };
+        let (ecosystem, scheme) = match ecosystem(&purl) {
+            Some(eco) => eco,
+            None => return findings,
+        };
+        let ecosystem = ecosystem.to_lowercase();
+        let version = comp.version();
+        if version.is_empty() {
+            return findings;
+        }
+
+        // distribution advisories refer to source packages
+        let mut names = vec![normalize_name(&ecosystem, &package_name(&purl))];
+        if let Some(origin) = comp.origin() {
+            let origin = normalize_name(&ecosystem, origin);
+            if !names.contains(&origin) {
+                names.push(origin);
+            }
+        }
+
+        for name in names {
+            let entries = match self.index.get(&(ecosystem.clone(), name)) {
+                Some(entries) => entries,
+                None => continue,
+            };
+
+            for (record_idx, affected_idx) in entries {
+                let record = &self.records[*record_idx];
+                let affected = &record.affected[*affected_idx];
+                if findings.iter().any(|f| f.id == record.id) {
+                    continue;
+                }
+
+                let release = affected
+                    .package
+                    .as_ref()
+                    .and_then(|p| split_ecosystem(&p.ecosystem).1.map(|r| r.to_owned()));
+                if !release_matches(purl.qualifiers.get("distro"), release.as_deref()) {
+                    continue;
+                }
+
+                if let Some(verdict) = affected.evaluate(scheme, version) {
+                    findings.push(Finding {
+                        id: record.id.clone(),
+                        aliases: record.aliases.clone(),
+                        summary: record.summary.clone(),
+                        fixed: verdict.fixed,
+                    });
+                }
+            }
+        }
+
+        findings
+    }
+
+    /// Attaches the known vulnerabilities to each component.
+    pub fn scan(&self, components: &mut [Component]) {
+        let mut total = 0;
+        let mut vulnerable = 0;
+
+        for comp in components.iter_mut() {
+            comp.vulnerabilities = self.check(comp);
+            if !comp.vulnerabilities.is_empty() {
+                total += comp.vulnerabilities.len();
+                vulnerable += 1;
+            }
+        }
+
+        log::info!(
+            "found {} vulnerabilities in {} components",
+            total,
+            vulnerable
+        );
+    }
+}
+
+fn is_json(file_name: &str) -> bool {
+    file_name.to_lowercase().ends_with(".json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(records: &[serde_json::Value]) -> Database {
+        let mut db = Database {
+            records: vec![],
+            index: HashMap::new(),
+        };
+        for (idx, record) in records.iter().enumerate() {
+            db.add(&format!("record-{}", idx), record.to_string().as_bytes());
+        }
+        db
+    }
+
+    fn record(id: &str, ecosystem: &str, name: &str, fixed: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "aliases": [format!("CVE-{}", id)],
+            "affected": [{
+                "package": {"ecosystem": ecosystem, "name": name},
+                "ranges": [{
+                    "type": "ECOSYSTEM",
+                    "events": [{"introduced": "0"}, {"fixed": fixed}]
+                }]
+            }]
+        })
+    }
+
+    fn component(purl: &str, origin: Option<&str>) -> Component {
+        let purl: Purl = purl.parse().unwrap();
+        Component {
+            name: purl.name.clone(),
+            version: purl.version.clone().unwrap_or_default(),
+            origin: origin.map(|origin| origin.to_owned()),
+            purl: Some(purl),
+            ..Default::default()
+        }
+    }
+
+    fn ids(findings: &[Finding]) -> Vec<(&str, Option<&str>)> {
+        findings
+            .iter()
+            .map(|f| (f.id.as_str(), f.fixed.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn maps_ecosystems() {
+        let name =
+            |purl: &str| ecosystem(&purl.parse::<Purl>().unwrap()).map(|(ecosystem, _)| ecosystem);
+        assert_eq!(name("pkg:deb/ubuntu/bash@5.1"), Some("Ubuntu"));
+        assert_eq!(name("pkg:deb/debian/bash@5.2"), Some("Debian"));
+        assert_eq!(name("pkg:apk/wolfi/bash@5.2"), Some("Wolfi"));
+        assert_eq!(name("pkg:apk/alpine/bash@5.2"), Some("Alpine"));
+        assert_eq!(name("pkg:rpm/redhat/bash@5.1"), Some("Red Hat"));
+        assert_eq!(name("pkg:npm/%40angular/core@17.0.0"), Some("npm"));
+        assert_eq!(name("pkg:golang/golang.org/x/net@v0.17.0"), Some("Go"));
+        assert_eq!(name("pkg:rpm/fedora/bash@5.2"), None);
+        assert_eq!(name("pkg:generic/bash@5.2"), None);
+    }
+
+    #[test]
+    fn matches_releases() {
+        let distro = |d: &str| Some(d.to_owned());
+        assert!(release_matches(distro("debian-12").as_ref(), Some("12")));
+        assert!(release_matches(distro("3.18.4").as_ref(), Some("v3.18")));
+        assert!(release_matches(
+            distro("ubuntu-22.04").as_ref(),
+            Some("22.04:LTS")
+        ));
+        assert!(!release_matches(distro("debian-12").as_ref(), Some("11")));
+        assert!(!release_matches(distro("3.180").as_ref(), Some("v3.18")));
+        // records and packages without a release match every release
+        assert!(release_matches(None, Some("12")));
+        assert!(release_matches(distro("debian-12").as_ref(), None));
+    }
+
+    #[test]
+    fn matches_source_packages() {
+        let db = database(&[
+            record("DSA-1", "Debian:12", "openssl", "3.0.13-1"),
+            record("DSA-2", "Debian:11", "openssl", "1.1.1w-1"),
+            record("DSA-3", "Debian:12", "openssl", "3.0.9-1"),
+        ]);
+
+        let libssl = component(
+            "pkg:deb/debian/libssl3@3.0.11-1?arch=amd64&distro=debian-12",
+            Some("openssl"),
+        );
+        assert_eq!(ids(&db.check(&libssl)), vec![("DSA-1", Some("3.0.13-1"))]);
+
+        // without the source package the binary one has no advisory
+        let libssl = component(
+            "pkg:deb/debian/libssl3@3.0.11-1?arch=amd64&distro=debian-12",
+            None,
+        );
+        assert!(db.check(&libssl).is_empty());
+    }
+
+    #[test]
+    fn matches_distro_releases() {
+        let db = database(&[
+            record("ALPINE-1", "Alpine:v3.18", "busybox", "1.36.1-r2"),
+            record("ALPINE-2", "Alpine:v3.19", "busybox", "1.36.1-r9"),
+        ]);
+
+        let busybox = component("pkg:apk/alpine/busybox@1.36.1-r1?distro=3.18.4", None);
+        assert_eq!(
+            ids(&db.check(&busybox)),
+            vec![("ALPINE-1", Some("1.36.1-r2"))]
+        );
+    }
+
+    #[test]
+    fn matches_namespaced_packages() {
+        let db = database(&[
+            record("GHSA-1", "npm", "@angular/core", "17.0.6"),
+            record("GHSA-2", "npm", "core", "1.0.0"),
+            record("GO-1", "Go", "google.golang.org/protobuf", "1.33.0"),
+            record(
+                "GHSA-3",
+                "Maven",
+                "org.apache.logging.log4j:log4j-core",
+                "2.17.1",
+            ),
+        ]);
+
+        let angular = component("pkg:npm/%40angular/core@17.0.0", None);
+        assert_eq!(ids(&db.check(&angular)), vec![("GHSA-1", Some("17.0.6"))]);
+
+        let protobuf = component("pkg:golang/google.golang.org/protobuf@v1.28.0", None);
+        assert_eq!(ids(&db.check(&protobuf)), vec![("GO-1", Some("1.33.0"))]);
+
+        let log4j = component("pkg:maven/org.apache.logging.log4j/log4j-core@2.14.1", None);
+        assert_eq!(ids(&db.check(&log4j)), vec![("GHSA-3", Some("2.17.1"))]);
+    }
+
+    #[test]
+    fn skips_withdrawn_records() {
+        let mut withdrawn = record("GHSA-1", "PyPI", "requests", "2.31.0");
+        withdrawn["withdrawn"] = serde_json::json!("2024-01-01T00:00:00Z");
+        let db = database(&[withdrawn, record("GHSA-2", "PyPI", "Requests", "2.32.0")]);
+
+        let requests = component("pkg:pypi/requests@2.30.0", None);
+        let findings = db.check(&requests);
+        assert_eq!(ids(&findings), vec![("GHSA-2", Some("2.32.0"))]);
+        assert_eq!(findings[0].aliases, vec!["CVE-GHSA-2"]);
+        assert_eq!(db.records.len(), 1);
+    }
+}
@@ -0,0 +1,294 @@
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use crate::version::{self, Scheme};
+
+// shortest abbreviation of a commit hash shown by git
+const MIN_COMMIT_LEN: usize = 7;
+
+// https://ossf.github.io/osv-schema/
+#[derive(Debug, Deserialize)]
+pub(crate) struct Record {
+    pub id: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub withdrawn: Option<String>,
+    #[serde(default)]
+    pub affected: Vec<Affected>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Package {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Affected {
+    #[serde(default)]
+    pub package: Option<Package>,
+    #[serde(default)]
+    pub ranges: Vec<Range>,
+    #[serde(default)]
+    pub versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Range {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Event {
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+    pub last_affected: Option<String>,
+    pub limit: Option<String>,
+}
+
+impl Event {
+    fn version(&self) -> &str {
+        self.introduced
+            .as_ref()
+            .or(self.fixed.as_ref())
+            .or(self.last_affected.as_ref())
+            .or(self.limit.as_ref())
+            .map(|v| v.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Result of evaluating an affected entry against a version.
+pub(crate) struct Verdict {
+    pub fixed: Option<String>,
+}
+
+fn compare(scheme: Scheme, a: &str, b: &str) -> Ordering {
+    // "0" is a special value that sorts before every other version
+    match (a == "0", b == "0") {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => version::compare(scheme, a, b),
+    }
+}
+
+// commit hashes are the only thing we can reason about without the repository history, either
+// the version itself or the trailing part of a Go pseudo-version (v0.0.0-20220101000000-abcdef123456)
+fn commit_of(version: &str) -> Option<&str> {
+    let candidate = version.rsplit('-').next().unwrap_or(version);
+    is_commit(candidate).then_some(candidate)
+}
+
+fn is_commit(hash: &str) -> bool {
+    hash.len() >= MIN_COMMIT_LEN && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// either hash can be abbreviated, "0" and other short values don't match any commit
+fn same_commit(a: &str, b: &str) -> bool {
+    if !is_commit(a) || !is_commit(b) {
+        return false;
+    }
+    let len = a.len().min(b.len());
+    match (a.get(..len), b.get(..len)) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => false,
+    }
+}
+
+impl Range {
+    // https://ossf.github.io/osv-schema/#evaluation
+    fn evaluate_ordered(&self, scheme: Scheme, version: &str) -> Option<Verdict> {
+        let mut events: Vec<&Event> = self.events.iter().filter(|e| e.limit.is_none()).collect();
+        events.sort_by(|a, b| compare(scheme, a.version(), b.version()));
+
+        let mut affected = false;
+        for event in &events {
+            if let Some(introduced) = &event.introduced {
+                if compare(scheme, version, introduced) != Ordering::Less {
+                    affected = true;
+                }
+            } else if let Some(fixed) = &event.fixed {
+                if compare(scheme, version, fixed) != Ordering::Less {
+                    affected = false;
+                }
+            } else if let Some(last_affected) = &event.last_affected {
+                if compare(scheme, version, last_affected) == Ordering::Greater {
+                    affected = false;
+                }
+            }
+        }
+
+        if !affected {
+            return None;
+        }
+
+        let fixed = events
+            .iter()
+            .filter_map(|e| e.fixed.as_ref())
+            .find(|fixed| compare(scheme, version, fixed) == Ordering::Less)
+            .cloned();
+
+        Some(Verdict { fixed })
+    }
+
+    fn evaluate_git(&self, version: &str) -> Option<Verdict> {
+        let commit = commit_of(version)?;
+        for event in &self.events {
+            if let Some(fixed) = &event.fixed {
+                if same_commit(commit, fixed) {
+                    return None;
+                }
+            }
+        }
+
+        self.events
+            .iter()
+            .filter_map(|e| e.introduced.as_ref().or(e.last_affected.as_ref()))
+            .find(|c| same_commit(commit, c))
+            .map(|_| Verdict {
+                fixed: self.events.iter().find_map(|e| e.fixed.clone()),
+            })
+    }
+
+    fn evaluate(&self, scheme: Scheme, version: &str) -> Option<Verdict> {
+        match self.kind.as_str() {
+            "SEMVER" => self.evaluate_ordered(Scheme::Semver, version),
+            "ECOSYSTEM" => self.evaluate_ordered(scheme, version),
+            "GIT" => self.evaluate_git(version),
+            other => {
+                log::debug!("unsupported range type {}", other);
+                None
+            }
+        }
+    }
+}
+
+impl Affected {
+    pub fn evaluate(&self, scheme: Scheme, version: &str) -> Option<Verdict> {
+        if let Some(verdict) = self.ranges.iter().find_map(|r| r.evaluate(scheme, version)) {
+            return Some(verdict);
+        }
+
+        // explicitly enumerated versions, this is also how GIT ranges are resolved to tags
+        if self
+            .versions
+            .iter()
+            .any(|v| compare(scheme, v, version) == Ordering::Equal)
+        {
+            return Some(Verdict { fixed: None });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_commit_requires_full_hashes() {
+        assert!(same_commit("abcdef1", "ABCDEF1234567890"));
+        assert!(same_commit("abcdef1234567890", "abcdef1"));
+        assert!(!same_commit("abcdef1", "abcdef2"));
+        // "0" is the start of the history, not a prefix
+        assert!(!same_commit("0123456789", "0"));
+        assert!(!same_commit("abcdef", "abcdef1234"));
+        assert!(!same_commit("abcdefg", "abcdefg"));
+        assert!(!same_commit("abcdef1", "abcdeé12"));
+        assert!(!same_commit("é1234567", "é1234567890"));
+    }
+
+    fn affected(json: &str) -> Affected {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn fixed_in(affected: &Affected, scheme: Scheme, version: &str) -> Option<Option<String>> {
+        affected
+            .evaluate(scheme, version)
+            .map(|verdict| verdict.fixed)
+    }
+
+    #[test]
+    fn ecosystem_ranges() {
+        let deb = affected(
+            r#"{"ranges": [{"type": "ECOSYSTEM", "events": [
+                {"introduced": "0"}, {"fixed": "1.2-1"},
+                {"introduced": "2.0~rc1"}, {"fixed": "2.0-3"}
+            ]}]}"#,
+        );
+        let cases = [
+            ("0.1", Some(Some("1.2-1"))),
+            ("1.1-9", Some(Some("1.2-1"))),
+            ("1.2-1", None),
+            ("1.5", None),
+            ("2.0~beta", None),
+            ("2.0~rc1", Some(Some("2.0-3"))),
+            ("2.0-2", Some(Some("2.0-3"))),
+            ("2.0-3", None),
+            // the epoch is newer than every fixed version
+            ("1:1.0", None),
+        ];
+        for (version, expected) in cases {
+            assert_eq!(
+                fixed_in(&deb, Scheme::Debian, version),
+                expected.map(|fixed| fixed.map(str::to_owned)),
+                "{}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn last_affected_ranges() {
+        let npm = affected(
+            r#"{"ranges": [{"type": "SEMVER", "events": [
+                {"introduced": "1.0.0"}, {"last_affected": "1.5.0"}
+            ]}]}"#,
+        );
+        assert_eq!(fixed_in(&npm, Scheme::Generic, "0.9.0"), None);
+        assert_eq!(fixed_in(&npm, Scheme::Generic, "1.0.0-rc.1"), None);
+        assert_eq!(fixed_in(&npm, Scheme::Generic, "1.0.0"), Some(None));
+        assert_eq!(fixed_in(&npm, Scheme::Generic, "1.5.0"), Some(None));
+        assert_eq!(fixed_in(&npm, Scheme::Generic, "1.5.1"), None);
+    }
+
+    #[test]
+    fn enumerated_versions() {
+        let pypi = affected(r#"{"versions": ["1.0", "1.1"]}"#);
+        assert_eq!(fixed_in(&pypi, Scheme::Generic, "1.1"), Some(None));
+        assert_eq!(fixed_in(&pypi, Scheme::Generic, "1.2"), None);
+    }
+
+    #[test]
+    fn git_ranges() {
+        let go = affected(
+            r#"{"ranges": [{"type": "GIT", "events": [
+                {"introduced": "0"}, {"introduced": "abcdef1234567890"},
+                {"fixed": "0123456789abcdef"}
+            ]}]}"#,
+        );
+        assert_eq!(
+            fixed_in(&go, Scheme::Semver, "v0.0.0-20220101000000-abcdef123456"),
+            Some(Some("0123456789abcdef".to_owned()))
+        );
+        assert_eq!(
+            fixed_in(&go, Scheme::Semver, "v0.0.0-20230101000000-0123456789ab"),
+            None
+        );
+        // not after "0", the history isn't known
+        assert_eq!(
+            fixed_in(&go, Scheme::Semver, "v0.0.0-20210101000000-0fedcba98765"),
+            None
+        );
+        assert_eq!(fixed_in(&go, Scheme::Semver, "v1.2.3"), None);
+    }
+}
@@ -0,0 +1,450 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+
+use crate::Error;
+
+// https://github.com/msuhanov/regf/blob/master/Windows%20registry%20file%20format%20specification.md
+const REGF_MAGIC: &[u8] = b"regf";
+const BASE_BLOCK_SIZE: usize = 4096;
+const ROOT_CELL_OFFSET: usize = 0x24;
+const KEY_COMP_NAME: u16 = 0x0020;
+const VALUE_COMP_NAME: u16 = 0x0001;
+const DATA_INLINE: u32 = 0x8000_0000;
+const BIG_DATA_SEGMENT_SIZE: usize = 16344;
+// protects against loops in corrupted subkey index trees
+const MAX_INDEX_DEPTH: usize = 16;
+// seconds between 1601-01-01 and 1970-01-01
+const FILETIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+pub(crate) const REG_SZ: u32 = 1;
+pub(crate) const REG_EXPAND_SZ: u32 = 2;
+pub(crate) const REG_DWORD: u32 = 4;
+pub(crate) const REG_MULTI_SZ: u32 = 7;
+pub(crate) const REG_QWORD: u32 = 11;
+
+fn malformed<M: std::fmt::Display>(message: M) -> Error {
+    Error::parse("registry hive", message)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8).map(|b| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(b);
+        u64::from_le_bytes(bytes)
+    })
+}
+
+fn filetime_to_datetime(filetime: u64) -> NaiveDateTime {
+    let secs = (filetime / 10_000_000) as i64 - FILETIME_EPOCH_OFFSET;
+    let nanos = (filetime % 10_000_000) as u32 * 100;
+    Utc.timestamp_opt(secs, nanos)
+        .single()
+        .map(|dt| dt.naive_utc())
+        .unwrap_or_default()
+}
+
+// compressed names are stored as latin-1, the others as UTF-16LE
+fn decode_name(raw: &[u8], compressed: bool) -> String {
+    if compressed {
+        raw.iter().map(|b| *b as char).collect()
+    } else {
+        let words: Vec<u16> = raw
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&words)
+    }
+}
+
+/// A registry hive file loaded in memory.
+pub(crate) struct Hive {
+    data: Vec<u8>,
+}
+
+/// A key node of a hive.
+pub(crate) struct Key<'a> {
+    hive: &'a Hive,
+    cell: usize,
+    pub name: String,
+    pub modified: NaiveDateTime,
+}
+
+/// A value of a key.
+pub(crate) struct Value {
+    pub name: String,
+    pub kind: u32,
+    pub data: Vec<u8>,
+}
+
+impl Hive {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+        Self::parse(data).map_err(|e| Error::parse(path.display(), e))
+    }
+
+    pub fn parse(data: Vec<u8>) -> Result<Self, Error> {
+        if !data.starts_with(REGF_MAGIC) {
+            return Err(malformed("not a registry hive: missing regf signature"));
+        }
+        if data.len() < BASE_BLOCK_SIZE {
+            return Err(malformed("truncated base block"));
+        }
+
+        // transaction logs (.LOG1/.LOG2) are not replayed, a dirty hive can miss the latest changes
+        Ok(Self { data })
+    }
+
+    // returns the data of the cell at the given offset, offsets are relative to the first hive bin
+    fn cell(&self, offset: u32) -> Result<&[u8], Error> {
+        let start = BASE_BLOCK_SIZE + offset as usize;
+        let size = u32_at(&self.data, start)
+            .map(|size| (size as i32).unsigned_abs() as usize)
+            .ok_or_else(|| malformed(format!("cell offset {:#x} out of bounds", offset)))?;
+        if size < 4 {
+            return Err(malformed(format!("invalid cell size at {:#x}", offset)));
+        }
+
+        self.data
+            .get(start + 4..start + size)
+            .ok_or_else(|| malformed(format!("truncated cell at {:#x}", offset)))
+    }
+
+    fn key(&self, offset: u32) -> Result<Key<'_>, Error> {
+        let cell = self.cell(offset)?;
+        if !cell.starts_with(b"nk") {
+            return Err(malformed(format!("expected key node at {:#x}", offset)));
+        }
+
+        let truncated = || malformed(format!("truncated key node at {:#x}", offset));
+        let flags = u16_at(cell, 2).ok_or_else(truncated)?;
+        let modified = u64_at(cell, 4).ok_or_else(truncated)?;
+        let name_len = u16_at(cell, 72).ok_or_else(truncated)? as usize;
+        let name = cell.get(76..76 + name_len).ok_or_else(truncated)?;
+
+        Ok(Key {
+            hive: self,
+            cell: offset as usize,
+            name: decode_name(name, flags & KEY_COMP_NAME != 0),
+            modified: filetime_to_datetime(modified),
+        })
+    }
+
+    pub fn root(&self) -> Result<Key<'_>, Error> {
+        let offset = u32_at(&self.data, ROOT_CELL_OFFSET)
+            .ok_or_else(|| malformed("truncated base block"))?;
+        self.key(offset)
+    }
+
+    /// Opens a key by its backslash separated path, relative to the root of the hive.
+    pub fn open_key(&self, path: &str) -> Result<Option<Key<'_>>, Error> {
+        let mut key = self.root()?;
+        for name in path.split('\\').filter(|n| !n.is_empty()) {
+            key = match key.subkey(name)? {
+                Some(subkey) => subkey,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(key))
+    }
+
+    fn collect_subkeys(&self, list: u32, depth: usize, keys: &mut Vec<u32>) -> Result<(), Error> {
+        if depth > MAX_INDEX_DEPTH {
+            return Err(malformed(format!("subkey index too deep at {:#x}", list)));
+        }
+
+        let cell = self.cell(list)?;
+        let count = u16_at(cell, 2).ok_or_else(|| malformed("truncated subkey list"))? as usize;
+        let (stride, nested) = match cell.get(0..2) {
+            // fast leaf and hash leaf have a 4 bytes hint after each offset
+            Some(b"lf") | Some(b"lh") => (8, false),
+            Some(b"li") => (4, false),
+            Some(b"ri") => (4, true),
+            _ => return Err(malformed(format!("unknown subkey list at {:#x}", list))),
+        };
+
+        for idx in 0..count {
+            let offset =
+                u32_at(cell, 4 + idx * stride).ok_or_else(|| malformed("truncated subkey list"))?;
+            if nested {
+                self.collect_subkeys(offset, depth + 1, keys)?;
+            } else {
+                keys.push(offset);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn value(&self, offset: u32) -> Result<Value, Error> {
+        let cell = self.cell(offset)?;
+        if !cell.starts_with(b"vk") {
+            return Err(malformed(format!("expected value at {:#x}", offset)));
+        }
+
+        let truncated = || malformed(format!("truncated value at {:#x}", offset));
+        let name_len = u16_at(cell, 2).ok_or_else(truncated)? as usize;
+        let size = u32_at(cell, 4).ok_or_else(truncated)?;
+        let data_offset = u32_at(cell, 8).ok_or_else(truncated)?;
+        let kind = u32_at(cell, 12).ok_or_else(truncated)?;
+        let flags = u16_at(cell, 16).ok_or_else(truncated)?;
+        let name = cell.get(20..20 + name_len).ok_or_else(truncated)?;
+
+        let data = if size & DATA_INLINE != 0 {
+            // up to 4 bytes are stored in place of the offset
+            let size = ((size & !DATA_INLINE) as usize).min(4);
+            data_offset.to_le_bytes()[..size].to_vec()
+        } else {
+            self.value_data(data_offset, size as usize)?
+        };
+
+        Ok(Value {
+            name: decode_name(name, flags & VALUE_COMP_NAME != 0),
+            kind,
+            data,
+        })
+    }
+
+    fn value_data(&self, offset: u32, size: usize) -> Result<Vec<u8>, Error> {
+        let cell = self.cell(offset)?;
+
+        // values bigger than a segment are split in a list of cells
+        if size > BIG_DATA_SEGMENT_SIZE && cell.starts_with(b"db") {
+            let count = u16_at(cell, 2).ok_or_else(|| malformed("truncated big data"))? as usize;
+            let list =
+                self.cell(u32_at(cell, 4).ok_or_else(|| malformed("truncated big data"))?)?;
+            // the size comes from the hive, the segments are the upper bound of the data
+            let mut data = Vec::with_capacity(size.min(count * BIG_DATA_SEGMENT_SIZE));
+            for idx in 0..count {
+                let segment =
+                    u32_at(list, idx * 4).ok_or_else(|| malformed("truncated big data list"))?;
+                let segment = self.cell(segment)?;
+                let remaining = size - data.len();
+                data.extend_from_slice(&segment[..segment.len().min(remaining)]);
+            }
+            if data.len() < size {
+                return Err(malformed(format!(
+                    "big data at {:#x} has {} of {} bytes",
+                    offset,
+                    data.len(),
+                    size
+                )));
+            }
+            return Ok(data);
+        }
+
+        cell.get(..size)
+            .map(|data| data.to_vec())
+            .ok_or_else(|| malformed(format!("truncated value data at {:#x}", offset)))
+    }
+}
+
+impl<'a> Key<'a> {
+    fn node(&self) -> Result<&'a [u8], Error> {
+        self.hive.cell(self.cell as u32)
+    }
+
+    /// Returns the subkeys one by one, an unreadable key node doesn't hide its siblings.
+    pub fn subkeys(&self) -> Result<Vec<Result<Key<'a>, Error>>, Error> {
+        let node = self.node()?;
+        let count = u32_at(node, 20).ok_or_else(|| malformed("truncated key node"))?;
+        let list = u32_at(node, 28).ok_or_else(|| malformed("truncated key node"))?;
+        if count == 0 || list == u32::MAX {
+            return Ok(vec![]);
+        }
+
+        let mut offsets = vec![];
+        self.hive.collect_subkeys(list, 0, &mut offsets)?;
+        Ok(offsets
+            .into_iter()
+            .map(|offset| self.hive.key(offset))
+            .collect())
+    }
+
+    /// Returns the subkey with the given name, names are case insensitive.
+    pub fn subkey(&self, name: &str) -> Result<Option<Key<'a>>, Error> {
+        // an unreadable key is only reported when none of the others matches, it could be the one
+        let mut error = None;
+        for key in self.subkeys()? {
+            match key {
+                Ok(key) if key.name.eq_ignore_ascii_case(name) => return Ok(Some(key)),
+                Ok(_) => {}
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        error.map_or(Ok(None), Err)
+    }
+
+    /// Returns the values one by one, an unreadable value cell doesn't hide the others.
+    pub fn values(&self) -> Result<Vec<Result<Value, Error>>, Error> {
+        let node = self.node()?;
+        let count = u32_at(node, 36).ok_or_else(|| malformed("truncated key node"))? as usize;
+        let list = u32_at(node, 40).ok_or_else(|| malformed("truncated key node"))?;
+        if count == 0 || list == u32::MAX {
+            return Ok(vec![]);
+        }
+
+        let list = self.hive.cell(list)?;
+        Ok((0..count)
+            .map(|idx| {
+                let offset =
+                    u32_at(list, idx * 4).ok_or_else(|| malformed("truncated value list"))?;
+                self.hive.value(offset)
+            })
+            .collect())
+    }
+}
+
+/// Formats registry data as a string, unknown types are dumped as a list of bytes.
+pub(crate) fn format_value(kind: u32, data: &[u8]) -> String {
+    match kind {
+        REG_SZ | REG_EXPAND_SZ | REG_MULTI_SZ => {
+            let mut s = decode_name(data, false);
+            while s.ends_with('\u{0}') {
+                s.pop();
+            }
+            if kind == REG_MULTI_SZ {
+                s.replace('\u{0}', "\n")
+            } else {
+                s
+            }
+        }
+        REG_DWORD if data.len() >= 4 => u32_at(data, 0).unwrap_or_default().to_string(),
+        REG_QWORD if data.len() >= 8 => u64_at(data, 0).unwrap_or_default().to_string(),
+        _ => format!("{:?}", data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a base block followed by the given cells, returns the offset of each of them
+    fn hive(cells: &[Vec<u8>]) -> (Hive, Vec<u32>) {
+        let mut data = REGF_MAGIC.to_vec();
+        data.resize(BASE_BLOCK_SIZE, 0);
+        let mut offsets = vec![];
+        for cell in cells {
+            offsets.push((data.len() - BASE_BLOCK_SIZE) as u32);
+            // allocated cells have a negative size
+            data.extend_from_slice(&(-(cell.len() as i32 + 4)).to_le_bytes());
+            data.extend_from_slice(cell);
+        }
+        (Hive::parse(data).unwrap(), offsets)
+    }
+
+    fn key_node(name: &str, subkeys: (u32, u32), values: (u32, u32)) -> Vec<u8> {
+        let mut cell = vec![0; 76];
+        cell[..2].copy_from_slice(b"nk");
+        cell[2..4].copy_from_slice(&KEY_COMP_NAME.to_le_bytes());
+        cell[20..24].copy_from_slice(&subkeys.0.to_le_bytes());
+        cell[28..32].copy_from_slice(&subkeys.1.to_le_bytes());
+        cell[36..40].copy_from_slice(&values.0.to_le_bytes());
+        cell[40..44].copy_from_slice(&values.1.to_le_bytes());
+        cell[72..74].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        cell.extend_from_slice(name.as_bytes());
+        cell
+    }
+
+    fn dword_value(name: &str, data: u32) -> Vec<u8> {
+        let mut cell = vec![0; 20];
+        cell[..2].copy_from_slice(b"vk");
+        cell[2..4].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        cell[4..8].copy_from_slice(&(4 | DATA_INLINE).to_le_bytes());
+        cell[8..12].copy_from_slice(&data.to_le_bytes());
+        cell[12..16].copy_from_slice(&REG_DWORD.to_le_bytes());
+        cell[16..18].copy_from_slice(&VALUE_COMP_NAME.to_le_bytes());
+        cell.extend_from_slice(name.as_bytes());
+        cell
+    }
+
+    // a db cell pointing to a list of two segments, as the last cells of the hive
+    fn big_data(first: usize, second: usize) -> (Hive, u32) {
+        let first_offset = 0u32;
+        let second_offset = first as u32 + 4;
+        let list_offset = second_offset + second as u32 + 4;
+        let mut list = first_offset.to_le_bytes().to_vec();
+        list.extend_from_slice(&second_offset.to_le_bytes());
+        let mut db = b"db".to_vec();
+        db.extend_from_slice(&2u16.to_le_bytes());
+        db.extend_from_slice(&list_offset.to_le_bytes());
+
+        let (hive, offsets) = hive(&[vec![b'a'; first], vec![b'b'; second], list, db]);
+        (hive, offsets[3])
+    }
+
+    #[test]
+    fn reads_big_data() {
+        let (hive, db) = big_data(BIG_DATA_SEGMENT_SIZE, 100);
+        let data = hive.value_data(db, BIG_DATA_SEGMENT_SIZE + 10).unwrap();
+        assert_eq!(data.len(), BIG_DATA_SEGMENT_SIZE + 10);
+        assert!(data[..BIG_DATA_SEGMENT_SIZE].iter().all(|b| *b == b'a'));
+        assert_eq!(&data[BIG_DATA_SEGMENT_SIZE..], b"bbbbbbbbbb");
+    }
+
+    #[test]
+    fn rejects_short_big_data() {
+        let (hive, db) = big_data(BIG_DATA_SEGMENT_SIZE, 100);
+        assert!(hive.value_data(db, 3 * BIG_DATA_SEGMENT_SIZE).is_err());
+        assert!(hive.value_data(db, i32::MAX as usize).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_cells() {
+        let (hive, offsets) = hive(&[b"small".to_vec()]);
+        assert_eq!(hive.value_data(offsets[0], 5).unwrap(), b"small");
+        assert!(hive.value_data(offsets[0], 6).is_err());
+        assert!(hive.value_data(0x10000, 4).is_err());
+    }
+
+    #[test]
+    fn skips_unreadable_entries() {
+        let broken = 0xff_0000u32.to_le_bytes();
+        let mut cells = vec![dword_value("Version", 7)];
+        let offset = |cells: &[Vec<u8>]| cells.iter().map(|c| c.len() as u32 + 4).sum::<u32>();
+
+        let value_list = offset(&cells);
+        cells.push([0u32.to_le_bytes(), broken].concat());
+        let child = offset(&cells);
+        cells.push(key_node("App", (0, u32::MAX), (0, u32::MAX)));
+        let subkey_list = offset(&cells);
+        let mut list = b"lf".to_vec();
+        list.extend_from_slice(&2u16.to_le_bytes());
+        for key in [child.to_le_bytes(), broken] {
+            list.extend_from_slice(&key);
+            list.extend_from_slice(&[0; 4]);
+        }
+        cells.push(list);
+        cells.push(key_node("Uninstall", (2, subkey_list), (2, value_list)));
+
+        let (hive, offsets) = hive(&cells);
+        let key = hive.key(offsets[4]).unwrap();
+
+        let subkeys = key.subkeys().unwrap();
+        assert_eq!(subkeys.len(), 2);
+        assert_eq!(subkeys[0].as_ref().unwrap().name, "App");
+        assert!(subkeys[1].is_err());
+        assert_eq!(key.subkey("app").unwrap().unwrap().name, "App");
+        assert!(key.subkey("missing").is_err());
+
+        let values = key.values().unwrap();
+        assert_eq!(values.len(), 2);
+        let value = values[0].as_ref().unwrap();
+        assert_eq!(value.name, "Version");
+        assert_eq!(format_value(value.kind, &value.data), "7");
+        assert!(values[1].is_err());
+    }
+}
@@ -1,15 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "windows")]
 use std::process::Command;
 
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
-use crate::collector;
+use crate::collector::{self, Collected};
 use crate::component::{ComponentTrait, Kind};
+use crate::pe;
+use crate::purl::Purl;
 use crate::Error;
 
+#[cfg(target_os = "windows")]
 mod api;
+mod hive;
+mod reg;
+
+const SOFTWARE_HIVE_PATH: &str = "Windows/System32/config/SOFTWARE";
+const DRIVERS_PATH: &str = "Windows/System32/drivers";
+const CURRENT_VERSION_KEY: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion";
+const UNINSTALL_LOCATIONS: &[&str] = &[
+    "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+    "SOFTWARE\\Wow6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall",
+];
 
 lazy_static! {
     static ref MICROSOFT_DEFAULT_PUBLISHERS: Vec<String> = vec!["Microsoft".to_string(),];
@@ -50,6 +66,14 @@ impl ComponentTrait for OS {
     fn publishers(&self) -> &Vec<String> {
         &MICROSOFT_DEFAULT_PUBLISHERS
     }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(
+            Purl::new("generic", &self.name)
+                .with_namespace("microsoft")
+                .with_version(&self.version),
+        )
+    }
 }
 
 #[allow(dead_code)]
@@ -88,9 +112,8 @@ impl Application {
         }
 
         for path in ["InstallLocation", "InstallSource", "BundleCachePath"] {
-            let location = zelf.properties.get(path);
-            if location.is_some() && !location.unwrap().is_empty() {
-                zelf.path = location.unwrap().to_string();
+            if let Some(location) = zelf.properties.get(path).filter(|l| !l.is_empty()) {
+                zelf.path = location.to_string();
                 break;
             }
         }
@@ -131,10 +154,20 @@ impl ComponentTrait for Application {
     fn publishers(&self) -> &Vec<String> {
         &self.publishers
     }
+
+    fn purl(&self) -> Option<Purl> {
+        if self.name.is_empty() {
+            return None;
+        }
+
+        Some(Purl::new("generic", &self.name).with_version(&self.version))
+    }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+// without /v driverquery only reports a few of the columns
+#[serde(default)]
 struct Driver {
     #[serde(rename = "Module Name")]
     pub module_name: String,
@@ -172,6 +205,10 @@ struct Driver {
     pub publishers: Vec<String>,
     #[serde(skip_deserializing)]
     pub version: String,
+    #[serde(skip_deserializing)]
+    pub file_version: String,
+    #[serde(skip_deserializing)]
+    pub original_filename: String,
 }
 
 impl Driver {
@@ -180,14 +217,35 @@ impl Driver {
             self.link_date = Utc
                 .datetime_from_str(&self.link_date_string, "%m/%e/%Y %l:%M:%S %p")
                 .map_err(|e| {
-                    format!(
-                        "could not parse driver datetime '{}': {:?}",
-                        &self.link_date_string, e
+                    Error::parse(
+                        "driver link date",
+                        format!("'{}': {}", &self.link_date_string, e),
                     )
                 })?;
         }
         Ok(())
     }
+
+    fn set_version_info(&mut self, info: &pe::VersionInfo) {
+        if let Some(version) = info.product_version() {
+            self.version = version;
+        }
+        if let Some(company) = info.company_name() {
+            self.publishers = vec![company.to_owned()];
+        }
+        if let Some(file_version) = info.file_version() {
+            self.file_version = file_version;
+        }
+        if let Some(filename) = info.original_filename() {
+            self.original_filename = filename.to_owned();
+        }
+        if self.display_name.is_empty() {
+            if let Some(description) = info.file_description() {
+                self.display_name = description.to_owned();
+                self.description = description.to_owned();
+            }
+        }
+    }
 }
 
 impl ComponentTrait for Driver {
@@ -218,93 +276,388 @@ impl ComponentTrait for Driver {
     fn publishers(&self) -> &Vec<String> {
         &self.publishers
     }
+
+    fn purl(&self) -> Option<Purl> {
+        Some(Purl::new("generic", &self.module_name).with_version(&self.version))
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        [
+            ("file_version", &self.file_version),
+            ("original_filename", &self.original_filename),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (name.to_owned(), value.clone()))
+        .collect()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct UninstallEntry {
+    pub key_name: String,
+    pub modified: NaiveDateTime,
+    pub properties: HashMap<String, String>,
+}
+
+// NTFS is case insensitive while the filesystem the image is mounted on might not be, the names
+// are matched inside the image and the links are only resolved within it
+pub(crate) fn image_path(ctx: &collector::Context, path: &str) -> PathBuf {
+    let mut relative = PathBuf::from("/");
+    for name in path.split('/').filter(|n| !n.is_empty()) {
+        let exact = relative.join(name);
+        if ctx.path(&exact).exists() {
+            relative = exact;
+            continue;
+        }
+
+        relative = fs::read_dir(ctx.path(&relative))
+            .ok()
+            .and_then(|entries| {
+                entries
+                    .flatten()
+                    .find(|entry| {
+                        entry
+                            .file_name()
+                            .to_string_lossy()
+                            .eq_ignore_ascii_case(name)
+                    })
+                    .map(|entry| relative.join(entry.file_name()))
+            })
+            .unwrap_or(exact);
+    }
+    ctx.path(relative)
+}
+
+pub(crate) fn is_available(ctx: &collector::Context) -> bool {
+    image_path(ctx, SOFTWARE_HIVE_PATH).is_file()
+}
+
+// the values of a key by their lowercase name, unreadable values are reported
+fn hive_strings(key: &hive::Key, collected: &mut Collected) -> HashMap<String, String> {
+    let mut strings = HashMap::new();
+    for value in key.values().unwrap_or_else(|e| vec![Err(e)]) {
+        match value {
+            Ok(value) => {
+                let data = hive::format_value(value.kind, &value.data);
+                if !data.is_empty() {
+                    strings.insert(value.name.to_lowercase(), data);
+                }
+            }
+            Err(e) => collected.warn(Error::parse(&key.name, e)),
+        }
+    }
+    strings
+}
+
+// the SOFTWARE hive is mounted as HKLM\SOFTWARE
+fn hive_key_path(location: &str) -> &str {
+    location.strip_prefix("SOFTWARE\\").unwrap_or(location)
+}
+
+// malformed rows are skipped and reported
+fn parse_driverquery(data: &str, collected: &mut Collected) -> Vec<Driver> {
+    let mut drivers = vec![];
+
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    for result in rdr.deserialize() {
+        let mut driver: Driver = match result {
+            Ok(driver) => driver,
+            Err(e) => {
+                collected.warn(Error::parse("driverquery csv", e));
+                continue;
+            }
+        };
+
+        // the link date format depends on the locale of the system
+        if let Err(e) = driver.parse() {
+            log::warn!("{}", e);
+        }
+
+        drivers.push(driver);
+    }
+
+    drivers
+}
+
+fn applications(entries: Vec<UninstallEntry>, collected: &mut Collected) {
+    for entry in entries {
+        if entry.properties.contains_key("DisplayName") {
+            collected.push(Application::new(
+                entry.key_name,
+                entry.modified,
+                entry.properties,
+            ));
+        } else {
+            log::debug!("skipping uninstall entry: {:?}", &entry);
+        }
+    }
 }
 
-#[derive(Default)]
-pub(crate) struct Collector {}
+/// Builds the drivers from the output of "driverquery /v /fo csv".
+pub(crate) fn import_driverquery(data: &str) -> Result<Collected, Error> {
+    let mut collected = Collected::new("windows");
+    for driver in parse_driverquery(data, &mut collected) {
+        collected.push(driver);
+    }
+    Ok(collected)
+}
+
+/// Builds the applications from a .reg export of the uninstall keys.
+pub(crate) fn import_uninstall_export(data: &str) -> Result<Collected, Error> {
+    let mut collected = Collected::new("windows");
+    applications(reg::parse_uninstall_export(data)?, &mut collected);
+    Ok(collected)
+}
+
+pub(crate) struct Collector {
+    ctx: collector::Context,
+}
+
+impl Collector {
+    pub fn new(ctx: &collector::Context) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    fn read_image_os(
+        &self,
+        hive: &hive::Hive,
+        collected: &mut Collected,
+    ) -> Result<Box<dyn ComponentTrait>, Error> {
+        let key = hive
+            .open_key(hive_key_path(CURRENT_VERSION_KEY))?
+            .ok_or_else(|| {
+                Error::parse(
+                    SOFTWARE_HIVE_PATH,
+                    format!("{} not found", CURRENT_VERSION_KEY),
+                )
+            })?;
+        let strings = hive_strings(&key, collected);
+        let string = |name: &str| strings.get(&name.to_lowercase()).cloned();
+
+        // same format of the ver command: major.minor.build.revision
+        let mut parts = match (
+            string("CurrentMajorVersionNumber"),
+            string("CurrentMinorVersionNumber"),
+        ) {
+            (Some(major), Some(minor)) => vec![major, minor],
+            // windows 8.1 and older
+            _ => string("CurrentVersion").into_iter().collect(),
+        };
+        parts.extend(string("CurrentBuildNumber").or_else(|| string("CurrentBuild")));
+        parts.extend(string("UBR"));
+
+        Ok(Box::new(OS {
+            name: "Microsoft Windows".to_owned(),
+            version: parts.join("."),
+        }))
+    }
+
+    // an unreadable key or value is reported and skipped, like the live registry
+    fn read_image_uninstall_locations(
+        &self,
+        hive: &hive::Hive,
+        collected: &mut Collected,
+    ) -> Vec<UninstallEntry> {
+        let mut found = vec![];
+
+        for location in UNINSTALL_LOCATIONS {
+            let uninstall = match hive.open_key(hive_key_path(location)) {
+                Ok(Some(key)) => key,
+                Ok(None) => {
+                    // 32 bit images don't have a Wow6432Node
+                    log::debug!("{} not found", location);
+                    continue;
+                }
+                Err(e) => {
+                    collected.warn(Error::parse(location, e));
+                    continue;
+                }
+            };
+            let sub_keys = match uninstall.subkeys() {
+                Ok(sub_keys) => sub_keys,
+                Err(e) => {
+                    collected.warn(Error::parse(location, e));
+                    continue;
+                }
+            };
+
+            for sub_key in sub_keys {
+                let sub_key = match sub_key {
+                    Ok(sub_key) => sub_key,
+                    Err(e) => {
+                        collected.warn(Error::parse(location, e));
+                        continue;
+                    }
+                };
+                let source = format!("{}\\{}", location, sub_key.name);
+                let values = match sub_key.values() {
+                    Ok(values) => values,
+                    Err(e) => {
+                        collected.warn(Error::parse(&source, e));
+                        continue;
+                    }
+                };
+
+                let mut properties = HashMap::new();
+                for value in values {
+                    match value {
+                        Ok(value) => {
+                            properties.insert(
+                                value.name.clone(),
+                                hive::format_value(value.kind, &value.data),
+                            );
+                        }
+                        Err(e) => collected.warn(Error::parse(&source, e)),
+                    }
+                }
+
+                found.push(UninstallEntry {
+                    key_name: sub_key.name.clone(),
+                    modified: sub_key.modified,
+                    properties,
+                });
+            }
+        }
+
+        found
+    }
+
+    fn read_image_drivers(&self, collected: &mut Collected) -> Result<(), Error> {
+        let drivers_path = image_path(&self.ctx, DRIVERS_PATH);
+        let entries = fs::read_dir(&drivers_path).map_err(|e| Error::io(&drivers_path, e))?;
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let is_driver = Path::new(&file_name)
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("sys"))
+                .unwrap_or(false);
+            // drivers can be symlinks, they are only followed within the image
+            let path = image_path(&self.ctx, &format!("{}/{}", DRIVERS_PATH, file_name));
+            if !is_driver || !path.is_file() {
+                continue;
+            }
+
+            let mut driver = Driver {
+                module_name: Path::new(&file_name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path: format!("C:\\Windows\\System32\\drivers\\{}", file_name),
+                ..Default::default()
+            };
+            if let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) {
+                driver.link_date = DateTime::from(modified);
+            }
+
+            match pe::read_version_info(&path) {
+                Ok(info) => driver.set_version_info(&info),
+                Err(e) => log::debug!("can't read version information: {}", e),
+            }
+            if driver.display_name.is_empty() {
+                driver.display_name = driver.module_name.clone();
+            }
+
+            collected.push(driver);
+        }
+
+        Ok(())
+    }
+
+    fn collect_image(&self) -> Result<Collected, Error> {
+        let hive = hive::Hive::open(&image_path(&self.ctx, SOFTWARE_HIVE_PATH))?;
 
+        let mut collected = Collected::new("windows");
+        if let Err(e) = self.read_image_drivers(&mut collected) {
+            collected.warn(e);
+        }
+        match self.read_image_os(&hive, &mut collected) {
+            Ok(os) => collected.components.push(os),
+            Err(e) => collected.warn(e),
+        }
+        let entries = self.read_image_uninstall_locations(&hive, &mut collected);
+        applications(entries, &mut collected);
+
+        Ok(collected)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn collect_live(&self) -> Result<Collected, Error> {
+        Err(Error::unsupported(
+            "the running system can only be inspected on windows, use --root with a mounted image",
+        ))
+    }
+}
+
+#[cfg(target_os = "windows")]
 impl Collector {
     fn collect_os(&self) -> Result<Box<dyn ComponentTrait>, Error> {
         let ver = Command::new("cmd.exe")
-            .args(&["/c", "ver"])
+            .args(["/c", "ver"])
             .output()
-            .map_err(|e| format!("could not execute ver: {:?}", e))?;
+            .map_err(|e| Error::command("ver", None, e.to_string().as_bytes()))?;
 
         if !ver.status.success() {
-            return Err(format!(
-                "ver exit status {:?}: {:?}",
-                ver.status,
-                String::from_utf8_lossy(&ver.stderr)
-            ));
+            return Err(Error::command("ver", ver.status.code(), &ver.stderr));
         }
 
-        let raw = String::from_utf8_lossy(&ver.stdout).into_owned();
+        let raw = String::from_utf8_lossy(&ver.stdout);
+        // Microsoft Windows [Version 10.0.19045.2965]
+        let version = raw
+            .trim()
+            .split_once("[Version ")
+            .map(|(_, version)| version.trim_end_matches(']').to_owned())
+            .ok_or_else(|| Error::parse("ver", format!("unexpected output '{}'", raw.trim())))?;
 
         Ok(Box::new(OS {
             name: "Microsoft Windows".to_owned(),
-            version: raw
-                .trim()
-                .to_string()
-                .split("[Version ")
-                .collect::<Vec<&str>>()[1]
-                .replace("]", ""),
+            version,
         }))
     }
 
-    fn collect_drivers(&self) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
-        let mut comps: Vec<Box<dyn ComponentTrait>> = vec![];
-
+    fn collect_drivers(&self, collected: &mut Collected) -> Result<(), Error> {
         let driverquery = Command::new("driverquery.exe")
             .arg("/v")
-            .args(&["/FO", "CSV"])
+            .args(["/FO", "CSV"])
             .output()
-            .map_err(|e| format!("could not execute driverquery.exe: {:?}", e))?;
+            .map_err(|e| Error::command("driverquery.exe", None, e.to_string().as_bytes()))?;
 
         if !driverquery.status.success() {
-            return Err(format!(
-                "driverquery exit status {:?}: {:?}",
-                driverquery.status,
-                String::from_utf8_lossy(&driverquery.stderr)
+            return Err(Error::command(
+                "driverquery.exe",
+                driverquery.status.code(),
+                &driverquery.stderr,
             ));
         }
 
         let raw_csv = String::from_utf8_lossy(&driverquery.stdout);
-        let mut rdr = csv::Reader::from_reader(raw_csv.as_bytes());
-        for result in rdr.deserialize() {
-            let mut driver: Driver =
-                result.map_err(|e| format!("could not deserialize driver record: {:?}", e))?;
-
-            driver.parse()?;
-
-            let version = api::parse_file_version(driver.path());
-            if let Ok(v) = version {
-                driver.version = v;
-            } else {
-                log::warn!("{:?}", version.err().unwrap());
+        for mut driver in parse_driverquery(&raw_csv, collected) {
+            match pe::read_version_info(Path::new(driver.path())) {
+                Ok(info) => driver.set_version_info(&info),
+                Err(e) => log::warn!("can't read version information: {}", e),
             }
 
-            comps.push(Box::new(driver));
+            collected.push(driver);
         }
 
-        Ok(comps)
+        Ok(())
     }
 
-    fn collect_apps(&self) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
-        let mut comps: Vec<Box<dyn ComponentTrait>> = vec![];
+    fn collect_live(&self) -> Result<Collected, Error> {
+        let mut collected = Collected::new("windows");
 
-        for entry in api::enum_registry_uninstall_locations()? {
-            if entry.properties.contains_key("DisplayName") {
-                comps.push(Box::new(Application::new(
-                    entry.key_name,
-                    entry.modified,
-                    entry.properties,
-                )));
-            } else {
-                log::debug!("skipping uninstall entry: {:?}", &entry);
-            }
+        match self.collect_os() {
+            Ok(os) => collected.components.push(os),
+            Err(e) => collected.warn(e),
+        }
+        if let Err(e) = self.collect_drivers(&mut collected) {
+            collected.warn(e);
         }
+        let entries = api::enum_registry_uninstall_locations(&mut collected);
+        applications(entries, &mut collected);
 
-        Ok(comps)
+        Ok(collected)
     }
 }
 
@@ -313,20 +666,84 @@ impl collector::Collector for Collector {
         Ok(())
     }
 
-    fn collect_from_json(&self, _: &str) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
-        Err("not implemented".to_owned())
+    fn collect_from_json(&self, data: &str) -> Result<Collected, Error> {
+        if reg::is_export(data) {
+            import_uninstall_export(data)
+        } else {
+            import_driverquery(data)
+        }
     }
 
-    fn collect(&self) -> Result<Vec<Box<dyn ComponentTrait>>, Error> {
+    fn collect(&self) -> Result<Collected, Error> {
         log::info!("collecting applications and drivers, please wait ...");
 
-        let os = self.collect_os()?;
-        let mut drivers = self.collect_drivers()?;
-        let mut apps = self.collect_apps()?;
+        if self.ctx.is_live() {
+            self.collect_live()
+        } else {
+            self.collect_image()
+        }
+    }
+}
 
-        drivers.push(os);
-        drivers.append(&mut apps);
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn image_path_ignores_case() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("Windows/System32/config")).unwrap();
+        fs::write(root.path().join("Windows/System32/config/SOFTWARE"), "").unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        assert_eq!(
+            image_path(&ctx, "/windows/SYSTEM32/Config/software"),
+            root.path().join("Windows/System32/config/SOFTWARE")
+        );
+        assert_eq!(
+            image_path(&ctx, "/windows/missing/file"),
+            root.path().join("Windows/missing/file")
+        );
+    }
+
+    #[test]
+    fn image_path_stays_in_image() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("evil.sys"), "").unwrap();
+        fs::create_dir_all(root.path().join("Windows/System32")).unwrap();
+        symlink(outside.path(), root.path().join("Windows/System32/drivers")).unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let path = image_path(&ctx, "/Windows/System32/DRIVERS/evil.sys");
+        assert!(path.starts_with(root.path()));
+        assert!(!path.exists());
+    }
 
-        Ok(drivers)
+    #[test]
+    fn keeps_symlinked_drivers_in_image() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("evil.sys"), "").unwrap();
+        let drivers = root.path().join(DRIVERS_PATH);
+        fs::create_dir_all(&drivers).unwrap();
+        fs::write(drivers.join("disk.sys"), "").unwrap();
+        symlink(outside.path().join("evil.sys"), drivers.join("evil.sys")).unwrap();
+        symlink(
+            "/Windows/System32/drivers/disk.sys",
+            drivers.join("alias.sys"),
+        )
+        .unwrap();
+
+        let ctx = collector::Context::new(Some(root.path().to_path_buf())).unwrap();
+        let mut collected = Collected::new("windows");
+        Collector::new(&ctx)
+            .read_image_drivers(&mut collected)
+            .unwrap();
+
+        let mut names: Vec<&str> = collected.components.iter().map(|c| c.name()).collect();
+        names.sort();
+        assert_eq!(names, vec!["alias", "disk"]);
     }
 }
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use super::{hive, UninstallEntry};
+use crate::Error;
+
+// https://support.microsoft.com/en-us/topic/how-to-add-modify-or-delete-registry-subkeys-and-values-by-using-a-registration-entries-reg-file-9c7f37cf-a5e9-e1cd-c4fa-2a26218a1a23
+const HEADERS: &[&str] = &["Windows Registry Editor Version 5.00", "REGEDIT4"];
+const REG_BINARY: u32 = 3;
+
+pub(crate) fn is_export(data: &str) -> bool {
+    let first = data.trim_start().lines().next().unwrap_or_default().trim();
+    HEADERS.contains(&first)
+}
+
+fn parse_string(raw: &str) -> Option<String> {
+    let raw = raw.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            value.extend(chars.next());
+        } else {
+            value.push(c);
+        }
+    }
+    Some(value)
+}
+
+fn parse_hex(raw: &str) -> Option<Vec<u8>> {
+    raw.split(',')
+        .map(|b| b.trim())
+        .filter(|b| !b.is_empty())
+        .map(|b| u8::from_str_radix(b, 16).ok())
+        .collect()
+}
+
+// "name"="string", "name"=dword:0000001, "name"=hex(7):41,00,00,00, @="default"
+fn parse_value(line: &str) -> Option<(String, String)> {
+    let (name, data) = if let Some(rest) = line.strip_prefix('@') {
+        (String::new(), rest.strip_prefix('=')?)
+    } else {
+        // the name is a quoted string that can contain escaped quotes
+        let mut end = None;
+        let mut escaped = false;
+        for (idx, c) in line.char_indices().skip(1) {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => {
+                    end = Some(idx);
+                    break;
+                }
+                _ => escaped = false,
+            }
+        }
+        let end = end?;
+        (
+            parse_string(&line[..=end])?,
+            line[end + 1..].trim_start().strip_prefix('=')?,
+        )
+    };
+
+    let data = data.trim();
+    let value = if data.starts_with('"') {
+        parse_string(data)?
+    } else if let Some(dword) = data.strip_prefix("dword:") {
+        u32::from_str_radix(dword, 16).ok()?.to_string()
+    } else if let Some(hex) = data.strip_prefix("hex:") {
+        hive::format_value(REG_BINARY, &parse_hex(hex)?)
+    } else if let Some(rest) = data.strip_prefix("hex(") {
+        let (kind, hex) = rest.split_once("):")?;
+        hive::format_value(u32::from_str_radix(kind, 16).ok()?, &parse_hex(hex)?)
+    } else {
+        // "-" deletes the value, anything else is not something regedit writes
+        return None;
+    };
+
+    Some((name, value))
+}
+
+// exports don't contain the last write time of the keys, the install date is the closest thing
+fn install_date(properties: &HashMap<String, String>) -> NaiveDateTime {
+    properties
+        .get("InstallDate")
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap_or_default()
+}
+
+/// Parses a .reg export of the uninstall keys (regedit or "reg export").
+pub(crate) fn parse_uninstall_export(data: &str) -> Result<Vec<UninstallEntry>, Error> {
+    if !is_export(data) {
+        return Err(Error::parse("registry export", "missing header"));
+    }
+
+    let mut entries = vec![];
+    let mut current: Option<UninstallEntry> = None;
+    let mut pending = String::new();
+
+    for line in data
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .skip(1)
+    {
+        let line = line.trim();
+        // long hex values are split on multiple lines ending with a backslash
+        if let Some(continued) = line.strip_suffix('\\') {
+            pending.push_str(continued);
+            continue;
+        }
+        pending.push_str(line);
+        let line = std::mem::take(&mut pending);
+
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            entries.extend(current.take());
+
+            // only the direct subkeys of an Uninstall key are entries
+            if let Some((parent, name)) = path.rsplit_once('\\') {
+                let is_uninstall = parent
+                    .rsplit('\\')
+                    .next()
+                    .map(|key| key.eq_ignore_ascii_case("Uninstall"))
+                    .unwrap_or(false);
+                if is_uninstall && !path.starts_with('-') {
+                    current = Some(UninstallEntry {
+                        key_name: name.to_owned(),
+                        modified: NaiveDateTime::default(),
+                        properties: HashMap::new(),
+                    });
+                }
+            }
+        } else if let Some(entry) = current.as_mut() {
+            match parse_value(&line) {
+                Some((name, value)) => {
+                    entry.properties.insert(name, value);
+                }
+                None => log::debug!("skipping registry value: {}", &line),
+            }
+        }
+    }
+
+    entries.extend(current.take());
+
+    for entry in entries.iter_mut() {
+        entry.modified = install_date(&entry.properties);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::utils;
+
+    const EXPORT: &str = r#"Windows Registry Editor Version 5.00
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall]
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\App]
+"DisplayName"="The \"Best\" App"
+"DisplayVersion"="2.1.0"
+"InstallLocation"="C:\\Program Files\\App\\"
+"Quoted \"Name\""="value"
+"DisplayIcon"=hex(2):25,00,50,00,72,00,6f,00,67,00,72,00,61,00,6d,00,46,00,69,\
+  00,6c,00,65,00,73,00,25,00,5c,00,41,00,70,00,70,00,5c,00,61,00,70,00,70,00,\
+  2e,00,65,00,78,00,65,00,00,00
+"NoModify"=dword:00000001
+"EstimatedSize"=dword:0000a2c4
+"InstallDate"="20240131"
+"Deleted"=-
+@="default"
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\App\Nested]
+"DisplayName"="Not an entry"
+
+; a comment
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Run]
+"App"="C:\\Program Files\\App\\app.exe"
+
+[-HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Removed]
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\uninstall\{6A1B2C3D-0000-4000-8000-000000000000}]
+"DisplayName"="Legacy Tool"
+"#;
+
+    fn property<'a>(entry: &'a UninstallEntry, name: &str) -> Option<&'a str> {
+        entry.properties.get(name).map(|value| value.as_str())
+    }
+
+    #[test]
+    fn parses_uninstall_export() {
+        let entries = parse_uninstall_export(EXPORT).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.key_name.as_str()).collect();
+        assert_eq!(names, vec!["App", "{6A1B2C3D-0000-4000-8000-000000000000}"]);
+
+        let app = &entries[0];
+        assert_eq!(property(app, "DisplayName"), Some("The \"Best\" App"));
+        assert_eq!(property(app, "DisplayVersion"), Some("2.1.0"));
+        assert_eq!(
+            property(app, "InstallLocation"),
+            Some("C:\\Program Files\\App\\")
+        );
+        assert_eq!(property(app, "Quoted \"Name\""), Some("value"));
+        // expandable strings are UTF-16 and split on several lines
+        assert_eq!(
+            property(app, "DisplayIcon"),
+            Some("%ProgramFiles%\\App\\app.exe")
+        );
+        assert_eq!(property(app, "NoModify"), Some("1"));
+        assert_eq!(property(app, "EstimatedSize"), Some("41668"));
+        assert_eq!(property(app, "Deleted"), None);
+        assert_eq!(property(app, ""), Some("default"));
+        assert_eq!(
+            app.modified,
+            NaiveDate::from_ymd_opt(2024, 1, 31)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        );
+
+        let legacy = &entries[1];
+        assert_eq!(property(legacy, "DisplayName"), Some("Legacy Tool"));
+        assert_eq!(legacy.modified, NaiveDateTime::default());
+    }
+
+    #[test]
+    fn parses_utf16_exports() {
+        // regedit writes UTF-16 little endian with a byte order mark and CRLF line endings
+        let mut data = vec![0xff, 0xfe];
+        data.extend(
+            EXPORT
+                .replace('\n', "\r\n")
+                .encode_utf16()
+                .flat_map(|c| c.to_le_bytes()),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("uninstall.reg");
+        fs::write(&path, data).unwrap();
+
+        let text = utils::read_text(&path).unwrap();
+        assert!(is_export(&text));
+        let entries = parse_uninstall_export(&text).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            property(&entries[0], "DisplayIcon"),
+            Some("%ProgramFiles%\\App\\app.exe")
+        );
+        assert_eq!(property(&entries[0], "NoModify"), Some("1"));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse_uninstall_export("[HKEY_LOCAL_MACHINE\\SOFTWARE]\n").is_err());
+        assert!(!is_export("{\"SPApplicationsDataType\": []}"));
+        assert!(is_export("\r\nREGEDIT4\r\n"));
+    }
+
+    #[test]
+    fn skips_malformed_values() {
+        assert_eq!(parse_value("\"Size\"=dword:zz"), None);
+        assert_eq!(parse_value("\"Icon\"=hex(2):2g,00"), None);
+        assert_eq!(parse_value("\"Unterminated=\"value\""), None);
+        assert_eq!(parse_value("\"Name\"=unknown:00"), None);
+        assert_eq!(
+            parse_value("\"Path\" = \"C:\\\\App\""),
+            Some(("Path".to_owned(), "C:\\App".to_owned()))
+        );
+    }
+}